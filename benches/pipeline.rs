@@ -0,0 +1,48 @@
+//! The whole line pipeline over lines nothing applies to (which flow through
+//! borrowed) and over lines every stage modifies, plus the legacy auto-detector
+//! over lines none of its patterns match
+//!
+//! Run with `cargo bench --bench pipeline`.
+
+use std::io::Cursor;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jynx::std::auto_detect::AutoDetector;
+use jynx::std::theme::Theme;
+use jynx::std::IconMode;
+use jynx::std::JynxApp;
+use jynx::text_styles::Ansi;
+
+const THEME: &str = r#"
+metadata: { name: "bench", version: "1.0.0", description: "pipeline bench" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR", "FATAL"], color: "crimson" }
+      warnings: { keywords: ["WARN"], color: "amber" }
+"#;
+
+fn pipeline(c: &mut Criterion) {
+    let app = JynxApp::with_theme(Some(Theme::from_yaml_str(THEME).unwrap()), Some("logs".to_string()), None, "left".to_string());
+    let noop = "request served without incident in the usual amount of time\n".repeat(1000);
+    let modified = "ERROR %c:red(failed) connecting to https://example.com v1.2.3\n".repeat(1000);
+    
+    c.bench_function("pipeline_1000_noop_lines", |b| {
+        b.iter(|| app.highlight_stream(Cursor::new(black_box(noop.as_bytes()))).map(|line| line.unwrap().len()).sum::<usize>())
+    });
+    c.bench_function("pipeline_1000_modified_lines", |b| {
+        b.iter(|| app.highlight_stream(Cursor::new(black_box(modified.as_bytes()))).map(|line| line.unwrap().len()).sum::<usize>())
+    });
+}
+
+fn auto_detect(c: &mut Criterion) {
+    let detector = AutoDetector::with_icon_mode(IconMode::None).unwrap();
+    let lines: Vec<String> = (0..1000).map(|i| format!("worker {} finished its batch without trouble", i)).collect();
+    
+    c.bench_function("auto_detect_1000_unmatched_lines", |b| {
+        b.iter(|| lines.iter().map(|line| detector.highlight_line(black_box(line), &Ansi::default()).len()).sum::<usize>())
+    });
+}
+
+criterion_group!(benches, pipeline, auto_detect);
+criterion_main!(benches);
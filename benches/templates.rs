@@ -0,0 +1,38 @@
+//! Template expansion over a line dense with `%c:` color lookups, and over
+//! long lines with and without templates
+//!
+//! Run with `cargo bench --bench templates`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jynx::extended_colors::get_extended_color_code;
+use jynx::template_parser::ColorTemplateParser;
+
+fn many_templates(c: &mut Criterion) {
+    let colors = ["crimson", "amber", "emerald", "azure", "violet", "slate", "pastel_orange", "dim_cyan"];
+    let line: String = (0..64)
+        .map(|i| format!("%c:{}(item{}) ", colors[i % colors.len()], i))
+        .collect();
+    let parser = ColorTemplateParser::new(false);
+    
+    c.bench_function("template_line_64_spans", |b| b.iter(|| parser.process(black_box(&line))));
+    c.bench_function("color_lookup", |b| {
+        b.iter(|| colors.iter().map(|name| get_extended_color_code(black_box(name)).len()).sum::<usize>())
+    });
+}
+
+fn long_lines(c: &mut Criterion) {
+    // ~16KB of mixed ASCII and multi-byte text; the plain line has no `%c:` at all
+    let plain = "request served in 12ms · ünïcödé payload 漢字 ok ".repeat(320);
+    let templated = format!("{}%c:crimson(ERROR){}", plain, plain);
+    let parser = ColorTemplateParser::new(false);
+    
+    c.bench_function("template_long_line_plain", |b| b.iter(|| parser.process(black_box(&plain))));
+    c.bench_function("template_long_line_one_span", |b| b.iter(|| parser.process(black_box(&templated))));
+    
+    // The common case: a short log line with no templates comes back borrowed
+    let short = "2024-05-01 12:00:00 INFO request served in 12ms (100% cache hits)";
+    c.bench_function("template_short_line_plain", |b| b.iter(|| parser.process(black_box(short)).len()));
+}
+
+criterion_group!(benches, many_templates, long_lines);
+criterion_main!(benches);
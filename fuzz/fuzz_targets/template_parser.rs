@@ -0,0 +1,17 @@
+//! `cargo +nightly fuzz run template_parser`
+//!
+//! Arbitrary UTF-8 through the template parser in both modes: neither may
+//! panic, and stripping templates may only ever shrink the text.
+#![no_main]
+
+use jynx::template_parser::ColorTemplateParser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = ColorTemplateParser::new(false).process(text);
+    let stripped = ColorTemplateParser::new(true).strip_templates(text);
+    assert!(stripped.len() <= text.len(), "{:?} stripped to {:?}", text, stripped);
+});
@@ -5,39 +5,298 @@
 //
 
 use jynx::std::*;
-use jynx::std::theme::Theme;
+use jynx::std::theme::{Appearance, Theme, MIN_CONTRAST_RATIO};
+use jynx::std::compiled::CompiledTheme;
+use jynx::extended_colors::{COLOR_NAMES, find_nearest_color, get_color_categories, get_extended_color_code, parse_hex_color};
+use jynx::text_styles::{Ansi, Html, Pango, Renderer};
 use clap::{Parser, Subcommand};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::io::Write;
 use std::process;
+use std::sync::Arc;
 
 #[derive(Parser)]
 #[command(name = "jynx")]
 #[command(about = "Intelligent syntax highlighter with auto-detection and theme management")]
 #[command(version)]
 struct Cli {
-    /// Theme name or path to load
+    /// Theme name, path or http(s) URL (cached; needs `remote-themes`) to load (repeatable: later themes are merged over earlier ones)
     #[arg(short, long)]
-    theme: Option<String>,
+    theme: Vec<String>,
+    
+    /// Extra directory to search for themes (repeatable; searched before $JYNX_THEME_PATH)
+    #[arg(long, value_name = "DIR", global = true)]
+    theme_dir: Vec<std::path::PathBuf>,
+    
+    /// Directory for compiled theme cache (default: $JYNX_CACHE_DIR or ~/.cache/rsb/jynx)
+    #[arg(long, value_name = "DIR", global = true)]
+    cache_dir: Option<std::path::PathBuf>,
+    
+    /// Ignore the compiled theme cache and rebuild it
+    #[arg(long)]
+    recompile: bool,
+    
+    /// Download `--theme <URL>` themes again instead of using their cached copy
+    #[arg(long)]
+    theme_refresh: bool,
+    
+    /// Inline theme YAML (instead of a theme file)
+    #[arg(long, value_name = "YAML", conflicts_with_all = ["theme", "theme_fd"])]
+    theme_string: Option<String>,
+    
+    /// Read theme YAML from an already-open file descriptor (e.g. `3< theme.yml`)
+    #[arg(long, value_name = "N", conflicts_with = "theme")]
+    theme_fd: Option<i32>,
+    
+    /// Fill `{{NAME}}` placeholders in theme values (repeatable; overrides the theme's `vars:`)
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_theme_var, global = true)]
+    theme_var: Vec<(String, String)>,
+    
+    /// Pick an installed theme by terminal background: auto, light, dark
+    #[arg(long)]
+    appearance: Option<String>,
     
     /// Filter to apply from theme
     #[arg(short, long)]
     filter: Option<String>,
     
-    /// Fixed width for output formatting
-    #[arg(short, long)]
-    width: Option<usize>,
+    /// Width for output formatting: columns, a percentage of the terminal (`80%`), or `auto`
+    #[arg(short, long, value_parser = WidthSpec::parse)]
+    width: Option<WidthSpec>,
     
     /// Text alignment: left, center, right
     #[arg(short, long, default_value = "left")]
     align: String,
     
+    /// Which side of lines wider than --width is cut: end, start (keep the tail), middle
+    #[arg(long, value_name = "SIDE", default_value = "end", value_parser = ["end", "start", "middle"])]
+    truncate: String,
+    
+    /// Text marking where --width cut a line (default `...`; style it with theme metadata.ellipsis_style)
+    #[arg(long, value_name = "TEXT")]
+    ellipsis: Option<String>,
+    
     /// Enable debug output
     #[arg(short, long)]
     debug: bool,
     
+    /// Fail instead of warning when a theme needs a newer jynx (metadata.min_jynx_version)
+    #[arg(long)]
+    strict: bool,
+    
     /// Disable colorization (passthrough mode)
     #[arg(long)]
     no_color: bool,
     
+    /// Icon rendering: auto, unicode, ascii (theme `icon_fallback` text), none
+    #[arg(long, value_parser = ["auto", "unicode", "ascii", "none"])]
+    icons: Option<String>,
+    
+    /// Disable auto-detection entirely (built-in and theme patterns); only theme keywords apply
+    #[arg(long, conflicts_with = "detect")]
+    no_detect: bool,
+    
+    /// Enable opt-in detectors (comma-separated): keyval, numbers, strings
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', value_parser = ["keyval", "numbers", "strings"])]
+    detect: Vec<String>,
+    
+    /// Highlight an ad-hoc regex for this run (repeatable; colors cycle per pattern)
+    #[arg(short = 'm', long = "match", value_name = "REGEX", value_parser = parse_match_pattern)]
+    matches: Vec<String>,
+    
+    /// Color for all --match patterns (see `jynx colors`)
+    #[arg(long, value_name = "COLOR", requires = "matches", hide_possible_values = true, value_parser = clap::builder::PossibleValuesParser::new(COLOR_NAMES))]
+    match_color: Option<String>,
+    
+    /// Color each distinct match of a regex by hashing it, so the same ID is always the same color (repeatable)
+    #[arg(long, value_name = "REGEX", value_parser = parse_match_pattern)]
+    hash_color: Vec<String>,
+    
+    /// Link file:line references (compiler output) to the file via OSC 8 hyperlinks
+    #[arg(long)]
+    hyperlinks: bool,
+    
+    /// Unicode icons: on, off (text fallbacks), auto (JYNX_UNICODE, then locale)
+    #[arg(long, value_parser = ["on", "off", "auto"])]
+    unicode: Option<String>,
+    
+    /// Suppress icons: `:word:` and auto-detected values are colored only (same as `--icons none`)
+    #[arg(long, conflicts_with = "icons")]
+    no_icons: bool,
+    
+    /// Header line to print before the output (runs through the pipeline)
+    #[arg(long, value_name = "TEXT")]
+    header: Option<String>,
+    
+    /// Print the header again every N output lines
+    #[arg(long, value_name = "N", requires = "header")]
+    repeat_every: Option<std::num::NonZeroUsize>,
+    
+    /// Pass a line through unstyled if keyword styling would grow it past N times its length (0 = no cap)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_EXPAND)]
+    max_expand: usize,
+    
+    /// Leave already styled text alone, so jynx output piped through jynx again comes out unchanged
+    #[arg(long)]
+    idempotent: bool,
+    
+    /// Only highlight visible columns START:END of each line (`%c:` templates still apply everywhere)
+    #[arg(long, value_name = "START:END", value_parser = ColumnRange::parse)]
+    column_range: Option<ColumnRange>,
+    
+    /// Split lines on --delimiter and align each field: `WIDTH:ALIGN,...` (width 0 = natural)
+    #[arg(long, value_name = "SPECS", value_parser = ColumnSpec::parse_list, conflicts_with = "column_range")]
+    columns: Option<ColumnSpecs>,
+    
+    /// Field delimiter for --columns (`\t` is a tab)
+    #[arg(long, value_name = "TEXT", default_value = "\\t", requires = "columns")]
+    delimiter: String,
+    
+    /// Honor a `#jynx: theme=<name> filter=<name>` directive on the first input line (the line is not emitted)
+    #[arg(long)]
+    auto_filter: bool,
+    
+    /// Pipe each line through a long-running shell command before highlighting
+    /// (it must print one line per input line and flush, e.g. `sed -u`)
+    #[arg(long, value_name = "CMD")]
+    exec_filter: Option<String>,
+    
+    /// Merge adjacent same-style spans (drop reset + identical reopen) for smaller output
+    #[arg(long)]
+    coalesce: bool,
+    
+    /// Color ()[]{} by nesting depth (theme metadata.bracket_colors); unpaired brackets stand out
+    #[arg(long)]
+    rainbow_brackets: bool,
+    
+    /// Indent JSON object/array lines and color them by value type (theme `json` filter groups or built-in colors)
+    #[arg(long)]
+    json_pretty: bool,
+    
+    /// Render markdown emphasis: **bold**, *italic* / _italic_ and `code` (theme `markdown` filter `code` group), markers stripped
+    #[arg(long)]
+    markdown: bool,
+    
+    /// Start every line with an SGR reset (`both`: also end it with one) against styling leaked by earlier programs
+    #[arg(long, value_name = "WHERE", num_args = 0..=1, default_missing_value = "start", value_parser = ["start", "both"])]
+    reset_lines: Option<String>,
+    
+    /// Print the time spent per pipeline stage (templates, detection, icons, keywords, width) to stderr at EOF
+    #[arg(long)]
+    profile: bool,
+    
+    /// Write highlighted output to a file instead of stdout (colors are kept; `-` is stdout)
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+    
+    /// Output markup: `ansi` (default) escapes, `html` spans or `pango` markup; html to an --output file is a complete page
+    #[arg(long, value_name = "FORMAT", default_value = "ansi", value_parser = ["ansi", "html", "pango"])]
+    format: String,
+    
+    /// Collapse runs of identical lines into one with a dim `(xN)` count, like `uniq -c`
+    #[arg(long)]
+    squeeze: bool,
+    
+    /// Print only a table of lines/matches per style group of --filter (no highlighted output)
+    #[arg(long)]
+    count_only: bool,
+    
+    /// Ring the terminal bell on lines matching these style groups of --filter (comma-separated)
+    #[arg(long, value_name = "GROUPS", value_delimiter = ',')]
+    bell_on: Vec<String>,
+    
+    /// Prefix lines with a one-character severity column (E/W/I...) from metadata.summary_groups
+    #[arg(long)]
+    gutter: bool,
+    
+    /// Print a footer with summary-group keyword and auto-detection counts at EOF
+    #[arg(long)]
+    summary: bool,
+    
+    /// Like --summary, but write the footer to stderr
+    #[arg(long, conflicts_with = "summary")]
+    summary_stderr: bool,
+    
+    /// Regex substitution on each colored line, `PATTERN=>REPLACEMENT` with `$1` captures
+    /// (repeatable, applied in order before --trim/--width; `$$` is a literal `$`)
+    #[arg(long = "replace", value_name = "PATTERN=>REPLACEMENT", value_parser = Replacement::parse)]
+    replacements: Vec<Replacement>,
+    
+    /// Lines starting with this marker are stderr: the marker is dropped and the
+    /// line styled with theme metadata.stderr_style (e.g. `cmd 2> >(sed 's/^/!! /')`)
+    #[arg(long, value_name = "MARKER")]
+    stderr_prefix: Option<String>,
+    
+    /// Show every occurrence of TEXT in reverse video, like a search in `less`
+    #[arg(long, value_name = "TEXT", value_parser = parse_search_text, conflicts_with = "search_regex")]
+    search: Option<regex::Regex>,
+    
+    /// Like --search, with a regex instead of literal text
+    #[arg(long, value_name = "REGEX", value_parser = parse_search_regex)]
+    search_regex: Option<regex::Regex>,
+    
+    /// Strip trailing whitespace from each line before --width/--align
+    #[arg(long)]
+    trim: bool,
+    
+    /// Prefix each line with its right-aligned input line number (like `cat -n`)
+    #[arg(short, long)]
+    number: bool,
+    
+    /// Color for line numbers (default: theme metadata.line_number_color, else plain)
+    #[arg(long, value_name = "COLOR", requires = "number")]
+    number_color: Option<String>,
+    
+    /// Minimum digits for the line number field
+    #[arg(long, value_name = "N", default_value_t = 6, requires = "number")]
+    number_width: usize,
+    
+    /// Count the line number as part of the --width column instead of adding it in front
+    #[arg(long, requires = "number")]
+    number_inside: bool,
+    
+    /// Let `%c:color(...)` templates span lines (buffers the whole input before output)
+    #[arg(long)]
+    multiline_templates: bool,
+    
+    /// Without --theme and --filter: `auto` runs auto-detection, `plain` only expands %c: templates
+    #[arg(long, value_name = "MODE", default_value = "auto", value_parser = ["auto", "plain"])]
+    default_mode: String,
+    
+    /// Color depth named colors resolve to: `16` (30-37/90-97 only), `256` (default) or `truecolor`
+    #[arg(long, value_name = "DEPTH", default_value = "256", value_parser = ["16", "256", "truecolor"])]
+    palette: String,
+    
+    /// How `bold` renders: `weight` (default, \x1b[1m) or `bright` (standard colors turn bright instead)
+    #[arg(long, value_name = "MODE", default_value = "weight", value_parser = ["weight", "bright"])]
+    bold_mode: String,
+    
+    /// When to flush output: `line` (default, for tail -f) or `eof` (bulk throughput)
+    #[arg(long, value_name = "MODE", default_value = "line", value_parser = ["line", "eof"])]
+    flush: String,
+    
+    /// Output buffer size in bytes (pair a large buffer with `--flush eof` for bulk work)
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u64).range(1..))]
+    buffer_size: Option<u64>,
+    
+    /// Only emit input lines START:END (1-based, END optional); other lines are suppressed
+    #[arg(long, value_name = "START:END", value_parser = LineRange::parse)]
+    lines: Option<LineRange>,
+    
+    /// Theme per input file by glob on its name, e.g. '*.rs:rust,*.log:logs' (others use --theme)
+    #[arg(long, value_name = "GLOB:THEME,...", value_parser = ThemeMap::parse)]
+    theme_map: Option<ThemeMap>,
+    
+    /// Decompress input as gzip even when it isn't named `*.gz` (e.g. `zcat`-free stdin)
+    #[cfg(feature = "gzip")]
+    #[arg(long)]
+    gzip: bool,
+    
+    /// Input files, highlighted in order (`-` or none: stdin; `*.gz` files are decompressed)
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+    
     /// Commands
     #[command(subcommand)]
     command: Option<Commands>,
@@ -50,28 +309,123 @@ enum Commands {
         #[command(subcommand)]
         action: ThemeAction,
     },
+    /// Show the named color palette
+    Colors {
+        /// Find the palette color nearest to a hex value
+        #[arg(long, value_name = "#RRGGBB")]
+        find: Option<String>,
+    },
+    /// Print a sample of everything jynx can render (a terminal smoke test)
+    Demo,
+    /// Highlight lines sent to a Unix socket, loading and compiling the theme only once
+    #[cfg(all(feature = "daemon", unix))]
+    Serve {
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+    },
+    /// Highlight TEXT (or stdin) through a `jynx serve` daemon
+    #[cfg(all(feature = "daemon", unix))]
+    Send {
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+        /// Lines to send; stdin when omitted
+        #[arg(value_name = "TEXT")]
+        text: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
 enum ThemeAction {
     /// List available themes
-    List,
+    List {
+        /// Only show themes designed for this background: light, dark
+        #[arg(long)]
+        appearance: Option<String>,
+    },
     /// Create new theme in current directory
-    Create { name: String },
+    Create {
+        name: String,
+        /// Overwrite the target file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
     /// Import theme from current directory to XDG+
-    Import { name: String },
+    Import {
+        name: String,
+        /// Overwrite the target file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Install every theme_*.yml of a theme pack (directory, .tar.gz, or URL) into XDG+
+    Install {
+        /// Directory, `.tar.gz` file, or http(s) URL (with the `remote-themes` feature)
+        source: String,
+        /// Replace themes that are already installed
+        #[arg(long)]
+        force: bool,
+    },
     /// Export theme from XDG+ to current directory
-    Export { name: String },
-    /// Edit theme in $EDITOR
+    Export {
+        name: String,
+        /// Overwrite the target file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Edit theme in $VISUAL / $EDITOR (falls back to nano, vi, vim)
     Edit { name: String },
+    /// List a theme's filters with style group and icon mapping counts
+    Filters {
+        name: String,
+        /// Also list the keywords in each style group
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Print the file a theme name resolves to (nothing and exit 1 if not found)
+    Which { name: String },
+    /// Compile a theme into the cache (with --report: time it and count its patterns instead)
+    Compile {
+        name: String,
+        /// Dry run: print pattern counts and compile time, leave the cache alone
+        #[arg(long)]
+        report: bool,
+    },
+    /// Show compile metadata: checksum, timestamp, pattern counts, cache status
+    Info { name: String },
+    /// Check style groups with a background color for WCAG contrast (4.5:1)
+    Audit {
+        name: String,
+        /// Exit nonzero if any style group fails
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Rewrite theme file in canonical form
+    Format {
+        name: String,
+        /// Merge defaults into filters before writing
+        #[arg(long)]
+        resolve: bool,
+    },
+    /// Print JSON Schema for theme files (for editor autocompletion)
+    Schema,
+    /// Delete all cached compiled themes
+    ClearCache,
 }
 
 fn main() {
     let cli = Cli::parse();
     
+    // Windows consoles render ANSI only once asked to; one that refuses gets plain output
+    let no_color = cli.no_color || !jynx::std::console::enable_ansi();
+    let palette = jynx::extended_colors::Palette::from_arg(&cli.palette).unwrap_or_default();
+    let bold_mode = jynx::extended_colors::BoldMode::from_arg(&cli.bold_mode).unwrap_or_default();
+    // Also process-wide, for the subcommands that print colors outside a JynxApp
+    jynx::extended_colors::set_palette(palette);
+    jynx::extended_colors::set_bold_mode(bold_mode);
+    
     // Handle subcommands
     if let Some(Commands::Theme { action }) = &cli.command {
-        match handle_theme_command(action) {
+        let search_path = Theme::theme_search_path_with(&cli.theme_dir);
+        match handle_theme_command(action, &ThemeCache::new(cli.cache_dir.clone()), &search_path, &theme_vars(&cli)) {
             Ok(()) => return,
             Err(e) => {
                 eprintln!("Theme command error: {}", e);
@@ -80,16 +434,55 @@ fn main() {
         }
     }
     
-    // Load theme using smart resolution
-    let theme = match Theme::load_theme(cli.theme.as_deref()) {
-        Ok(theme) => {
+    if let Some(Commands::Colors { find }) = &cli.command {
+        if let Err(e) = handle_colors_command(find.as_deref()) {
+            eprintln!("Colors command error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    if let Some(Commands::Demo) = &cli.command {
+        let icon_mode = if cli.no_icons {
+            IconMode::None
+        } else {
+            cli.icons.as_deref().and_then(IconMode::from_arg).unwrap_or_else(IconMode::detect)
+        };
+        if let Err(e) = jynx::std::demo::write_demo(std::io::stdout().lock(), icon_mode) {
+            eprintln!("Demo error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    #[cfg(all(feature = "daemon", unix))]
+    if let Some(Commands::Send { socket, text }) = &cli.command {
+        let stdout = std::io::stdout().lock();
+        let result = match text.is_empty() {
+            true => jynx::std::daemon::send(socket, std::io::stdin(), stdout),
+            false => jynx::std::daemon::send(socket, format!("{}\n", text.join("\n")).as_bytes(), stdout),
+        };
+        if let Err(e) = result {
+            eprintln!("jynx send: {}: {}", socket.display(), e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    let theme = match load_theme(&cli) {
+        Ok(None) => None,
+        Ok(Some((theme, paths))) => {
             if cli.debug {
+                for (name, path) in &paths {
+                    let resolved = path.canonicalize().unwrap_or_else(|_| path.clone());
+                    eprintln!("Resolved theme '{}' -> {}", name, resolved.display());
+                }
                 eprintln!("Loaded theme: {} v{}", theme.metadata.name, theme.metadata.version);
             }
             Some(theme)
         },
         Err(e) => {
-            if cli.theme.is_some() {
+            if !cli.theme.is_empty() || cli.theme_string.is_some() || cli.theme_fd.is_some() {
                 eprintln!("Warning: {}", e);
                 eprintln!("Falling back to auto-detection only");
             }
@@ -97,19 +490,249 @@ fn main() {
         }
     };
     
-    let app = JynxApp::with_theme_and_options(theme, cli.filter, cli.width, cli.align, cli.no_color);
+    let cache = ThemeCache::new(cli.cache_dir).with_recompile(cli.recompile);
+    let width = cli.width.map(|width| width.resolve(terminal_width()));
+    let renderer: Arc<dyn Renderer> = match cli.format.as_str() {
+        "html" => Arc::new(Html),
+        "pango" => Arc::new(Pango),
+        _ => Arc::new(Ansi::new(palette, bold_mode)),
+    };
+    let mut app = JynxApp::with_theme_cached(theme, cli.filter, width, cli.align, no_color, &cache)
+        .with_renderer(renderer)
+        .with_line_range(cli.lines)
+        .with_auto_filter(cli.auto_filter)
+        .with_theme_dirs(cli.theme_dir)
+        .with_theme_vars(cli.theme_var.into_iter().collect())
+        .with_column_range(cli.column_range)
+        .with_columns(cli.columns, cli.delimiter.replace("\\t", "\t"))
+        .with_header(cli.header, cli.repeat_every.map(|n| n.get()))
+        .with_extra_detectors(cli.detect)
+        .with_auto_detection(!cli.no_detect)
+        .with_hyperlinks(cli.hyperlinks)
+        .with_match_patterns(cli.matches, cli.match_color)
+        .with_hash_patterns(cli.hash_color)
+        .with_max_expand(cli.max_expand)
+        .with_idempotent(cli.idempotent)
+        .with_exec_filter(cli.exec_filter)
+        .with_replacements(cli.replacements)
+        .with_search(cli.search.or(cli.search_regex))
+        .with_stderr_prefix(cli.stderr_prefix)
+        .with_trim(cli.trim)
+        .with_truncate(TruncateSide::from_arg(&cli.truncate).unwrap_or_default())
+        .with_ellipsis(cli.ellipsis)
+        .with_bell_on(cli.bell_on)
+        .with_line_numbers(cli.number.then_some(LineNumbers {
+            width: cli.number_width,
+            color: cli.number_color,
+            inside: cli.number_inside,
+        }))
+        .with_squeeze(cli.squeeze)
+        .with_profile(cli.profile)
+        .with_json_pretty(cli.json_pretty)
+        .with_markdown(cli.markdown)
+        .with_rainbow_brackets(cli.rainbow_brackets)
+        .with_coalesce(cli.coalesce)
+        .with_gutter(cli.gutter)
+        .with_reset_lines(cli.reset_lines.as_deref().and_then(ResetLines::from_arg))
+        .with_count_only(cli.count_only)
+        .with_summary(if cli.summary_stderr {
+            Some(SummaryOutput::Stderr)
+        } else {
+            cli.summary.then_some(SummaryOutput::Stdout)
+        })
+        .with_multiline_templates(cli.multiline_templates)
+        .with_default_mode(DefaultMode::from_arg(&cli.default_mode).unwrap_or_default())
+        .with_flush_mode(FlushMode::from_arg(&cli.flush).unwrap_or_default())
+        .with_buffer_size(cli.buffer_size.map(|n| n as usize));
+    #[cfg(feature = "gzip")]
+    let gzip = cli.gzip;
+    #[cfg(not(feature = "gzip"))]
+    let gzip = false;
+    #[cfg(feature = "gzip")]
+    {
+        app = app.with_gzip(gzip);
+    }
+    if cli.no_icons {
+        app = app.with_icon_mode(IconMode::None);
+    } else if let Some(icon_mode) = cli.icons.as_deref().and_then(IconMode::from_arg) {
+        app = app.with_icon_mode(icon_mode);
+    } else if let Some(icon_mode) = cli.unicode.as_deref().and_then(IconMode::from_unicode_arg) {
+        app = app.with_icon_mode(icon_mode);
+    }
+    
+    #[cfg(all(feature = "daemon", unix))]
+    if let Some(Commands::Serve { socket }) = &cli.command {
+        let result = jynx::std::daemon::Daemon::bind(socket).and_then(|daemon| daemon.serve(&app));
+        if let Err(e) = result {
+            eprintln!("jynx serve: {}: {}", socket.display(), e);
+            process::exit(1);
+        }
+        return;
+    }
     
     // Graceful error handling - if anything fails, we become 'cat'
-    if let Err(e) = app.run() {
+    let mut output = match JynxApp::open_output(cli.output.as_deref()) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("jynx: {}: {}", cli.output.unwrap_or_default().display(), e);
+            process::exit(1);
+        }
+    };
+    let document = cli.format == "html" && cli.output.as_deref().is_some_and(|path| path != Path::new("-"));
+    let stdin = cli.files.is_empty() && cli.theme_map.is_none() && !gzip;
+    let files = if cli.files.is_empty() { vec![PathBuf::from("-")] } else { cli.files };
+    let run = |output: &mut dyn Write| if stdin {
+        app.run_io(std::io::stdin().lock(), output)
+    } else {
+        app.run_files(&files, cli.theme_map.as_ref(), output)
+    };
+    let result = if document {
+        HtmlDocument::new(&mut output).map_err(JynxError::from).and_then(|mut document| {
+            run(&mut document)?;
+            document.finish()?;
+            Ok(())
+        })
+    } else {
+        run(&mut output)
+    };
+    if let Some(report) = app.profile_report() {
+        eprintln!("{}", report);
+    }
+    if let Err(e) = result {
         eprintln!("jynx error: {}", e);
         process::exit(1);
     }
 }
 
-fn handle_theme_command(action: &ThemeAction) -> Result<(), Box<dyn std::error::Error>> {
+/// Columns of the terminal: `$COLUMNS`, else the size of whichever of
+/// stdout/stderr/stdin is a TTY (stdout is often a pipe)
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).filter(|&c: &usize| c > 0) {
+        return Some(columns);
+    }
+    #[cfg(unix)]
+    for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO, libc::STDIN_FILENO] {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // SAFETY: TIOCGWINSZ only writes a `winsize` into the struct we pass
+        if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    None
+}
+
+/// Reject invalid `--match`/`--hash-color` regexes up front instead of losing auto-detection
+fn parse_match_pattern(pattern: &str) -> Result<String, String> {
+    regex::Regex::new(pattern).map(|_| pattern.to_string()).map_err(|e| e.to_string())
+}
+
+/// `--theme-var NAME=VALUE`
+fn parse_theme_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{}'", arg)),
+    }
+}
+
+/// `--search` text, matched literally
+fn parse_search_text(text: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(&regex::escape(text)).map_err(|e| e.to_string())
+}
+
+fn parse_search_regex(pattern: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(pattern).map_err(|e| e.to_string())
+}
+
+/// One `--columns` value (an alias so clap parses the list as a single value)
+type ColumnSpecs = Vec<ColumnSpec>;
+
+/// Theme names paired with the files they resolved to
+type ResolvedThemes = Vec<(String, PathBuf)>;
+
+/// Load the theme from inline YAML, a file descriptor, appearance or name resolution,
+/// along with the files it was resolved to (for `--debug`); none under
+/// `--default-mode plain` when no theme or filter was asked for
+fn load_theme(cli: &Cli) -> Result<Option<(Theme, ResolvedThemes)>, Box<dyn std::error::Error>> {
+    // --theme-dir entries are searched before $JYNX_THEME_PATH by every lookup below
+    let search_path = Theme::theme_search_path_with(&cli.theme_dir);
+    let vars = theme_vars(cli);
+    let cache = ThemeCache::new(cli.cache_dir.clone());
+    
+    if let Some(yaml) = &cli.theme_string {
+        return Ok(Some((Theme::from_yaml_str_with_vars(yaml, &vars)?.enforce_min_version(cli.strict)?, Vec::new())));
+    }
+    
+    if let Some(fd) = cli.theme_fd {
+        return Ok(Some((Theme::load_from_fd(fd, &vars)?.enforce_min_version(cli.strict)?, Vec::new())));
+    }
+    
+    // Multiple themes: load each and merge in order (later wins)
+    if let Some((first, rest)) = cli.theme.split_first() {
+        let (mut theme, path) = Theme::load_theme_with_path_in(Some(&theme_source(first, &cache.dir, cli.theme_refresh)?), cli.strict, &search_path, &vars)?;
+        let mut paths: ResolvedThemes = path.map(|p| (first.clone(), p)).into_iter().collect();
+        for name in rest {
+            let (overlay, path) = Theme::load_theme_with_path_in(Some(&theme_source(name, &cache.dir, cli.theme_refresh)?), cli.strict, &search_path, &vars)?;
+            theme.merge(overlay);
+            paths.extend(path.map(|p| (name.clone(), p)));
+        }
+        return Ok(Some((theme, paths)));
+    }
+    
+    // Appearance selection only applies when no explicit theme is given;
+    // if it can't be determined we fall through to the configured default
+    if let Some(value) = &cli.appearance {
+        if let Some(path) = Appearance::from_arg(value).and_then(|appearance| Theme::find_theme_for_appearance_in(appearance, &search_path)) {
+            return Ok(Some((Theme::load_from_file_with_vars(&path, cli.strict, &vars)?, vec![(value.clone(), path)])));
+        }
+    }
+    
+    // The configured or embedded default would otherwise always give plain mode a theme
+    if cli.filter.is_none() && DefaultMode::from_arg(&cli.default_mode) == Some(DefaultMode::Plain) {
+        return Ok(None);
+    }
+    
+    // Load theme using smart resolution
+    let (theme, path) = Theme::load_theme_with_path_in(None, cli.strict, &search_path, &vars)?;
+    Ok(Some((theme, path.map(|p| (theme_label(&p), p)).into_iter().collect())))
+}
+
+/// `--theme-var` overrides for `{{name}}` placeholders in every theme loaded
+fn theme_vars(cli: &Cli) -> BTreeMap<String, String> {
+    cli.theme_var.iter().cloned().collect()
+}
+
+/// A theme name to resolve: URLs become their cached copy in `cache_dir` (`--cache-dir`),
+/// downloaded when missing or on `refresh` (`--theme-refresh`); anything else is passed through
+fn theme_source(name: &str, cache_dir: &Path, refresh: bool) -> Result<String, Box<dyn std::error::Error>> {
+    #[cfg(feature = "remote-themes")]
+    if Theme::is_theme_url(name) {
+        return Ok(Theme::fetch_remote_theme(name, cache_dir, refresh)?.to_string_lossy().into_owned());
+    }
+    #[cfg(not(feature = "remote-themes"))]
+    let _ = (cache_dir, refresh);
+    Ok(name.to_string())
+}
+
+/// Theme name as a user would type it: `theme_rust.yml` -> `rust`
+fn theme_label(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    stem.strip_prefix("theme_").map(str::to_string).unwrap_or(stem)
+}
+
+fn handle_theme_command(action: &ThemeAction, cache: &ThemeCache, search_path: &[(PathBuf, String)],
+                        vars: &BTreeMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+    let source = |name: &str| theme_source(name, &cache.dir, false);
+    let load = |name: &str| Theme::load_theme_with_path_in(Some(&source(name)?), false, search_path, vars).map(|(theme, _)| theme);
     match action {
-        ThemeAction::List => {
-            let themes = Theme::list_themes()?;
+        ThemeAction::List { appearance } => {
+            let themes = match appearance {
+                Some(value) => {
+                    let appearance = Appearance::from_arg(value)
+                        .ok_or_else(|| format!("Cannot determine appearance from '{}'", value))?;
+                    Theme::list_themes_for_appearance_in(appearance, search_path)?
+                },
+                None => Theme::list_themes_in(search_path)?,
+            };
             if themes.is_empty() {
                 println!("No themes found");
             } else {
@@ -119,22 +742,139 @@ fn handle_theme_command(action: &ThemeAction) -> Result<(), Box<dyn std::error::
                 }
             }
         },
-        ThemeAction::Create { name } => {
-            let path = Theme::create_theme(name)?;
+        ThemeAction::Create { name, force } => {
+            let path = Theme::create_theme(name, *force)?;
             println!("Created theme '{}' at {}", name, path.display());
         },
-        ThemeAction::Import { name } => {
-            let path = Theme::import_theme(name)?;
+        ThemeAction::Import { name, force } => {
+            let path = Theme::import_theme(name, *force)?;
             println!("Imported theme '{}' to {}", name, path.display());
         },
-        ThemeAction::Export { name } => {
-            let path = Theme::export_theme(name)?;
+        ThemeAction::Install { source, force } => {
+            let report = Theme::install_pack(source, *force)?;
+            for (name, path) in &report.installed {
+                println!("Installed theme '{}' to {}", name, path.display());
+            }
+            for (file, reason) in &report.skipped {
+                println!("Skipped {}: {}", file, reason);
+            }
+        },
+        ThemeAction::Export { name, force } => {
+            let path = Theme::export_theme(name, *force)?;
             println!("Exported theme '{}' to {}", name, path.display());
         },
         ThemeAction::Edit { name } => {
-            Theme::edit_theme(name)?;
+            Theme::edit_theme_in(name, search_path)?;
             println!("Edited theme '{}'", name);
         },
+        ThemeAction::Filters { name, verbose } => {
+            let theme = load(name)?;
+            if theme.filters.is_empty() {
+                println!("Theme '{}' defines no filters", name);
+            } else {
+                print!("{}", theme.describe_filters(*verbose));
+            }
+        },
+        ThemeAction::Which { name } => {
+            match Theme::resolve_theme_path_in(&source(name)?, search_path) {
+                Some(path) => println!("{}", path.canonicalize().unwrap_or(path).display()),
+                None => process::exit(1),
+            }
+        },
+        ThemeAction::Compile { name, report } => {
+            let theme = load(name)?;
+            if *report {
+                println!("Theme: {} v{}", theme.metadata.name, theme.metadata.version);
+                print!("{}", CompiledTheme::compile_report(&theme)?);
+            } else {
+                let mut compiled = CompiledTheme::from_theme(&theme)?;
+                compiled.init_runtime()?;
+                let path = compiled.save_cache(&theme, &cache.dir)?;
+                println!("Compiled theme '{}' to {}", name, path.display());
+            }
+        },
+        ThemeAction::Info { name } => {
+            let theme = load(name)?;
+            let mut compiled = CompiledTheme::from_theme(&theme)?;
+            compiled.init_runtime()?;
+            println!("Theme: {} v{}", theme.metadata.name, theme.metadata.version);
+            print!("{}", compiled.describe());
+            let cache_path = CompiledTheme::cache_path(&theme, &cache.dir);
+            let status = if CompiledTheme::load_cached(&theme, &cache.dir).is_some() { "hit" } else { "miss" };
+            println!("cache: {} ({})", cache_path.display(), status);
+        },
+        ThemeAction::Audit { name, strict } => {
+            let theme = load(name)?;
+            let report = theme.contrast_report();
+            if report.is_empty() {
+                println!("Theme '{}' has no style groups with both color and bg", name);
+            }
+            for check in &report {
+                println!("  {}/{}: {} on {} {:.2}:1 {}",
+                    check.filter, check.group, check.color, check.bg, check.ratio,
+                    if check.passes() { "ok" } else { "LOW" });
+            }
+            let failures = report.iter().filter(|check| !check.passes()).count();
+            if failures > 0 {
+                println!("{} style group(s) below {}:1 contrast", failures, MIN_CONTRAST_RATIO);
+                if *strict {
+                    return Err(format!("Theme '{}' failed contrast audit", name).into());
+                }
+            }
+        },
+        ThemeAction::Format { name, resolve } => {
+            let path = Theme::format_theme_in(name, *resolve, search_path)?;
+            println!("Formatted theme '{}' at {}", name, path.display());
+        },
+        ThemeAction::Schema => {
+            println!("{}", Theme::json_schema()?);
+        },
+        ThemeAction::ClearCache => {
+            let removed = CompiledTheme::clear_cache(&cache.dir)?;
+            println!("Removed {} cached theme(s) from {}", removed, cache.dir.display());
+        },
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+fn handle_colors_command(find: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(hex) = find {
+        let rgb = parse_hex_color(hex).ok_or_else(|| format!("Invalid hex color '{}'", hex))?;
+        let (name, delta) = find_nearest_color(rgb).ok_or("Palette is empty")?;
+        println!("{}{}\x1B[0m (delta {:.1})", get_extended_color_code(name), name, delta);
+        return Ok(());
+    }
+    
+    for (category, names) in get_color_categories() {
+        let swatches: Vec<String> = names.iter()
+            .map(|name| format!("{}{}\x1B[0m", get_extended_color_code(name), name))
+            .collect();
+        println!("{}: {}", category, swatches.join(" "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_plain_default_mode_loads_no_theme() {
+        let line = "ERROR see https://example.com/x 1.2.3\n";
+        let theme = r#"{ metadata: { name: "p", version: "1.0.0", description: "plain" }, filters: { logs: { styles: { errors: { keywords: ["ERROR"], color: "crimson" } } } } }"#;
+        let highlight = |args: &[&str]| {
+            let cli = Cli::parse_from(args);
+            let theme = load_theme(&cli).unwrap().map(|(theme, _)| theme);
+            let app = JynxApp::with_theme(theme, cli.filter.clone(), None, cli.align.clone())
+                .with_default_mode(DefaultMode::from_arg(&cli.default_mode).unwrap_or_default());
+            let mut output = Vec::new();
+            app.run_io(line.as_bytes(), &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        
+        assert_eq!(highlight(&["jynx", "--default-mode", "plain"]), line);
+        assert_ne!(highlight(&["jynx"]), line);
+        // An explicit theme and filter still highlight
+        assert_ne!(highlight(&["jynx", "--default-mode", "plain", "--theme-string", theme, "--filter", "logs"]), line);
+    }
+}
@@ -1,161 +1,492 @@
 // Extended Color Palette - Rich variation for semantic highlighting
 // Builds on ref_colors.rs with expanded palette for diverse use cases
 
+use std::collections::HashMap;
+
+// Named colors and their escapes, in palette order
+const COLOR_CODES: &[(&str, &str)] = &[
+    // === CORE PALETTE (from ref_colors.rs) ===
+    ("red", "\x1B[38;5;9m"),
+    ("red2", "\x1B[38;5;197m"), 
+    ("deep", "\x1B[38;5;61m"),
+    ("deep_green", "\x1B[38;5;60m"),
+    ("orange", "\x1B[38;5;214m"),
+    ("yellow", "\x1B[33m"),
+    ("green", "\x1B[38;5;10m"),
+    ("green2", "\x1B[32m"),
+    ("blue", "\x1B[36m"),
+    ("blue2", "\x1B[38;5;39m"),
+    ("cyan", "\x1B[38;5;14m"),
+    ("magenta", "\x1B[35m"),
+    ("purple", "\x1B[38;5;213m"),
+    ("purple2", "\x1B[38;5;141m"),
+    ("white", "\x1B[38;5;247m"),
+    ("white2", "\x1B[38;5;15m"),
+    ("grey", "\x1B[38;5;242m"),
+    ("grey2", "\x1B[38;5;240m"),
+    ("grey3", "\x1B[38;5;237m"),
+
+    // === EXTENDED RED SPECTRUM ===
+    ("crimson", "\x1B[38;5;196m"),        // Pure red - critical alerts
+    ("ruby", "\x1B[38;5;160m"),           // Dark red - errors
+    ("coral", "\x1B[38;5;203m"),          // Red-orange - warnings
+    ("salmon", "\x1B[38;5;209m"),         // Light red-orange - notices
+    ("rose", "\x1B[38;5;217m"),           // Pink-red - highlights
+    ("brick", "\x1B[38;5;124m"),          // Dark brick red - severe
+
+    // === EXTENDED ORANGE SPECTRUM ===
+    ("amber", "\x1B[38;5;220m"),          // Golden orange - attention
+    ("tangerine", "\x1B[38;5;208m"),      // Bright orange - active
+    ("peach", "\x1B[38;5;216m"),          // Light orange - soft alerts
+    ("rust", "\x1B[38;5;166m"),           // Dark orange - deprecation
+    ("bronze", "\x1B[38;5;130m"),         // Brown-orange - legacy
+    ("gold", "\x1B[38;5;178m"),           // Golden - achievements
+
+    // === EXTENDED YELLOW SPECTRUM ===
+    ("lemon", "\x1B[38;5;226m"),          // Bright yellow - warnings
+    ("mustard", "\x1B[38;5;184m"),        // Muted yellow - caution  
+    ("sand", "\x1B[38;5;223m"),           // Beige-yellow - neutral
+    ("cream", "\x1B[38;5;230m"),          // Light yellow - info
+    ("khaki", "\x1B[38;5;143m"),          // Olive-yellow - pending
+
+    // === EXTENDED GREEN SPECTRUM ===
+    ("lime", "\x1B[38;5;46m"),            // Bright green - success
+    ("emerald", "\x1B[38;5;34m"),         // Pure green - completed
+    ("forest", "\x1B[38;5;22m"),          // Dark green - stable
+    ("mint", "\x1B[38;5;121m"),           // Light green - fresh
+    ("sage", "\x1B[38;5;108m"),           // Muted green - accepted
+    ("jade", "\x1B[38;5;35m"),            // Blue-green - verified
+    ("olive", "\x1B[38;5;58m"),           // Brown-green - archived
+
+    // === EXTENDED BLUE SPECTRUM ===
+    ("azure", "\x1B[38;5;33m"),           // Sky blue - information
+    ("navy", "\x1B[38;5;17m"),            // Dark blue - system
+    ("royal", "\x1B[38;5;21m"),           // Royal blue - primary
+    ("ice", "\x1B[38;5;159m"),            // Light blue - secondary
+    ("steel", "\x1B[38;5;67m"),           // Grey-blue - infrastructure
+    ("teal", "\x1B[38;5;30m"),            // Blue-green - data
+    ("indigo", "\x1B[38;5;54m"),          // Deep blue - configuration
+
+    // === EXTENDED PURPLE SPECTRUM ===
+    ("violet", "\x1B[38;5;129m"),         // Blue-purple - special
+    ("plum", "\x1B[38;5;96m"),            // Dark purple - reserved
+    ("lavender", "\x1B[38;5;183m"),       // Light purple - optional
+    ("orchid", "\x1B[38;5;170m"),         // Pink-purple - enhanced
+    ("mauve", "\x1B[38;5;139m"),          // Muted purple - metadata
+    ("amethyst", "\x1B[38;5;98m"),        // Deep purple - advanced
+
+    // === EXTENDED CYAN SPECTRUM ===
+    ("aqua", "\x1B[38;5;51m"),            // Bright cyan - active data
+    ("turquoise", "\x1B[38;5;45m"),       // Blue-cyan - processing
+    ("sky", "\x1B[38;5;117m"),            // Light cyan - status
+    ("ocean", "\x1B[38;5;31m"),           // Deep cyan - persistence
+
+    // === MONOCHROME SPECTRUM ===
+    ("black", "\x1B[38;5;16m"),           // Pure black - disabled
+    ("charcoal", "\x1B[38;5;235m"),       // Dark grey - inactive
+    ("slate", "\x1B[38;5;244m"),          // Medium grey - secondary
+    ("silver", "\x1B[38;5;250m"),         // Light grey - tertiary
+    ("pearl", "\x1B[38;5;253m"),          // Very light grey - background
+    ("snow", "\x1B[38;5;255m"),           // Pure white - emphasis
+
+    // === SEMANTIC GROUPINGS ===
+
+    // Error/Alert semantic colors
+    ("error", "\x1B[38;5;196m"),          // Critical error
+    ("warning", "\x1B[38;5;220m"),        // Warning state
+    ("danger", "\x1B[38;5;160m"),         // Dangerous operation
+    ("alert", "\x1B[38;5;208m"),          // Alert state
+
+    // Success/Positive semantic colors
+    ("success", "\x1B[38;5;46m"),         // Success state
+    ("complete", "\x1B[38;5;34m"),        // Completion
+    ("verified", "\x1B[38;5;35m"),        // Verification
+    ("approved", "\x1B[38;5;121m"),       // Approval
+
+    // Info/Neutral semantic colors  
+    ("info", "\x1B[38;5;33m"),            // Information
+    ("note", "\x1B[38;5;159m"),           // Note/annotation
+    ("hint", "\x1B[38;5;117m"),           // Hint/tip
+    ("debug", "\x1B[38;5;67m"),           // Debug information
+
+    // Process/State semantic colors
+    ("pending", "\x1B[38;5;184m"),        // Pending state
+    ("progress", "\x1B[38;5;214m"),       // In progress
+    ("blocked", "\x1B[38;5;197m"),        // Blocked state
+    ("queued", "\x1B[38;5;143m"),         // Queued state
+    ("active", "\x1B[38;5;51m"),          // Active state
+    ("inactive", "\x1B[38;5;240m"),       // Inactive state
+
+    // Extended BashFX stderr semantic colors (debugging personality)
+    ("silly", "\x1B[38;5;201m"),          // Bright magenta - ridiculous debugging/invalid conditions
+    ("magic", "\x1B[38;5;93m"),           // Purple variation - "how did this even work?" moments
+    ("trace", "\x1B[38;5;242m"),          // Medium grey - tracing state progression/function output
+    ("think", "\x1B[38;5;15m"),           // Bright white - tracing function calls only
+
+    // Priority semantic colors
+    ("critical", "\x1B[38;5;196m"),       // Critical priority
+    ("high", "\x1B[38;5;208m"),           // High priority
+    ("medium", "\x1B[38;5;220m"),         // Medium priority  
+    ("low", "\x1B[38;5;250m"),            // Low priority
+    ("trivial", "\x1B[38;5;237m"),        // Trivial priority
+
+    // === ADVANCED VARIATIONS ===
+
+    // Bright variants (high contrast)
+    ("bright_red", "\x1B[91m"),
+    ("bright_green", "\x1B[92m"),
+    ("bright_yellow", "\x1B[93m"),
+    ("bright_blue", "\x1B[94m"),
+    ("bright_magenta", "\x1B[95m"),
+    ("bright_cyan", "\x1B[96m"),
+    ("bright_black", "\x1B[90m"),
+    ("bright_white", "\x1B[97m"),
+
+    // Dim variants (low contrast)
+    ("dim_red", "\x1B[38;5;52m"),
+    ("dim_green", "\x1B[38;5;22m"),
+    ("dim_yellow", "\x1B[38;5;58m"),
+    ("dim_blue", "\x1B[38;5;17m"),
+    ("dim_magenta", "\x1B[38;5;54m"),
+    ("dim_cyan", "\x1B[38;5;23m"),
+
+    // Pastel variants (soft colors)
+    ("pastel_red", "\x1B[38;5;217m"),
+    ("pastel_green", "\x1B[38;5;157m"),
+    ("pastel_yellow", "\x1B[38;5;230m"),
+    ("pastel_blue", "\x1B[38;5;159m"),
+    ("pastel_purple", "\x1B[38;5;183m"),
+    ("pastel_orange", "\x1B[38;5;223m"),
+];
+
+// Escape for a named color ("" if unknown) at the palette set by `set_palette`
 pub fn get_extended_color_code(color: &str) -> &'static str {
-    match color {
-        // === CORE PALETTE (from ref_colors.rs) ===
-        "red" => "\x1B[38;5;9m",
-        "red2" => "\x1B[38;5;197m", 
-        "deep" => "\x1B[38;5;61m",
-        "deep_green" => "\x1B[38;5;60m",
-        "orange" => "\x1B[38;5;214m",
-        "yellow" => "\x1B[33m",
-        "green" => "\x1B[38;5;10m",
-        "green2" => "\x1B[32m",
-        "blue" => "\x1B[36m",
-        "blue2" => "\x1B[38;5;39m",
-        "cyan" => "\x1B[38;5;14m",
-        "magenta" => "\x1B[35m",
-        "purple" => "\x1B[38;5;213m",
-        "purple2" => "\x1B[38;5;141m",
-        "white" => "\x1B[38;5;247m",
-        "white2" => "\x1B[38;5;15m",
-        "grey" => "\x1B[38;5;242m",
-        "grey2" => "\x1B[38;5;240m",
-        "grey3" => "\x1B[38;5;237m",
-        
-        // === EXTENDED RED SPECTRUM ===
-        "crimson" => "\x1B[38;5;196m",        // Pure red - critical alerts
-        "ruby" => "\x1B[38;5;160m",           // Dark red - errors
-        "coral" => "\x1B[38;5;203m",          // Red-orange - warnings
-        "salmon" => "\x1B[38;5;209m",         // Light red-orange - notices
-        "rose" => "\x1B[38;5;217m",           // Pink-red - highlights
-        "brick" => "\x1B[38;5;124m",          // Dark brick red - severe
-        
-        // === EXTENDED ORANGE SPECTRUM ===
-        "amber" => "\x1B[38;5;220m",          // Golden orange - attention
-        "tangerine" => "\x1B[38;5;208m",      // Bright orange - active
-        "peach" => "\x1B[38;5;216m",          // Light orange - soft alerts
-        "rust" => "\x1B[38;5;166m",           // Dark orange - deprecation
-        "bronze" => "\x1B[38;5;130m",         // Brown-orange - legacy
-        "gold" => "\x1B[38;5;178m",           // Golden - achievements
-        
-        // === EXTENDED YELLOW SPECTRUM ===
-        "lemon" => "\x1B[38;5;226m",          // Bright yellow - warnings
-        "mustard" => "\x1B[38;5;184m",        // Muted yellow - caution  
-        "sand" => "\x1B[38;5;223m",           // Beige-yellow - neutral
-        "cream" => "\x1B[38;5;230m",          // Light yellow - info
-        "khaki" => "\x1B[38;5;143m",          // Olive-yellow - pending
-        
-        // === EXTENDED GREEN SPECTRUM ===
-        "lime" => "\x1B[38;5;46m",            // Bright green - success
-        "emerald" => "\x1B[38;5;34m",         // Pure green - completed
-        "forest" => "\x1B[38;5;22m",          // Dark green - stable
-        "mint" => "\x1B[38;5;121m",           // Light green - fresh
-        "sage" => "\x1B[38;5;108m",           // Muted green - accepted
-        "jade" => "\x1B[38;5;35m",            // Blue-green - verified
-        "olive" => "\x1B[38;5;58m",           // Brown-green - archived
-        
-        // === EXTENDED BLUE SPECTRUM ===
-        "azure" => "\x1B[38;5;33m",           // Sky blue - information
-        "navy" => "\x1B[38;5;17m",            // Dark blue - system
-        "royal" => "\x1B[38;5;21m",           // Royal blue - primary
-        "ice" => "\x1B[38;5;159m",            // Light blue - secondary
-        "steel" => "\x1B[38;5;67m",           // Grey-blue - infrastructure
-        "teal" => "\x1B[38;5;30m",            // Blue-green - data
-        "indigo" => "\x1B[38;5;54m",          // Deep blue - configuration
-        
-        // === EXTENDED PURPLE SPECTRUM ===
-        "violet" => "\x1B[38;5;129m",         // Blue-purple - special
-        "plum" => "\x1B[38;5;96m",            // Dark purple - reserved
-        "lavender" => "\x1B[38;5;183m",       // Light purple - optional
-        "orchid" => "\x1B[38;5;170m",         // Pink-purple - enhanced
-        "mauve" => "\x1B[38;5;139m",          // Muted purple - metadata
-        "amethyst" => "\x1B[38;5;98m",        // Deep purple - advanced
-        
-        // === EXTENDED CYAN SPECTRUM ===
-        "aqua" => "\x1B[38;5;51m",            // Bright cyan - active data
-        "turquoise" => "\x1B[38;5;45m",       // Blue-cyan - processing
-        "sky" => "\x1B[38;5;117m",            // Light cyan - status
-        "ocean" => "\x1B[38;5;31m",           // Deep cyan - persistence
-        
-        // === MONOCHROME SPECTRUM ===
-        "black" => "\x1B[38;5;16m",           // Pure black - disabled
-        "charcoal" => "\x1B[38;5;235m",       // Dark grey - inactive
-        "slate" => "\x1B[38;5;244m",          // Medium grey - secondary
-        "silver" => "\x1B[38;5;250m",         // Light grey - tertiary
-        "pearl" => "\x1B[38;5;253m",          // Very light grey - background
-        "snow" => "\x1B[38;5;255m",           // Pure white - emphasis
-        
-        // === SEMANTIC GROUPINGS ===
-        
-        // Error/Alert semantic colors
-        "error" => "\x1B[38;5;196m",          // Critical error
-        "warning" => "\x1B[38;5;220m",        // Warning state
-        "danger" => "\x1B[38;5;160m",         // Dangerous operation
-        "alert" => "\x1B[38;5;208m",          // Alert state
-        
-        // Success/Positive semantic colors
-        "success" => "\x1B[38;5;46m",         // Success state
-        "complete" => "\x1B[38;5;34m",        // Completion
-        "verified" => "\x1B[38;5;35m",        // Verification
-        "approved" => "\x1B[38;5;121m",       // Approval
-        
-        // Info/Neutral semantic colors  
-        "info" => "\x1B[38;5;33m",            // Information
-        "note" => "\x1B[38;5;159m",           // Note/annotation
-        "hint" => "\x1B[38;5;117m",           // Hint/tip
-        "debug" => "\x1B[38;5;67m",           // Debug information
-        
-        // Process/State semantic colors
-        "pending" => "\x1B[38;5;184m",        // Pending state
-        "progress" => "\x1B[38;5;214m",       // In progress
-        "blocked" => "\x1B[38;5;197m",        // Blocked state
-        "queued" => "\x1B[38;5;143m",         // Queued state
-        "active" => "\x1B[38;5;51m",          // Active state
-        "inactive" => "\x1B[38;5;240m",       // Inactive state
-        
-        // Extended BashFX stderr semantic colors (debugging personality)
-        "silly" => "\x1B[38;5;201m",          // Bright magenta - ridiculous debugging/invalid conditions
-        "magic" => "\x1B[38;5;93m",           // Purple variation - "how did this even work?" moments
-        "trace" => "\x1B[38;5;242m",          // Medium grey - tracing state progression/function output
-        "think" => "\x1B[38;5;15m",           // Bright white - tracing function calls only
-        
-        // Priority semantic colors
-        "critical" => "\x1B[38;5;196m",       // Critical priority
-        "high" => "\x1B[38;5;208m",           // High priority
-        "medium" => "\x1B[38;5;220m",         // Medium priority  
-        "low" => "\x1B[38;5;250m",            // Low priority
-        "trivial" => "\x1B[38;5;237m",        // Trivial priority
-        
-        // === ADVANCED VARIATIONS ===
-        
-        // Bright variants (high contrast)
-        "bright_red" => "\x1B[38;5;9m",
-        "bright_green" => "\x1B[38;5;10m",
-        "bright_yellow" => "\x1B[38;5;11m",
-        "bright_blue" => "\x1B[38;5;12m",
-        "bright_magenta" => "\x1B[38;5;13m",
-        "bright_cyan" => "\x1B[38;5;14m",
-        
-        // Dim variants (low contrast)
-        "dim_red" => "\x1B[38;5;52m",
-        "dim_green" => "\x1B[38;5;22m",
-        "dim_yellow" => "\x1B[38;5;58m",
-        "dim_blue" => "\x1B[38;5;17m",
-        "dim_magenta" => "\x1B[38;5;54m",
-        "dim_cyan" => "\x1B[38;5;23m",
-        
-        // Pastel variants (soft colors)
-        "pastel_red" => "\x1B[38;5;217m",
-        "pastel_green" => "\x1B[38;5;157m",
-        "pastel_yellow" => "\x1B[38;5;230m",
-        "pastel_blue" => "\x1B[38;5;159m",
-        "pastel_purple" => "\x1B[38;5;183m",
-        "pastel_orange" => "\x1B[38;5;223m",
-        
-        // Default fallback
-        _ => "",
+    color_code_for(color, palette())
+}
+
+// Escape for a named color in the 256-color palette: O(1) lookup in a map built once
+fn base_color_code(color: &str) -> &'static str {
+    static CODES: std::sync::OnceLock<HashMap<&'static str, &'static str>> = std::sync::OnceLock::new();
+    CODES.get_or_init(|| COLOR_CODES.iter().copied()
+            .chain(indexed_colors().iter().map(|(name, code)| (name.as_str(), code.as_str())))
+            .collect())
+        .get(color)
+        .copied()
+        .unwrap_or("")
+}
+
+// Name for a raw 256-color index, `color0` ... `color255` (0-15 are the basic
+// SGR codes), so colors outside the named palette can still go in a Style
+pub fn indexed_color_name(index: u8) -> &'static str {
+    &indexed_colors()[index as usize].0
+}
+
+// (name, escape) for every 256-color index, built once
+fn indexed_colors() -> &'static [(String, String)] {
+    static INDEXED: std::sync::OnceLock<Vec<(String, String)>> = std::sync::OnceLock::new();
+    INDEXED.get_or_init(|| (0..=255u8).map(|index| {
+        let code = if index < 16 { ansi16_code(index) } else { format!("\x1B[38;5;{}m", index) };
+        (format!("color{}", index), code)
+    }).collect())
+}
+
+// Every named color understood by get_extended_color_code, in palette order
+pub const COLOR_NAMES: &[&str] = &[
+    "red", "red2", "deep", "deep_green", "orange", "yellow", "green", "green2",
+    "blue", "blue2", "cyan", "magenta", "purple", "purple2", "white", "white2",
+    "grey", "grey2", "grey3", "crimson", "ruby", "coral", "salmon", "rose",
+    "brick", "amber", "tangerine", "peach", "rust", "bronze", "gold", "lemon",
+    "mustard", "sand", "cream", "khaki", "lime", "emerald", "forest", "mint",
+    "sage", "jade", "olive", "azure", "navy", "royal", "ice", "steel",
+    "teal", "indigo", "violet", "plum", "lavender", "orchid", "mauve", "amethyst",
+    "aqua", "turquoise", "sky", "ocean", "black", "charcoal", "slate", "silver",
+    "pearl", "snow", "error", "warning", "danger", "alert", "success", "complete",
+    "verified", "approved", "info", "note", "hint", "debug", "pending", "progress",
+    "blocked", "queued", "active", "inactive", "silly", "magic", "trace", "think",
+    "critical", "high", "medium", "low", "trivial", "bright_red", "bright_green", "bright_yellow",
+    "bright_blue", "bright_magenta", "bright_cyan", "bright_black", "bright_white", "dim_red", "dim_green", "dim_yellow",
+    "dim_blue", "dim_magenta", "dim_cyan", "pastel_red", "pastel_green", "pastel_yellow", "pastel_blue", "pastel_purple",
+    "pastel_orange",
+];
+
+// The classic 16 SGR colors (30-37, then 90-97 for `bright_`), in palette index order
+pub const ANSI16_NAMES: [&str; 16] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    "bright_black", "bright_red", "bright_green", "bright_yellow", "bright_blue", "bright_magenta", "bright_cyan", "bright_white",
+];
+
+// Color depth named colors resolve to (`--palette 16|256|truecolor`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Palette {
+    // 30-37 / 90-97 only: the 16 standard names exactly, other names at their nearest of the 16
+    Ansi16,
+    // The palette as defined above (38;5;N plus a few basic codes)
+    #[default]
+    Ansi256,
+    // 24-bit `38;2;R;G;B` for every 256-color entry; basic codes stay as they are
+    TrueColor,
+}
+
+impl Palette {
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "16" => Some(Self::Ansi16),
+            "256" => Some(Self::Ansi256),
+            "truecolor" | "24bit" => Some(Self::TrueColor),
+            _ => None,
+        }
+    }
+}
+
+static PALETTE: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(Palette::Ansi256 as u8);
+
+// Set the process-wide palette used by `get_extended_color_code`, `Style::to_ansi`
+// and `Ansi::current`, which a new `JynxApp` starts from (`with_ansi` sets its own)
+pub fn set_palette(palette: Palette) {
+    PALETTE.store(palette as u8, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn palette() -> Palette {
+    match PALETTE.load(std::sync::atomic::Ordering::Relaxed) {
+        0 => Palette::Ansi16,
+        2 => Palette::TrueColor,
+        _ => Palette::Ansi256,
+    }
+}
+
+// How `bold` is rendered (`--bold-mode weight|bright`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BoldMode {
+    // `\x1B[1m`, a heavier font weight
+    #[default]
+    Weight,
+    // One of the 16 standard colors switches to its bright variant instead; other colors keep the weight
+    Bright,
+}
+
+impl BoldMode {
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "weight" => Some(Self::Weight),
+            "bright" => Some(Self::Bright),
+            _ => None,
+        }
+    }
+}
+
+static BOLD_MODE: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(BoldMode::Weight as u8);
+
+// Set the process-wide bold mode used by `Style::to_ansi` and `Ansi::current`,
+// which a new `JynxApp` starts from (`with_ansi` sets its own)
+pub fn set_bold_mode(mode: BoldMode) {
+    BOLD_MODE.store(mode as u8, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn bold_mode() -> BoldMode {
+    match BOLD_MODE.load(std::sync::atomic::Ordering::Relaxed) {
+        1 => BoldMode::Bright,
+        _ => BoldMode::Weight,
+    }
+}
+
+// Bright variant of one of the 16 standard colors (`red` -> `bright_red`; bright ones map to themselves)
+pub fn bright_variant(color: &str) -> Option<&'static str> {
+    let index = ANSI16_NAMES.iter().position(|&name| name == color)?;
+    Some(ANSI16_NAMES[index | 8])
+}
+
+// Basic SGR code for a palette index below 16
+fn ansi16_code(index: u8) -> String {
+    match index {
+        0..=7 => format!("\x1B[{}m", 30 + index),
+        _ => format!("\x1B[{}m", 90 + index - 8),
+    }
+}
+
+// Named colors followed by the indexed ones
+fn all_color_names() -> impl Iterator<Item = &'static str> {
+    COLOR_NAMES.iter().copied().chain(indexed_colors().iter().map(|(name, _)| name.as_str()))
+}
+
+// Escape for a named color at a given palette ("" if unknown)
+pub fn color_code_for(color: &str, palette: Palette) -> &'static str {
+    type Codes = std::sync::OnceLock<HashMap<&'static str, String>>;
+    static ANSI16: Codes = std::sync::OnceLock::new();
+    static TRUECOLOR: Codes = std::sync::OnceLock::new();
+    
+    let codes = match palette {
+        Palette::Ansi256 => return base_color_code(color),
+        Palette::Ansi16 => ANSI16.get_or_init(|| all_color_names().map(|name| {
+            let index = match ANSI16_NAMES.iter().position(|&standard| standard == name) {
+                Some(index) => index as u8,
+                None => {
+                    let rgb = get_color_rgb(name).unwrap_or_default();
+                    (0..16u8).min_by(|&a, &b| rgb_distance(rgb, ansi256_to_rgb(a)).total_cmp(&rgb_distance(rgb, ansi256_to_rgb(b)))).unwrap_or(7)
+                },
+            };
+            (name, ansi16_code(index))
+        }).collect()),
+        Palette::TrueColor => TRUECOLOR.get_or_init(|| all_color_names().map(|name| {
+            let code = base_color_code(name);
+            let code = match code.strip_prefix("\x1B[38;5;").and_then(|rest| rest.strip_suffix('m')?.parse::<u8>().ok()) {
+                Some(index) => {
+                    let (r, g, b) = ansi256_to_rgb(index);
+                    format!("\x1B[38;2;{};{};{}m", r, g, b)
+                },
+                None => code.to_string(),
+            };
+            (name, code)
+        }).collect()),
+    };
+    codes.get(color).map_or("", String::as_str)
+}
+
+// Palette index behind a color escape: 38;5;N or the basic 30-37 / 90-97 codes
+pub fn ansi_code_to_index(code: &str) -> Option<u8> {
+    let params = code.strip_prefix("\x1B[")?.strip_suffix('m')?;
+    
+    if let Some(index) = params.strip_prefix("38;5;") {
+        return index.parse().ok();
+    }
+    
+    match params.parse::<u8>().ok()? {
+        n @ 30..=37 => Some(n - 30),
+        n @ 90..=97 => Some(n - 90 + 8),
+        _ => None,
+    }
+}
+
+// Standard xterm RGB value for a 256-color palette index
+pub fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    const SYSTEM: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (128, 0, 0), (0, 128, 0), (128, 128, 0),
+        (0, 0, 128), (128, 0, 128), (0, 128, 128), (192, 192, 192),
+        (128, 128, 128), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+        (0, 0, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    
+    match index {
+        0..=15 => SYSTEM[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[((i / 6) % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        },
+        232..=255 => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        },
+    }
+}
+
+// RGB triple for a named color (derived from its 256-color palette index)
+pub fn get_color_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    ansi_code_to_index(base_color_code(color)).map(ansi256_to_rgb)
+}
+
+// Parse "#rrggbb" / "rrggbb" (or shorthand "#rgb") into an RGB triple
+pub fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
+    let digits = hex.trim().trim_start_matches('#');
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    
+    let channel = |i: usize, len: usize| u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).ok();
+    match digits.len() {
+        6 => Some((channel(0, 2)?, channel(1, 2)?, channel(2, 2)?)),
+        3 => {
+            let (r, g, b) = (channel(0, 1)?, channel(1, 1)?, channel(2, 1)?);
+            Some((r * 17, g * 17, b * 17))
+        },
+        _ => None,
+    }
+}
+
+// Euclidean distance between two RGB colors
+pub fn rgb_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let dr = a.0 as f64 - b.0 as f64;
+    let dg = a.1 as f64 - b.1 as f64;
+    let db = a.2 as f64 - b.2 as f64;
+    (dr * dr + dg * dg + db * db).sqrt()
+}
+
+// WCAG relative luminance of an sRGB color (0.0 black .. 1.0 white)
+pub fn relative_luminance(rgb: (u8, u8, u8)) -> f64 {
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.0;
+        if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(rgb.0) + 0.7152 * linear(rgb.1) + 0.0722 * linear(rgb.2)
+}
+
+// WCAG contrast ratio between two colors (1.0 .. 21.0, order doesn't matter)
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+// Color-cube indices readable on both dark and light backgrounds:
+// very dark and very light shades are left out
+fn readable_hash_indices() -> &'static [u8] {
+    static INDICES: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+    INDICES.get_or_init(|| {
+        (16..=231u8)
+            .filter(|&index| {
+                let luminance = relative_luminance(ansi256_to_rgb(index));
+                (0.08..=0.6).contains(&luminance)
+            })
+            .collect()
+    })
+}
+
+// Stable 256-color index for arbitrary text (FNV-1a, same text -> same color
+// across lines and runs), limited to readable shades
+pub fn hash_color_index(text: &str) -> u8 {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    let indices = readable_hash_indices();
+    indices[(hash % indices.len() as u64) as usize]
+}
+
+// Foreground escape for hash_color_index
+pub fn hash_color_code(text: &str) -> String {
+    format!("\x1B[38;5;{}m", hash_color_index(text))
+}
+
+// Closest named palette color to an RGB value: (name, distance)
+// Ties go to the earliest name in COLOR_NAMES
+pub fn find_nearest_color(rgb: (u8, u8, u8)) -> Option<(&'static str, f64)> {
+    COLOR_NAMES
+        .iter()
+        .filter_map(|name| get_color_rgb(name).map(|color| (*name, rgb_distance(rgb, color))))
+        .fold(None, |best: Option<(&'static str, f64)>, candidate| match best {
+            Some(current) if current.1 <= candidate.1 => Some(current),
+            _ => Some(candidate),
+        })
+}
+
+// Background escape for a named color ("" if unknown) at the palette set by `set_palette`
+pub fn get_extended_bg_code(color: &str) -> String {
+    bg_code_for(color, palette())
+}
+
+// Background escape for a named color at a given palette ("" if unknown): the
+// foreground escape moved over (38;5;N -> 48;5;N, 38;2 -> 48;2, 3x -> 4x, 9x -> 10x)
+pub fn bg_code_for(color: &str, palette: Palette) -> String {
+    let code = color_code_for(color, palette);
+    if let Some(rgb) = code.strip_prefix("\x1B[38;2;") {
+        return format!("\x1B[48;2;{}", rgb);
+    }
+    match ansi_code_to_index(code) {
+        Some(index) if code.contains("38;5;") => format!("\x1B[48;5;{}m", index),
+        Some(index @ 0..=7) => format!("\x1B[{}m", 40 + index),
+        Some(index) => format!("\x1B[{}m", 100 + index - 8),
+        None => String::new(),
     }
 }
 
@@ -201,4 +532,99 @@ pub fn generate_kb_theme_suggestions() -> Vec<(&'static str, Vec<&'static str>)>
         ("high_contrast", vec!["snow", "crimson", "emerald"]),
         ("colorblind_safe", vec!["azure", "amber", "charcoal"]),
     ]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_every_named_color_resolves() {
+        for name in COLOR_NAMES {
+            assert!(!get_extended_color_code(name).is_empty(), "'{}' has no escape", name);
+            assert!(get_color_rgb(name).is_some(), "'{}' has no RGB value", name);
+        }
+    }
+    
+    #[test]
+    fn test_color_table_matches_names() {
+        let names: Vec<&str> = COLOR_CODES.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, COLOR_NAMES);
+        assert_eq!(get_extended_color_code("crimson"), "\x1B[38;5;196m");
+        assert_eq!(get_extended_color_code("not-a-color"), "");
+    }
+    
+    #[test]
+    fn test_sixteen_color_palette() {
+        assert_eq!(get_extended_color_code("bright_red"), "\x1B[91m");
+        assert_eq!(get_extended_bg_code("bright_red"), "\x1B[101m");
+        
+        assert_eq!(color_code_for("red", Palette::Ansi16), "\x1B[31m");
+        assert_eq!(color_code_for("bright_red", Palette::Ansi16), "\x1B[91m");
+        assert_eq!(color_code_for("bright_white", Palette::Ansi16), "\x1B[97m");
+        assert_eq!(bright_variant("red"), Some("bright_red"));
+        assert_eq!(bright_variant("bright_red"), Some("bright_red"));
+        assert_eq!(bright_variant("crimson"), None);
+        
+        assert_eq!(get_extended_color_code(indexed_color_name(4)), "\x1B[34m");
+        assert_eq!(color_code_for("color208", Palette::Ansi256), "\x1B[38;5;208m");
+        assert_eq!(color_code_for("color208", Palette::TrueColor), "\x1B[38;2;255;135;0m");
+        assert_eq!(color_code_for("color12", Palette::Ansi16), "\x1B[94m");
+        assert_eq!(get_color_rgb("color196"), Some((255, 0, 0)));
+        // Other names fall to their nearest standard color: crimson (#ff0000) is bright red
+        assert_eq!(color_code_for("crimson", Palette::Ansi16), "\x1B[91m");
+        for name in COLOR_NAMES {
+            assert!(ansi_code_to_index(color_code_for(name, Palette::Ansi16)).is_some_and(|index| index < 16), "{}", name);
+        }
+        
+        assert_eq!(color_code_for("crimson", Palette::TrueColor), "\x1B[38;2;255;0;0m");
+        assert_eq!(color_code_for("bright_red", Palette::TrueColor), "\x1B[91m");
+        assert_eq!(color_code_for("not-a-color", Palette::Ansi16), "");
+        assert_eq!(Palette::from_arg("truecolor"), Some(Palette::TrueColor));
+    }
+    
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8000"), Some((255, 128, 0)));
+        assert_eq!(parse_hex_color("00FF00"), Some((0, 255, 0)));
+        assert_eq!(parse_hex_color("#f00"), Some((255, 0, 0)));
+        assert_eq!(parse_hex_color("#zzzzzz"), None);
+        assert_eq!(parse_hex_color("#ff00"), None);
+    }
+    
+    #[test]
+    fn test_find_nearest_red() {
+        let (name, delta) = find_nearest_color((255, 0, 0)).unwrap();
+        let red_family = &get_color_categories()[0].1;
+        assert!(red_family.contains(&name), "'{}' is not in the red family", name);
+        assert_eq!(delta, 0.0);
+    }
+    
+    #[test]
+    fn test_find_nearest_inexact() {
+        // #1f1f1f sits between black (0) and charcoal (38)
+        let (name, delta) = find_nearest_color((0x1f, 0x1f, 0x1f)).unwrap();
+        assert_eq!(name, "charcoal");
+        assert!(delta > 0.0);
+    }
+    
+    #[test]
+    fn test_contrast_ratio() {
+        assert!((contrast_ratio((0, 0, 0), (255, 255, 255)) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio((128, 128, 128), (128, 128, 128)) - 1.0).abs() < f64::EPSILON);
+        // Known good pair: white on navy; known bad: yellow on white
+        let (white, navy) = (get_color_rgb("white").unwrap(), get_color_rgb("navy").unwrap());
+        assert!(contrast_ratio(white, navy) >= 4.5);
+        assert!(contrast_ratio((255, 255, 0), (255, 255, 255)) < 4.5);
+    }
+    
+    #[test]
+    fn test_hash_color_index() {
+        assert_eq!(hash_color_index("req-42"), hash_color_index("req-42"));
+        for index in readable_hash_indices() {
+            let luminance = relative_luminance(ansi256_to_rgb(*index));
+            assert!((0.08..=0.6).contains(&luminance));
+        }
+        assert!(readable_hash_indices().len() > 50);
+    }
+}
@@ -2,7 +2,12 @@
 //! 
 //! Recognizes common patterns like paths, versions, URLs automatically
 
-use regex::Regex;
+use std::borrow::Cow;
+
+use regex::{Regex, RegexSet};
+use crate::extended_colors::{hash_color_index, indexed_color_name, COLOR_NAMES};
+use crate::std::RegexLimits;
+use crate::text_styles::{Renderer, SgrState, Style};
 
 /// ANSI color codes for basic highlighting
 pub struct Colors;
@@ -13,8 +18,14 @@ impl Colors {
     pub const RESET: &'static str = "\x1b[0m";       // Reset
     pub const UNDERLINE: &'static str = "\x1b[4m";   // Underline
     pub const BOLD: &'static str = "\x1b[1m";        // Bold
+    pub const DIM: &'static str = "\x1b[2m";         // Dim for line/column numbers
+    pub const AMBER: &'static str = "\x1b[33m";      // Yellow for strings
+    pub const VIOLET: &'static str = "\x1b[35m";     // Magenta for numbers
 }
 
+/// Opt-in detectors (`--detect`), in the order they are applied
+pub const EXTRA_DETECTORS: &[&str] = &["keyval", "strings", "numbers"];
+
 /// Unicode emoji icons for visual enhancement
 pub struct Icons;
 impl Icons {
@@ -30,64 +41,602 @@ impl Icons {
 
 /// Check if terminal supports Unicode (basic heuristic)
 fn supports_unicode() -> bool {
-    // Check if LANG or LC_ALL contains UTF-8
-    std::env::var("LANG")
-        .or_else(|_| std::env::var("LC_ALL"))
-        .map(|s| s.contains("UTF-8") || s.contains("utf8"))
-        .unwrap_or(false)
+    let var = |name| std::env::var(name).ok();
+    unicode_from_env(
+        var("JYNX_UNICODE").as_deref(),
+        var("LC_ALL").as_deref(),
+        var("LC_CTYPE").as_deref(),
+        var("LANG").as_deref(),
+        var("TERM").as_deref(),
+    )
+}
+
+/// Decide Unicode support from environment values
+///
+/// `JYNX_UNICODE=1/0` wins; otherwise the effective locale (first non-empty of
+/// LC_ALL, LC_CTYPE, LANG, as POSIX resolves it) must name UTF-8. With no locale
+/// at all, terminals known to render emoji still count.
+pub fn unicode_from_env(
+    jynx_unicode: Option<&str>,
+    lc_all: Option<&str>,
+    lc_ctype: Option<&str>,
+    lang: Option<&str>,
+    term: Option<&str>,
+) -> bool {
+    match jynx_unicode.map(|v| v.trim().to_lowercase()).as_deref() {
+        Some("1" | "on" | "true" | "yes") => return true,
+        Some("0" | "off" | "false" | "no") => return false,
+        _ => {}
+    }
+    
+    let locale = [lc_all, lc_ctype, lang].into_iter().flatten().find(|v| !v.is_empty());
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        },
+        None => term.is_some_and(|t| ["kitty", "alacritty", "wezterm", "foot"].iter().any(|known| t.contains(known))),
+    }
+}
+
+/// How icons are rendered in front of highlighted words
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IconMode {
+    /// Emoji icons
+    #[default]
+    Unicode,
+    /// Text fallbacks for terminals without emoji glyphs
+    Ascii,
+    /// No icons at all, colored words only
+    None,
+}
+
+impl IconMode {
+    /// Unicode if the terminal looks like it can render it, otherwise ASCII
+    pub fn detect() -> Self {
+        if supports_unicode() { Self::Unicode } else { Self::Ascii }
+    }
+    
+    /// Parse `--unicode` values: on, off, auto
+    pub fn from_unicode_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "on" => Some(Self::Unicode),
+            "off" => Some(Self::Ascii),
+            "auto" => Some(Self::detect()),
+            _ => None,
+        }
+    }
+    
+    /// Parse `--icons` values: auto, unicode, ascii, none
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "auto" => Some(Self::detect()),
+            "unicode" => Some(Self::Unicode),
+            "ascii" => Some(Self::Ascii),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+    
+    /// Pick the icon for this mode; an empty string means no icon
+    pub fn pick<'a>(&self, unicode: &'a str, fallback: &'a str) -> &'a str {
+        match self {
+            Self::Unicode => unicode,
+            Self::Ascii => fallback,
+            Self::None => "",
+        }
+    }
+}
+
+/// The text a detector styles: capture group 1, or the whole match for patterns
+/// whose parentheses capture nothing (`\(\d+\)`, `(?:ERR)\d+`)
+fn styled_group<'t>(caps: &regex::Captures<'t>) -> &'t str {
+    caps.get(1).unwrap_or_else(|| caps.get(0).unwrap()).as_str()
+}
+
+/// Default colors cycled through by `--match` patterns
+pub const MATCH_COLORS: &[&str] = &["yellow", "magenta", "cyan", "lime", "orange"];
+
+/// Everything needed to build an `AutoDetector`
+#[derive(Debug, Clone, Default)]
+pub struct DetectorConfig {
+    /// No auto-detection at all (`--no-detect`), overriding everything below
+    pub disabled: bool,
+    pub icon_mode: IconMode,
+    /// Opt-in detectors by name (see `EXTRA_DETECTORS`)
+    pub extra_detectors: Vec<String>,
+    pub hyperlinks: bool,
+    /// Ad-hoc `--match` regexes, highlighted for this run only
+    pub match_patterns: Vec<String>,
+    /// Color for all `--match` patterns instead of cycling `MATCH_COLORS`
+    pub match_color: Option<String>,
+    /// `--hash-color` regexes: each distinct match gets its own stable color
+    pub hash_patterns: Vec<String>,
+}
+
+/// How an opt-in detector styles its matches
+#[derive(Debug, Clone)]
+enum PatternStyle {
+    /// The same style for every match
+    Fixed(Style),
+    /// A color derived from the matched text (`--hash-color`)
+    Hashed,
 }
 
 /// Core auto-detection patterns - start with just 3 for MVP
+#[derive(Clone)]
 pub struct AutoDetector {
     // Ordered list: (name, regex, style, icon)
-    patterns: Vec<(String, Regex, String, String)>,
+    patterns: Vec<(String, Regex, Style, String)>,
+    // All of `patterns` at once, to skip the ones a line can't match
+    pattern_set: Option<RegexSet>,
+    // Opt-in detectors, only applied to text not already styled:
+    // (name, regex, style, capture group to style; 0 is the whole match)
+    extras: Vec<(String, Regex, PatternStyle, usize)>,
+    // Matches SGR and OSC escape sequences so detectors can skip styled spans
+    ansi_pattern: Regex,
+    // The same plus tags and entities, for a markup renderer's output
+    markup_pattern: Regex,
+    // Wrap file:line references in OSC 8 hyperlinks
+    hyperlinks: bool,
 }
 
 impl AutoDetector {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let mut patterns = Vec::new();
-        let use_unicode = supports_unicode();
+        Self::with_icon_mode(IconMode::detect())
+    }
+    
+    /// Build the detector with every option in `config` applied
+    pub fn from_config(config: &DetectorConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_icon_mode(config.icon_mode)?
+            .with_extra_detectors(&config.extra_detectors)?
+            .with_match_patterns(&config.match_patterns, config.match_color.as_deref())?
+            .with_hash_patterns(&config.hash_patterns)?
+            .with_hyperlinks(config.hyperlinks))
+    }
+    
+    /// Build the detector with icons rendered in the given mode
+    pub fn with_icon_mode(icon_mode: IconMode) -> Result<Self, Box<dyn std::error::Error>> {
+        let limits = RegexLimits::default();
         
-        // Apply in order: URLs first (most specific), then versions, then paths
+        // Apply in order: URLs first (most specific), then file:line references,
+        // versions, then paths. Each only sees text earlier ones left unstyled.
+        let patterns = vec![
+            // URL pattern: http:// or https:// (complete URLs)
+            (
+                "urls".to_string(),
+                limits.compile(r"(https?://[^\s]+)")?,
+                Style::new().color(indexed_color_name(4)).underline(),
+                icon_mode.pick(Icons::URL, Icons::URL_FALLBACK).to_string(),
+            ),
+            // Compiler-style references: src/main.rs:42 or foo.rs:1:2 (path needs an extension)
+            (
+                "filelines".to_string(),
+                limits.compile(r"([\w./~-]*\w\.[A-Za-z]\w*):(\d+)(?::(\d+))?\b")?,
+                Style::new().color(indexed_color_name(6)),
+                String::new(),
+            ),
+            // Version pattern: 1.2.3 or 2.0.0-alpha  
+            (
+                "versions".to_string(),
+                limits.compile(r"\bv?(\d+\.\d+\.\d+(-\w+)?)\b")?,
+                Style::new().color(indexed_color_name(2)).bold(),
+                icon_mode.pick(Icons::VERSION, Icons::VERSION_FALLBACK).to_string(),
+            ),
+            // Path pattern: filesystem paths (simple and robust)
+            (
+                "paths".to_string(),
+                limits.compile(r"\b([~/][^\s]+\.[a-z]{2,4})\b")?,
+                Style::new().color(indexed_color_name(6)).underline(),
+                icon_mode.pick(Icons::PATH, Icons::PATH_FALLBACK).to_string(),
+            ),
+        ];
         
-        // URL pattern: http:// or https:// (complete URLs)
-        patterns.push((
-            "urls".to_string(),
-            Regex::new(r"(https?://[^\s]+)")?,
-            format!("{}{}", Colors::UNDERLINE, Colors::ROYAL),
-            if use_unicode { Icons::URL.to_string() } else { Icons::URL_FALLBACK.to_string() },
-        ));
+        Ok(AutoDetector {
+            pattern_set: Self::compile_pattern_set(&patterns, &limits),
+            patterns,
+            extras: Vec::new(),
+            ansi_pattern: limits.compile(r"\x1b\[[0-9;:]*m|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)")?,
+            markup_pattern: limits.compile(r"\x1b\[[0-9;:]*m|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|<[^>]*>|&[#0-9A-Za-z]+;")?,
+            hyperlinks: false,
+        })
+    }
+    
+    /// One `RegexSet` over the detection patterns, within the `limits` they were
+    /// compiled with; if it can't be built (reported) every pattern just runs on every line
+    fn compile_pattern_set(patterns: &[(String, Regex, Style, String)], limits: &RegexLimits) -> Option<RegexSet> {
+        let sources: Vec<String> = patterns.iter().map(|(_, regex, _, _)| regex.as_str().to_string()).collect();
+        limits.compile_set(&sources)
+            .map_err(|e| eprintln!("Warning: auto_detection patterns don't fit the regex limits together ({}); trying each on every line", e))
+            .ok()
+    }
+    
+    /// Replace the built-in detectors with theme `auto_detection` patterns given as
+    /// `(name, regex, style)`; none leaves the built-ins in place. Patterns
+    /// without a capture group style their whole match; invalid ones are reported
+    /// and skipped so one bad pattern doesn't cost all detection.
+    pub fn with_theme_patterns(mut self, patterns: &[(String, String, Style)], icon_mode: IconMode, limits: &RegexLimits) -> Result<Self, Box<dyn std::error::Error>> {
+        if patterns.is_empty() {
+            return Ok(self);
+        }
         
-        // Version pattern: 1.2.3 or 2.0.0-alpha  
-        patterns.push((
-            "versions".to_string(),
-            Regex::new(r"\bv?(\d+\.\d+\.\d+(-\w+)?)\b")?,
-            format!("{}{}", Colors::BOLD, Colors::EMERALD),
-            if use_unicode { Icons::VERSION.to_string() } else { Icons::VERSION_FALLBACK.to_string() },
-        ));
+        self.patterns = Vec::new();
+        for (name, pattern, style) in patterns {
+            let pattern = if pattern.contains('(') { pattern.clone() } else { format!("({})", pattern) };
+            let icon = match name.as_str() {
+                "paths" => icon_mode.pick(Icons::PATH, Icons::PATH_FALLBACK),
+                "versions" => icon_mode.pick(Icons::VERSION, Icons::VERSION_FALLBACK),
+                "urls" => icon_mode.pick(Icons::URL, Icons::URL_FALLBACK),
+                _ => "",
+            };
+            match limits.compile(&pattern) {
+                Ok(regex) => self.patterns.push((name.clone(), regex, style.clone(), icon.to_string())),
+                Err(e) => eprintln!("Warning: Skipping auto_detection '{}': {}", name, e),
+            }
+        }
+        self.pattern_set = Self::compile_pattern_set(&self.patterns, limits);
+        Ok(self)
+    }
+    
+    /// Append ad-hoc regexes (`--match`) as transient detectors
+    ///
+    /// Each pattern gets the next color from `MATCH_COLORS` unless `color` is given.
+    pub fn with_match_patterns(mut self, patterns: &[String], color: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(color) = color {
+            if !COLOR_NAMES.contains(&color) {
+                return Err(format!("Unknown match color '{}'", color).into());
+            }
+        }
         
-        // Path pattern: filesystem paths (simple and robust)
-        patterns.push((
-            "paths".to_string(),
-            Regex::new(r"\b([~/][^\s]+\.[a-z]{2,4})\b")?,
-            format!("{}{}", Colors::UNDERLINE, Colors::AZURE),
-            if use_unicode { Icons::PATH.to_string() } else { Icons::PATH_FALLBACK.to_string() },
-        ));
+        for (index, pattern) in patterns.iter().enumerate() {
+            let color = color.unwrap_or(MATCH_COLORS[index % MATCH_COLORS.len()]);
+            let style = Style::new().color(color).bold();
+            self.extras.push(("match".to_string(), RegexLimits::default().compile(pattern)?, PatternStyle::Fixed(style), 0));
+        }
+        
+        Ok(self)
+    }
+    
+    /// Color each distinct match of these regexes by hashing its text (`--hash-color`),
+    /// so the same token gets the same color on every line
+    pub fn with_hash_patterns(mut self, patterns: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        for pattern in patterns {
+            self.extras.push(("hash".to_string(), RegexLimits::default().compile(pattern)?, PatternStyle::Hashed, 0));
+        }
+        Ok(self)
+    }
+    
+    /// Turn file:line references into OSC 8 `file://` hyperlinks
+    pub fn with_hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+    
+    /// Enable opt-in detectors by name (see `EXTRA_DETECTORS`)
+    ///
+    /// They run after the built-ins regardless of the order given, so versions
+    /// and quoted strings claim their text before bare numbers can.
+    pub fn with_extra_detectors(mut self, names: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(unknown) = names.iter().find(|name| !EXTRA_DETECTORS.contains(&name.as_str())) {
+            return Err(format!("Unknown detector '{}' (available: {})", unknown, EXTRA_DETECTORS.join(", ")).into());
+        }
         
-        Ok(AutoDetector { patterns })
+        for name in EXTRA_DETECTORS.iter().filter(|name| names.iter().any(|n| n == *name)) {
+            let (pattern, color, group) = match *name {
+                // Logfmt pairs: only the key is colored. Keys must start a token
+                // (so `?a=1&b=2` query strings don't match) and values can't
+                // start with `=` (so `a==b` doesn't either)
+                "keyval" => (r#"(?:^|[\s,(\[{])(\w+)=(?:"[^"]*"|[^\s=]\S*)"#, 6, 1),
+                // Quoted strings, allowing escaped quotes inside
+                "strings" => (r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'"#, 3, 0),
+                "numbers" => (r"\b\d+(\.\d+)?\b", 5, 0),
+                _ => continue,
+            };
+            self.extras.push((name.to_string(), RegexLimits::default().compile(pattern)?, PatternStyle::Fixed(Style::new().color(indexed_color_name(color))), group));
+        }
+        
+        Ok(self)
     }
     
-    /// Apply auto-detection to a line and return highlighted version
-    pub fn highlight_line(&self, line: &str) -> String {
-        let mut result = line.to_string();
+    /// Apply auto-detection to a line and return highlighted version, styled by
+    /// `renderer` (borrowed when nothing was detected)
+    pub fn highlight_line<'a>(&self, line: &'a str, renderer: &dyn Renderer) -> Cow<'a, str> {
+        // Patterns that can match the line as given: exact while nothing has styled it
+        // yet (text with escapes is matched per unstyled segment, so it gets no pre-check)
+        let hits = self.pattern_set.as_ref()
+            .filter(|_| self.is_unstyled(line, renderer))
+            .map(|set| set.matches(line));
+        let mut result = Cow::Borrowed(line);
         
         // Apply each pattern in sequence with icons
-        for (_name, regex, style, icon) in &self.patterns {
-            result = regex.replace_all(&result, |caps: &regex::Captures| {
-                format!("{} {}{}{}", icon, style, &caps[1], Colors::RESET)
-            }).to_string();
+        for (index, (name, regex, style, icon)) in self.patterns.iter().enumerate() {
+            if matches!(result, Cow::Borrowed(_)) && hits.as_ref().is_some_and(|hits| !hits.matched(index)) {
+                continue;
+            }
+            let replaced = self.replace_unstyled(&result, regex, renderer, |caps| {
+                if name == "filelines" {
+                    self.format_fileline(caps, style, renderer)
+                } else if icon.is_empty() {
+                    renderer.paint(style, styled_group(caps))
+                } else {
+                    format!("{} {}", icon, renderer.paint(style, styled_group(caps)))
+                }
+            });
+            if let Cow::Owned(replaced) = replaced {
+                result = Cow::Owned(replaced);
+            }
         }
         
+        match self.highlight_extras(&result, renderer) {
+            Cow::Owned(extended) => Cow::Owned(extended),
+            Cow::Borrowed(_) => result,
+        }
+    }
+    
+    /// Count built-in detections in a line as `(name, count, style)`, in detection order;
+    /// like `highlight_line`, text claimed by an earlier pattern isn't counted again
+    pub fn count_matches(&self, line: &str) -> Vec<(&str, usize, &Style)> {
+        let mut remaining = line.to_string();
+        self.patterns.iter().map(|(name, regex, style, _icon)| {
+            let mut count = 0;
+            remaining = regex.replace_all(&remaining, |caps: &regex::Captures| {
+                count += 1;
+                " ".repeat(caps[0].len())
+            }).to_string();
+            (name.as_str(), count, style)
+        }).collect()
+    }
+    
+    /// Apply the opt-in detectors to the parts of a line that carry no styling yet
+    pub fn highlight_extras<'a>(&self, line: &'a str, renderer: &dyn Renderer) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(line);
+        for (_name, regex, style, group) in &self.extras {
+            // Style the whole match, or just one capture group of it
+            let replaced = self.replace_unstyled(&result, regex, renderer, |caps| {
+                let whole = caps.get(0).unwrap();
+                let target = caps.get(*group).unwrap_or(whole);
+                let (start, end) = (target.start() - whole.start(), target.end() - whole.start());
+                let painted = match style {
+                    PatternStyle::Fixed(style) => renderer.paint(style, target.as_str()),
+                    PatternStyle::Hashed => renderer.paint(&Style::new().color(indexed_color_name(hash_color_index(target.as_str()))), target.as_str()),
+                };
+                format!("{}{}{}", &whole.as_str()[..start], painted, &whole.as_str()[end..])
+            });
+            if let Cow::Owned(replaced) = replaced {
+                result = Cow::Owned(replaced);
+            }
+        }
         result
     }
-}
\ No newline at end of file
+    
+    /// Path in `style`, `:line:col` dimmed, optionally wrapped in a hyperlink
+    fn format_fileline(&self, caps: &regex::Captures, style: &Style, renderer: &dyn Renderer) -> String {
+        let path = styled_group(caps);
+        let location = &caps[0][path.len()..];
+        let text = format!("{}{}", renderer.paint(style, path), renderer.paint(&Style::new().dim(), location));
+        
+        if self.hyperlinks {
+            let target = std::env::current_dir()
+                .map(|dir| dir.join(path))
+                .unwrap_or_else(|_| std::path::PathBuf::from(path));
+            format!("\x1b]8;;file://{}\x1b\\{}\x1b]8;;\x1b\\", target.display(), text)
+        } else {
+            text
+        }
+    }
+    
+    /// True when `text` holds no escape sequences, nor tags or entities of a markup `renderer`
+    fn is_unstyled(&self, text: &str, renderer: &dyn Renderer) -> bool {
+        !text.contains('\x1b') && (!renderer.is_markup() || !text.contains(['<', '&']))
+    }
+    
+    /// Replace regex matches, skipping text between a color code and its reset
+    /// (escape sequences, and a markup `renderer`'s tags and entities, are never
+    /// touched); borrowed when nothing matched
+    fn replace_unstyled<'a, F>(&self, text: &'a str, regex: &Regex, renderer: &dyn Renderer, render: F) -> Cow<'a, str>
+    where
+        F: Fn(&regex::Captures) -> String,
+    {
+        // Without escapes the text is one unstyled segment
+        if self.is_unstyled(text, renderer) {
+            return regex.replace_all(text, |caps: &regex::Captures| render(caps));
+        }
+        let escapes = match renderer.is_markup() {
+            true => &self.markup_pattern,
+            false => &self.ansi_pattern,
+        };
+        
+        let mut output = String::with_capacity(text.len());
+        let mut sgr = SgrState::default();
+        let mut last = 0;
+        
+        let push_segment = |output: &mut String, segment: &str, styled: bool| {
+            if styled {
+                output.push_str(segment);
+            } else {
+                output.push_str(&regex.replace_all(segment, |caps: &regex::Captures| render(caps)));
+            }
+        };
+        
+        for code in escapes.find_iter(text) {
+            push_segment(&mut output, &text[last..code.start()], sgr.is_styled());
+            output.push_str(code.as_str());
+            sgr.apply(code.as_str());
+            last = code.end();
+        }
+        push_segment(&mut output, &text[last..], sgr.is_styled());
+        
+        Cow::Owned(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extended_colors::{get_extended_color_code, hash_color_code};
+    use crate::text_styles::Ansi;
+    
+    #[test]
+    fn test_unicode_override() {
+        assert!(unicode_from_env(Some("1"), None, None, Some("C"), None));
+        assert!(!unicode_from_env(Some("0"), None, None, Some("en_US.UTF-8"), None));
+        // Unrecognized override values fall through to the locale
+        assert!(unicode_from_env(Some("maybe"), None, None, Some("en_US.UTF-8"), None));
+    }
+    
+    #[test]
+    fn test_unicode_locale_precedence() {
+        assert!(unicode_from_env(None, None, Some("en_US.UTF-8"), None, None));
+        assert!(unicode_from_env(None, None, None, Some("de_DE.utf8"), None));
+        assert!(!unicode_from_env(None, Some("C"), Some("en_US.UTF-8"), Some("en_US.UTF-8"), None));
+        assert!(unicode_from_env(None, Some(""), Some("en_US.UTF-8"), Some("C"), None));
+        assert!(!unicode_from_env(None, None, None, Some("POSIX"), Some("xterm-kitty")));
+    }
+    
+    #[test]
+    fn test_unicode_term_without_locale() {
+        assert!(unicode_from_env(None, None, None, None, Some("xterm-kitty")));
+        assert!(!unicode_from_env(None, None, None, None, Some("linux")));
+        assert!(!unicode_from_env(None, None, None, None, None));
+    }
+    
+    fn extras(names: &[&str]) -> AutoDetector {
+        let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+        AutoDetector::with_icon_mode(IconMode::None).unwrap().with_extra_detectors(&names).unwrap()
+    }
+    
+    #[test]
+    fn test_numbers_detector() {
+        let detector = extras(&["numbers"]);
+        assert_eq!(detector.highlight_line("x=42", &Ansi::default()), format!("x={}42{}", Colors::VIOLET, Colors::RESET));
+        // Versions are claimed first; their digits are not re-colored
+        let output = detector.highlight_line("v1.2.3", &Ansi::default());
+        assert!(!output.contains(Colors::VIOLET));
+    }
+    
+    #[test]
+    fn test_strings_detector() {
+        let detector = extras(&["numbers", "strings"]);
+        let output = detector.highlight_line(r#"msg="hello \"world\"" n=7"#, &Ansi::default());
+        assert_eq!(output, format!(
+            r#"msg={}"hello \"world\""{} n={}7{}"#,
+            Colors::AMBER, Colors::RESET, Colors::VIOLET, Colors::RESET
+        ));
+    }
+    
+    #[test]
+    fn test_keyval_detector() {
+        let detector = extras(&["keyval"]);
+        let key = |k: &str| format!("{}{}{}", Colors::AZURE, k, Colors::RESET);
+        
+        let output = detector.highlight_line(r#"level=info msg="disk almost full" dur=3ms"#, &Ansi::default());
+        assert_eq!(output, format!(r#"{}=info {}="disk almost full" {}=3ms"#, key("level"), key("msg"), key("dur")));
+        
+        assert_eq!(detector.highlight_line("if a==b", &Ansi::default()), "if a==b");
+        assert_eq!(detector.highlight_line("see example.com/?a=1&b=2", &Ansi::default()), "see example.com/?a=1&b=2");
+    }
+    
+    #[test]
+    fn test_filelines_detector() {
+        let detector = AutoDetector::with_icon_mode(IconMode::None).unwrap();
+        assert_eq!(
+            detector.highlight_line("error at foo.rs:1:2", &Ansi::default()),
+            format!("error at {}foo.rs{}{}:1:2{}", Colors::AZURE, Colors::RESET, Colors::DIM, Colors::RESET)
+        );
+        // Versions are still versions
+        assert_eq!(
+            detector.highlight_line("jynx 1.2.3", &Ansi::default()),
+            format!("jynx {}{}1.2.3{}", Colors::EMERALD, Colors::BOLD, Colors::RESET)
+        );
+        
+        let linked = detector.with_hyperlinks(true).highlight_line("src/main.rs:42", &Ansi::default());
+        assert!(linked.starts_with("\x1b]8;;file://") && linked.contains("src/main.rs\x1b\\"));
+        assert!(linked.ends_with("\x1b]8;;\x1b\\"));
+    }
+    
+    #[test]
+    fn test_match_pattern() {
+        let config = DetectorConfig { match_patterns: vec![r"timeout|refused".to_string()], ..Default::default() };
+        let detector = AutoDetector::from_config(&config).unwrap();
+        let yellow = format!("{}{}", get_extended_color_code("yellow"), Colors::BOLD);
+        assert_eq!(
+            detector.highlight_line("connection refused", &Ansi::default()),
+            format!("connection {}refused{}", yellow, Colors::RESET)
+        );
+    }
+    
+    #[test]
+    fn test_multiple_match_patterns() {
+        let mut config = DetectorConfig {
+            match_patterns: vec!["alpha".to_string(), "beta".to_string()],
+            ..Default::default()
+        };
+        let paint = |color: &str, text: &str| format!("{}{}{}{}", get_extended_color_code(color), Colors::BOLD, text, Colors::RESET);
+        
+        // Colors cycle per pattern...
+        let output = AutoDetector::from_config(&config).unwrap().highlight_line("alpha beta", &Ansi::default());
+        assert_eq!(output, format!("{} {}", paint("yellow", "alpha"), paint("magenta", "beta")));
+        
+        // ...unless one color is given for all
+        config.match_color = Some("crimson".to_string());
+        let output = AutoDetector::from_config(&config).unwrap().highlight_line("alpha beta", &Ansi::default());
+        assert_eq!(output, format!("{} {}", paint("crimson", "alpha"), paint("crimson", "beta")));
+        
+        config.match_color = Some("not-a-color".to_string());
+        assert!(AutoDetector::from_config(&config).is_err());
+    }
+    
+    #[test]
+    fn test_hash_color_is_stable() {
+        let config = DetectorConfig { hash_patterns: vec![r"req-\w+".to_string()], ..Default::default() };
+        let detector = AutoDetector::from_config(&config).unwrap();
+        let output = detector.highlight_line("req-abc start, req-xyz start, req-abc done", &Ansi::default());
+        
+        let expected = format!("{}req-abc{}", hash_color_code("req-abc"), Colors::RESET);
+        assert_eq!(output.matches(&expected).count(), 2);
+        assert!(output.contains(&format!("{}req-xyz{}", hash_color_code("req-xyz"), Colors::RESET)));
+    }
+    
+    #[test]
+    fn test_unknown_detector() {
+        let result = AutoDetector::new().unwrap().with_extra_detectors(&["colours".to_string()]);
+        assert!(result.is_err());
+    }
+    
+    #[test]
+    fn test_unicode_arg() {
+        assert_eq!(IconMode::from_unicode_arg("on"), Some(IconMode::Unicode));
+        assert_eq!(IconMode::from_unicode_arg("off"), Some(IconMode::Ascii));
+        assert_eq!(IconMode::from_unicode_arg("sometimes"), None);
+    }
+    
+    #[test]
+    fn test_unmatched_line_is_borrowed() {
+        let detector = AutoDetector::with_icon_mode(IconMode::None).unwrap();
+        assert!(matches!(detector.highlight_line("nothing to see here", &Ansi::default()), Cow::Borrowed(_)));
+        
+        // Only the hit patterns run, and later ones still see the earlier styling
+        let output = detector.highlight_line("get https://example.com/v1.2.3 then 2.0.0", &Ansi::default());
+        assert_eq!(output.matches(Colors::RESET).count(), 2, "{:?}", output);
+        assert!(output.ends_with(&format!("{}{}2.0.0{}", Colors::EMERALD, Colors::BOLD, Colors::RESET)));
+    }
+    
+    #[test]
+    fn test_pattern_set_uses_theme_limits() {
+        let words = |prefix: &str| (0..100).map(|i| format!("{}{}", prefix, i)).collect::<Vec<_>>().join("|");
+        let style = Style::new().color("crimson");
+        let patterns = vec![
+            ("alpha".to_string(), words("alpha"), style.clone()),
+            ("beta".to_string(), words("beta"), style.clone()),
+        ];
+        let build = |limits: &RegexLimits| AutoDetector::with_icon_mode(IconMode::None).unwrap()
+            .with_theme_patterns(&patterns, IconMode::None, limits).unwrap();
+        assert!(build(&RegexLimits::default()).pattern_set.is_some());
+        
+        // Each pattern fits the theme's limit but the set doesn't: detection still runs, unfiltered
+        let detector = build(&RegexLimits { size_limit: 50_000, ..Default::default() });
+        assert!(detector.pattern_set.is_none());
+        let output = detector.highlight_line("alpha7 and beta3", &Ansi::default());
+        assert_eq!(output.matches(Colors::RESET).count(), 2, "{:?}", output);
+    }
+}
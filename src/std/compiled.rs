@@ -2,12 +2,21 @@
 //! 
 //! Pre-compiles regex patterns and stores them in optimized binary format
 
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::time::SystemTime;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 use serde::{Deserialize, Serialize};
 use regex::{Regex, RegexSet};
-use crate::std::theme::{Theme, Filter, IconMapping, StyleGroup, AutoDetectionPattern};
-use crate::extended_colors::get_extended_color_code;
+use crate::std::theme::{describe_suffix, Anchor, Theme, Filter, IconMapping, StyleGroup, AutoDetectionPattern, ProcessingPath};
+use crate::text_styles::{Renderer, Style};
+use crate::std::RegexLimits;
+use crate::std::auto_detect::{IconMode, Icons};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::std::ThemeCache;
 
 /// Compiled theme with pre-optimized regex patterns
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +31,48 @@ pub struct CompiledTheme {
     
     // Compiled filters
     pub filters: HashMap<String, CompiledFilter>,
+    
+    // Size caps every pattern is compiled under (from the theme's `compilation:`)
+    #[serde(default)]
+    pub regex_limits: RegexLimits,
+    
+    // Runtime option: how icons are rendered
+    #[serde(skip)]
+    pub icon_mode: IconMode,
+    
+    // Runtime option: skip the auto-detection patterns (`--no-detect`)
+    #[serde(skip)]
+    pub no_detect: bool,
+    
+    // Runtime option: keyword styling growth cap per line (`--max-expand`)
+    #[serde(skip, default = "default_max_expand")]
+    pub max_expand: usize,
+    
+    // Runtime option: leave already styled text alone (`--idempotent`)
+    #[serde(skip)]
+    pub idempotent: bool,
+}
+
+fn default_max_expand() -> usize {
+    crate::std::DEFAULT_MAX_EXPAND
+}
+
+/// Scale and cost of compiling a theme (`jynx theme compile --report`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileReport {
+    pub auto_detection_patterns: usize,
+    pub keyword_groups: usize,
+    pub keywords: usize,
+    pub duration: Duration,
+}
+
+impl std::fmt::Display for CompileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "auto_detection patterns: {}", self.auto_detection_patterns)?;
+        writeln!(f, "keyword groups: {}", self.keyword_groups)?;
+        writeln!(f, "keywords: {}", self.keywords)?;
+        writeln!(f, "compile time: {:.3}ms", self.duration.as_secs_f64() * 1000.0)
+    }
 }
 
 /// Compiled auto-detection pattern with regex and styling
@@ -31,8 +82,12 @@ pub struct CompiledAutoPattern {
     pub pattern_str: String, // Store pattern string for serialization
     #[serde(skip)]
     pub regex: Option<Regex>, // Runtime compiled regex
-    pub ansi_style: String,
+    pub style: Style,
     pub icon: Option<String>,
+    #[serde(default)]
+    pub icon_fallback: Option<String>, // Replaces `icon` in ASCII mode
+    #[serde(default)]
+    pub anchor: Option<Anchor>, // Applied around the capture group in `compile_regex`
 }
 
 /// Compiled filter with optimized pattern matching
@@ -48,14 +103,25 @@ pub struct CompiledFilter {
     pub pattern_set_str: Vec<String>, // Store pattern strings for serialization
     #[serde(skip)]
     pub pattern_set: Option<RegexSet>, // Runtime compiled regex set
+    
+    // Spans keywords never match in (`skip_regions`, joined into one pattern)
+    #[serde(default)]
+    pub skip_regions_str: Option<String>,
+    #[serde(skip)]
+    pub skip_regions: Option<Regex>,
+    
+    // Shown by `theme info`; no effect on highlighting
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 /// Compiled icon mapping with pre-formatted output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledIconMapping {
     pub icon: String,
-    pub color_ansi: String,
-    pub formatted_template: String, // Pre-built template: "{color}{icon} {word}{reset}"
+    #[serde(default)]
+    pub icon_fallback: Option<String>,
+    pub style: Style,
 }
 
 /// Compiled keyword pattern with regex and styling
@@ -64,8 +130,14 @@ pub struct CompiledKeywordPattern {
     pub pattern_str: String, // Store for serialization
     #[serde(skip)]
     pub regex: Option<Regex>, // Runtime compiled regex
-    pub ansi_style: String,
+    pub style: Style,
     pub keywords: Vec<String>, // Original keywords for reference
+    #[serde(default)]
+    pub replace_with: Option<String>, // Text substituted for a match (`{}` = the match)
+    #[serde(default)]
+    pub icon: Option<String>, // Leads every match, outside the color
+    #[serde(default)]
+    pub icon_fallback: Option<String>, // Replaces `icon` in ASCII mode
 }
 
 impl CompiledTheme {
@@ -77,10 +149,15 @@ impl CompiledTheme {
             compiled_at: SystemTime::now(),
             auto_detection: Vec::new(),
             filters: HashMap::new(),
+            regex_limits: theme.regex_limits(),
+            icon_mode: IconMode::default(),
+            no_detect: false,
+            max_expand: default_max_expand(),
+            idempotent: false,
         };
         
-        // Compile auto-detection patterns
-        for (name, pattern) in &theme.auto_detection {
+        // Compile auto-detection patterns (legacy-only ones are left out)
+        for (name, pattern) in theme.auto_detection.iter().filter(|(_, p)| p.enabled_for(ProcessingPath::Compiled)) {
             let compiled_pattern = CompiledAutoPattern::from_auto_pattern(name, pattern)?;
             compiled_theme.auto_detection.push(compiled_pattern);
         }
@@ -94,117 +171,266 @@ impl CompiledTheme {
         Ok(compiled_theme)
     }
     
+    /// Render icons in the given mode (emoji, text fallback or none)
+    pub fn with_icon_mode(mut self, icon_mode: IconMode) -> Self {
+        self.icon_mode = icon_mode;
+        self
+    }
+    
+    /// Apply or skip the theme's auto-detection patterns
+    pub fn with_auto_detection(mut self, enabled: bool) -> Self {
+        self.no_detect = !enabled;
+        self
+    }
+    
+    /// Cap keyword styling growth per line (0 disables the cap)
+    pub fn with_max_expand(mut self, max_expand: usize) -> Self {
+        self.max_expand = max_expand;
+        self
+    }
+    
+    /// Skip text that is already styled when detecting and highlighting keywords
+    pub fn with_idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = idempotent;
+        self
+    }
+    
+    /// Compile `theme` including its regexes, timed, without touching the cache
+    pub fn compile_report(theme: &Theme) -> Result<CompileReport, Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let mut compiled = Self::from_theme(theme)?;
+        compiled.init_runtime()?;
+        let duration = start.elapsed();
+        
+        let patterns = compiled.filters.values().flat_map(|filter| &filter.keyword_patterns);
+        Ok(CompileReport {
+            auto_detection_patterns: compiled.auto_detection.len(),
+            keyword_groups: patterns.clone().count(),
+            keywords: patterns.map(|pattern| pattern.keywords.len()).sum(),
+            duration,
+        })
+    }
+    
+    /// Human-readable compile metadata: version, checksum, timestamp and per-filter counts
+    pub fn describe(&self) -> String {
+        let compiled_at = self.compiled_at
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut out = format!(
+            "jynx version: {}\nsource_checksum: {:016x}\ncompiled_at: {} (unix seconds)\nauto_detection patterns: {}\n",
+            self.version, self.source_checksum, compiled_at, self.auto_detection.len()
+        );
+        
+        let mut names: Vec<&String> = self.filters.keys().collect();
+        names.sort();
+        for name in names {
+            let filter = &self.filters[name];
+            let keywords: usize = filter.keyword_patterns.iter().map(|pattern| pattern.keywords.len()).sum();
+            out.push_str(&format!(
+                "filter {}: {} keyword patterns ({} keywords), {} icon mappings{}\n",
+                name, filter.keyword_patterns.len(), keywords, filter.icon_mappings.len(),
+                describe_suffix(&filter.description)
+            ));
+        }
+        out
+    }
+    
     /// Initialize runtime regex compilation after deserialization
+    /// Errors name the pattern that failed, e.g. `auto_detection 'tickets' (...)`
     pub fn init_runtime(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Compile auto-detection patterns
         for pattern in &mut self.auto_detection {
-            pattern.compile_regex()?;
+            pattern.compile_regex(&self.regex_limits)
+                .map_err(|e| format!("auto_detection '{}' (`{}`): {}", pattern.name, pattern.pattern_str, e))?;
         }
         
         // Compile filter patterns
-        for filter in self.filters.values_mut() {
-            filter.compile_patterns()?;
+        for (name, filter) in self.filters.iter_mut() {
+            filter.compile_patterns(&self.regex_limits).map_err(|e| format!("filter '{}': {}", name, e))?;
         }
         
         Ok(())
     }
     
     /// Calculate checksum for theme change detection
+    ///
+    /// Hashes the whole serialized theme so any edit invalidates cached compilations.
     fn calculate_theme_checksum(theme: &Theme) -> u64 {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
         
         let mut hasher = DefaultHasher::new();
-        
-        // Hash theme structure (simplified)
-        theme.metadata.name.hash(&mut hasher);
-        theme.metadata.version.hash(&mut hasher);
-        
-        // Hash auto-detection patterns
-        for (name, pattern) in &theme.auto_detection {
-            name.hash(&mut hasher);
-            pattern.pattern.hash(&mut hasher);
-            pattern.color.hash(&mut hasher);
-        }
-        
-        // Hash filters
-        for (filter_name, filter) in &theme.filters {
-            filter_name.hash(&mut hasher);
-            
-            // Hash icon mappings
-            for (icon_key, mapping) in &filter.icon_mappings {
-                icon_key.hash(&mut hasher);
-                mapping.icon.hash(&mut hasher);
-                mapping.color.hash(&mut hasher);
-            }
-            
-            // Hash styles
-            for (style_key, style) in &filter.styles {
-                style_key.hash(&mut hasher);
-                style.keywords.hash(&mut hasher);
-                style.color.hash(&mut hasher);
-            }
-        }
-        
+        // Theme maps are ordered, so serialization is deterministic
+        serde_json::to_string(theme).unwrap_or_default().hash(&mut hasher);
         hasher.finish()
     }
     
-    /// High-performance text processing using compiled patterns
-    pub fn process_text(&self, text: &str, filter_name: &str) -> String {
-        let mut result = text.to_string();
+    /// High-performance text processing using compiled patterns, styled by `renderer`
+    /// (with no filter, only the theme's auto-detection applies);
+    /// borrowed when no pattern matched
+    pub fn process_text<'a>(&self, text: &'a str, filter_name: Option<&str>, renderer: &dyn Renderer) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(text);
         
         // Apply auto-detection first
-        for pattern in &self.auto_detection {
+        let auto_detection: &[CompiledAutoPattern] = if self.no_detect { &[] } else { &self.auto_detection };
+        for pattern in auto_detection {
             if let Some(ref regex) = pattern.regex {
-                result = regex.replace_all(&result, |caps: &regex::Captures| {
+                let render = |caps: &regex::Captures| {
                     let matched = caps.get(1).map_or(caps.get(0).unwrap().as_str(), |m| m.as_str());
-                    if let Some(ref icon) = pattern.icon {
-                        format!("{} {}{}{}", icon, pattern.ansi_style, matched, "\x1b[0m")
-                    } else {
-                        format!("{}{}{}", pattern.ansi_style, matched, "\x1b[0m")
+                    let icon = pattern.icon.as_deref()
+                        .map(|icon| self.icon_mode.pick(icon, pattern.icon_fallback.as_deref().unwrap_or_default()))
+                        .filter(|icon| !icon.is_empty());
+                    match icon {
+                        Some(icon) => format!("{} {}", icon, renderer.paint(&pattern.style, matched)),
+                        None => renderer.paint(&pattern.style, matched),
                     }
-                }).to_string();
+                };
+                // Segmented to keep off styled text (`--idempotent`) and off markup
+                let segmented = self.idempotent || renderer.is_markup();
+                let replaced = match !segmented || crate::std::unsegmented(&result, None, self.idempotent, renderer.is_markup()) {
+                    true => regex.replace_all(&result, &render),
+                    false => Cow::Owned(crate::std::skip_region_segments(&result, None, self.idempotent, renderer.is_markup()).into_iter()
+                        .map(|(segment, skipped)| match skipped {
+                            true => segment,
+                            false => regex.replace_all(&segment, &render).into_owned(),
+                        })
+                        .collect()),
+                };
+                if let Cow::Owned(replaced) = replaced {
+                    result = Cow::Owned(replaced);
+                }
             }
         }
         
-        // Apply filter-specific processing
-        if let Some(filter) = self.filters.get(filter_name) {
-            // Apply icon mappings first
-            let icon_regex = Regex::new(r":([a-zA-Z_][a-zA-Z0-9_]*):").unwrap();
-            result = icon_regex.replace_all(&result, |caps: &regex::Captures| {
-                let word = &caps[1];
-                if let Some(mapping) = filter.icon_mappings.get(word) {
-                    // Use pre-compiled template
-                    mapping.formatted_template.replace("{word}", word)
-                } else {
-                    caps[0].to_string()
+        // Apply filter-specific processing (without a filter: auto-detection only)
+        if let Some(filter) = filter_name.and_then(|name| self.filters.get(name)) {
+            // Apply icon mappings first (without mappings or a `:` there is nothing to replace)
+            if !filter.icon_mappings.is_empty() && result.contains(':') {
+                let replaced = crate::std::icon_word_regex().replace_all(&result, |caps: &regex::Captures| {
+                    let word = &caps[1];
+                    if let Some(mapping) = filter.icon_mappings.get(word) {
+                        mapping.render(word, self.icon_mode, renderer)
+                    } else {
+                        caps[0].to_string()
+                    }
+                });
+                if let Cow::Owned(replaced) = replaced {
+                    result = Cow::Owned(replaced);
                 }
-            }).to_string();
+            }
+            
+            // A line that is one segment and matches no keyword comes out unchanged
+            if crate::std::unsegmented(&result, filter.skip_regions.as_ref(), self.idempotent, renderer.is_markup())
+                && !filter.pattern_set.as_ref().is_some_and(|set| set.is_match(&result)) {
+                return result;
+            }
             
-            // Apply keyword highlighting
+            // Apply keyword highlighting, outside skip_regions
+            let unstyled = result;
+            let mut segments = crate::std::skip_region_segments(&unstyled, filter.skip_regions.as_ref(), self.idempotent, renderer.is_markup());
             for pattern in &filter.keyword_patterns {
                 if let Some(ref regex) = pattern.regex {
-                    result = regex.replace_all(&result, |caps: &regex::Captures| {
-                        let matched = &caps[0];
-                        format!("{}{}{}", pattern.ansi_style, matched, "\x1b[0m")
-                    }).to_string();
+                    segments = crate::std::replace_in_segments(segments, regex, self.idempotent, renderer.is_markup(), |caps| {
+                        StyleGroup::styled_match(&pattern.style, pattern.icon.as_deref(), pattern.icon_fallback.as_deref(),
+                            pattern.replace_with.as_deref(), &caps[0], self.icon_mode, renderer)
+                    });
+                    let styled_len: usize = segments.iter().map(|(segment, _)| segment.len()).sum();
+                    if crate::std::expansion_exceeded(unstyled.len(), styled_len, self.max_expand) {
+                        return unstyled;
+                    }
                 }
             }
+            result = Cow::Owned(segments.into_iter().map(|(segment, _)| segment).collect());
         }
         
         result
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl CompiledTheme {
+    /// Cache file for a theme, keyed by source checksum so edits miss the cache
+    pub fn cache_path(theme: &Theme, cache_dir: &Path) -> PathBuf {
+        cache_dir.join(format!("{:016x}.json", Self::calculate_theme_checksum(theme)))
+    }
+    
+    /// Load a cached compilation if it matches this theme and jynx version
+    pub fn load_cached(theme: &Theme, cache_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(Self::cache_path(theme, cache_dir)).ok()?;
+        let mut compiled: Self = serde_json::from_str(&content).ok()?;
+        if compiled.version != env!("CARGO_PKG_VERSION") || compiled.source_checksum != Self::calculate_theme_checksum(theme) {
+            return None;
+        }
+        compiled.init_runtime().ok()?;
+        Some(compiled)
+    }
+    
+    /// Serialize to a compact binary blob, e.g. for embedding with `include_bytes!`
+    ///
+    /// Only pattern strings are stored: the `#[serde(skip)]` regexes are rebuilt
+    /// by `from_bytes`, and runtime options (icon mode, `--no-detect`,
+    /// `--max-expand`) are not part of the blob
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(bincode::serialize(self)?)
+    }
+    
+    /// Load a blob written by `to_bytes`, recompiling its regexes (`init_runtime`)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut compiled: Self = bincode::deserialize(bytes)?;
+        compiled.init_runtime()?;
+        Ok(compiled)
+    }
+    
+    /// Write this compilation to the cache directory
+    pub fn save_cache(&self, theme: &Theme, cache_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        fs::create_dir_all(cache_dir)?;
+        let path = Self::cache_path(theme, cache_dir);
+        fs::write(&path, serde_json::to_string(self)?)?;
+        Ok(path)
+    }
+    
+    /// Compile through the cache: a valid cached copy is reused unless
+    /// `recompile` is set; fresh compilations are written back (best effort)
+    pub fn load_or_compile(theme: &Theme, cache: &ThemeCache) -> Result<Self, Box<dyn std::error::Error>> {
+        if !cache.recompile {
+            if let Some(compiled) = Self::load_cached(theme, &cache.dir) {
+                return Ok(compiled);
+            }
+        }
+        
+        let mut compiled = Self::from_theme(theme)?;
+        compiled.init_runtime()?;
+        let _ = compiled.save_cache(theme, &cache.dir);
+        Ok(compiled)
+    }
+    
+    /// Delete all cached compiled themes, returning how many were removed
+    pub fn clear_cache(cache_dir: &Path) -> std::io::Result<usize> {
+        if !cache_dir.exists() {
+            return Ok(0);
+        }
+        
+        let mut removed = 0;
+        for entry in fs::read_dir(cache_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
 impl CompiledAutoPattern {
     fn from_auto_pattern(name: &str, pattern: &AutoDetectionPattern) -> Result<Self, Box<dyn std::error::Error>> {
-        let ansi_style = pattern.to_ansi();
-        
-        // Determine if this pattern should have an icon (based on auto-detection type)
-        let icon = match name {
-            "paths" => Some("📁".to_string()),
-            "versions" => Some("🏷️".to_string()),
-            "urls" => Some("🔗".to_string()),
+        // Determine if this pattern should have an icon (based on auto-detection type),
+        // with the same ASCII fallback the built-in detector uses
+        let icons = match name {
+            "paths" => Some((Icons::PATH, Icons::PATH_FALLBACK)),
+            "versions" => Some((Icons::VERSION, Icons::VERSION_FALLBACK)),
+            "urls" => Some((Icons::URL, Icons::URL_FALLBACK)),
             _ => None,
         };
         
@@ -212,20 +438,27 @@ impl CompiledAutoPattern {
             name: name.to_string(),
             pattern_str: pattern.pattern.clone(),
             regex: None, // Will be compiled at runtime
-            ansi_style,
-            icon,
+            style: pattern.style(),
+            icon: icons.map(|(icon, _)| icon.to_string()),
+            icon_fallback: icons.map(|(_, fallback)| fallback.to_string()),
+            anchor: pattern.anchor,
         })
     }
     
-    fn compile_regex(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn compile_regex(&mut self, limits: &RegexLimits) -> Result<(), Box<dyn std::error::Error>> {
         // Wrap pattern to capture the match
         let capture_pattern = if !self.pattern_str.contains('(') {
             format!("({})", self.pattern_str)
         } else {
             self.pattern_str.clone()
         };
+        let capture_pattern = match self.anchor {
+            Some(anchor) => anchor.wrap(&capture_pattern),
+            None => capture_pattern,
+        };
         
-        self.regex = Some(Regex::new(&capture_pattern)?);
+        self.regex = Some(limits.compile(&capture_pattern)
+            .map_err(|e| format!("auto-detection pattern '{}': {}", self.name, e))?);
         Ok(())
     }
 }
@@ -237,6 +470,9 @@ impl CompiledFilter {
             keyword_patterns: Vec::new(),
             pattern_set_str: Vec::new(),
             pattern_set: None,
+            skip_regions_str: None,
+            skip_regions: None,
+            description: filter.description.clone(),
         };
         
         // Compile icon mappings
@@ -246,18 +482,25 @@ impl CompiledFilter {
         }
         
         // Compile keyword patterns
-        for (_style_name, style) in &filter.styles {
+        for style in filter.styles.values() {
             let compiled_pattern = CompiledKeywordPattern::from_style_group(style)?;
             compiled_filter.keyword_patterns.push(compiled_pattern);
         }
         
+        compiled_filter.skip_regions_str = filter.skip_pattern();
+        
         Ok(compiled_filter)
     }
     
-    fn compile_patterns(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn compile_patterns(&mut self, limits: &RegexLimits) -> Result<(), Box<dyn std::error::Error>> {
         // Compile individual regex patterns
         for pattern in &mut self.keyword_patterns {
-            pattern.compile_regex()?;
+            pattern.compile_regex(limits)
+                .map_err(|e| format!("keywords {:?}: {}", pattern.keywords, e))?;
+        }
+        
+        if let Some(pattern) = &self.skip_regions_str {
+            self.skip_regions = Some(limits.compile(pattern).map_err(|e| format!("skip_regions: {}", e))?);
         }
         
         // Build pattern set strings
@@ -268,7 +511,7 @@ impl CompiledFilter {
         
         // Compile regex set for bulk matching optimization
         if !self.pattern_set_str.is_empty() {
-            self.pattern_set = Some(RegexSet::new(&self.pattern_set_str)?);
+            self.pattern_set = Some(limits.compile_set(&self.pattern_set_str)?);
         }
         
         Ok(())
@@ -277,33 +520,30 @@ impl CompiledFilter {
 
 impl CompiledIconMapping {
     fn from_icon_mapping(mapping: &IconMapping) -> Self {
-        let color_ansi = get_extended_color_code(&mapping.color);
-        let formatted_template = format!("{}{} {{word}}\x1b[0m", color_ansi, mapping.icon);
-        
         CompiledIconMapping {
             icon: mapping.icon.clone(),
-            color_ansi: color_ansi.to_string(),
-            formatted_template,
+            icon_fallback: mapping.icon_fallback.clone(),
+            style: Style::new().color(&mapping.color),
+        }
+    }
+    
+    /// Render ":word:" as "{color}{icon} {word}{reset}", or just the colored word
+    pub fn render(&self, word: &str, icon_mode: IconMode, renderer: &dyn Renderer) -> String {
+        let icon = icon_mode.pick(&self.icon, self.icon_fallback.as_deref().unwrap_or(&self.icon));
+        if icon.is_empty() {
+            renderer.paint(&self.style, word)
+        } else {
+            renderer.paint(&self.style, &format!("{} {}", icon, word))
         }
     }
 }
 
 impl CompiledKeywordPattern {
     fn from_style_group(style: &StyleGroup) -> Result<Self, Box<dyn std::error::Error>> {
-        let ansi_style = style.to_ansi();
-        
         // Create unified pattern for all keywords in this style group
         let escaped_keywords: Vec<String> = style.keywords
             .iter()
-            .map(|k| {
-                if k.contains(":") || k.contains(" ") {
-                    // Literal matching for phrases
-                    format!("(?i){}", regex::escape(k))
-                } else {
-                    // Word boundary matching for single words
-                    format!(r"(?i)\b{}\b", regex::escape(k))
-                }
-            })
+            .map(|k| StyleGroup::keyword_regex(k))
             .collect();
         
         let pattern_str = format!("({})", escaped_keywords.join("|"));
@@ -311,13 +551,163 @@ impl CompiledKeywordPattern {
         Ok(CompiledKeywordPattern {
             pattern_str,
             regex: None, // Will be compiled at runtime
-            ansi_style,
+            style: style.style(),
             keywords: style.keywords.clone(),
+            replace_with: style.replace_with.clone(),
+            icon: style.icon.clone(),
+            icon_fallback: style.icon_fallback.clone(),
         })
     }
     
-    fn compile_regex(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.regex = Some(Regex::new(&self.pattern_str)?);
+    fn compile_regex(&mut self, limits: &RegexLimits) -> Result<(), Box<dyn std::error::Error>> {
+        self.regex = Some(limits.compile(&self.pattern_str)
+            .map_err(|e| format!("keywords {:?}: {}", self.keywords, e))?);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::text_styles::{Ansi, Html};
+    
+    #[test]
+    fn test_regex_size_limit_from_theme() {
+        let keywords = (0..2000).map(|i| format!("\"kw{}\"", i)).collect::<Vec<_>>().join(", ");
+        let theme = Theme::from_yaml_str(&format!(r#"
+metadata: {{ name: "big", version: "1.0.0", description: "oversized alternation" }}
+compilation: {{ optimize_for: "speed", pattern_limit: 100, enable_fast_lookup: true, regex_size_limit: 10000 }}
+filters:
+  logs:
+    styles:
+      everything: {{ keywords: [{}], color: "crimson" }}
+"#, keywords)).unwrap();
+        
+        let mut compiled = CompiledTheme::from_theme(&theme).unwrap();
+        let err = compiled.init_runtime().unwrap_err().to_string();
+        assert!(err.contains("size limit"), "{}", err);
+    }
+    
+    #[test]
+    fn test_bytes_round_trip() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "blob", version: "1.0.0", description: "embedded" }
+filters:
+  logs:
+    skip_regions: ['"[^"]*"']
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson", bold: true }
+      secrets: { keywords: ["token"], color: "slate", replace_with: "***" }
+"#).unwrap();
+        let mut compiled = CompiledTheme::from_theme(&theme).unwrap();
+        compiled.init_runtime().unwrap();
+        let line = r#"ERROR token "ERROR token" ERROR"#;
+        
+        let loaded = CompiledTheme::from_bytes(&compiled.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.process_text(line, Some("logs"), &Ansi::default()), compiled.process_text(line, Some("logs"), &Ansi::default()));
+        assert_eq!(loaded.compiled_at, compiled.compiled_at);
+        assert!(CompiledTheme::from_bytes(b"not a theme").is_err());
+    }
+    
+    #[test]
+    fn test_process_text_with_renderer() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "html", version: "1.0.0", description: "renderer" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson", bold: true }
+"#).unwrap();
+        let mut compiled = CompiledTheme::from_theme(&theme).unwrap();
+        compiled.init_runtime().unwrap();
+        let style = theme.filters["logs"].styles["errors"].style();
+        
+        assert_eq!(compiled.process_text("an ERROR", Some("logs"), &Html), format!("an {}", Html.paint(&style, "ERROR")));
+        assert_eq!(compiled.process_text("an ERROR", Some("logs"), &Ansi::default()), format!("an {}", style.paint("ERROR")));
+    }
+    
+    #[test]
+    fn test_checksum_stable_across_loads() {
+        let path = std::env::temp_dir().join(format!("jynx-checksum-{}.yml", std::process::id()));
+        fs::write(&path, r#"
+metadata: { name: "sum", version: "1.0.0", description: "checksum" }
+filters:
+  logs:
+    description: "Application logs"
+    styles:
+      errors: { keywords: ["ERROR", "FATAL"], color: "crimson" }
+"#).unwrap();
+        
+        let first = CompiledTheme::from_theme(&Theme::load_from_file(&path).unwrap()).unwrap();
+        let second = CompiledTheme::from_theme(&Theme::load_from_file(&path).unwrap()).unwrap();
+        assert_eq!(first.source_checksum, second.source_checksum);
+        assert_ne!(first.source_checksum, CompiledTheme::from_theme(&Theme::default()).unwrap().source_checksum);
+        assert!(first.describe().contains("filter logs: 1 keyword patterns (2 keywords), 0 icon mappings - Application logs\n"));
+        
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_compile_report_counts() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "report", version: "1.0.0", description: "counts" }
+auto_detection:
+  ips: { pattern: '\b\d+\.\d+\.\d+\.\d+\b', color: "cyan" }
+  tickets: { pattern: '\b[A-Z]+-\d+\b', color: "amber" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR", "FATAL"], color: "crimson" }
+      warnings: { keywords: ["WARN"], color: "amber" }
+  build:
+    styles:
+      done: { keywords: ["ok", "passed", "built"], color: "emerald" }
+"#).unwrap();
+        let report = CompiledTheme::compile_report(&theme).unwrap();
+        assert_eq!((report.auto_detection_patterns, report.keyword_groups, report.keywords), (2, 3, 6));
+        assert!(report.to_string().contains("keyword groups: 3\nkeywords: 6\n"));
+        
+        let broken = Theme::from_yaml_str(r#"
+metadata: { name: "broken", version: "1.0.0", description: "bad regex" }
+auto_detection:
+  bad: { pattern: '(unclosed', color: "cyan" }
+filters: {}
+"#).unwrap();
+        assert!(CompiledTheme::compile_report(&broken).is_err());
+    }
+    
+    #[test]
+    fn test_recompile_refreshes_cache() {
+        let dir = std::env::temp_dir().join(format!("jynx-cache-{}", std::process::id()));
+        let theme = Theme::default();
+        let cache = ThemeCache::new(Some(dir.clone()));
+        
+        let first = CompiledTheme::load_or_compile(&theme, &cache).unwrap();
+        assert!(CompiledTheme::cache_path(&theme, &dir).exists());
+        
+        // The cache is keyed by a checksum that tells themes apart
+        let mut edited = Theme::default();
+        edited.metadata.name.push_str("-edited");
+        assert_eq!(first.source_checksum, CompiledTheme::calculate_theme_checksum(&theme));
+        assert_ne!(first.source_checksum, CompiledTheme::calculate_theme_checksum(&edited));
+        
+        // Mark the cached copy so reuse and recompilation are told apart without timing
+        let mut marked = first.clone();
+        marked.compiled_at = SystemTime::UNIX_EPOCH;
+        marked.save_cache(&theme, &dir).unwrap();
+        
+        // Cached copy is reused as-is
+        let cached = CompiledTheme::load_or_compile(&theme, &cache).unwrap();
+        assert_eq!(cached.compiled_at, SystemTime::UNIX_EPOCH);
+        assert_eq!(cached.source_checksum, first.source_checksum);
+        
+        let fresh = CompiledTheme::load_or_compile(&theme, &cache.clone().with_recompile(true)).unwrap();
+        assert_ne!(fresh.compiled_at, SystemTime::UNIX_EPOCH);
+        assert_eq!(fresh.source_checksum, first.source_checksum);
+        assert_eq!(CompiledTheme::load_cached(&theme, &dir).unwrap().compiled_at, fresh.compiled_at);
+        
+        assert_eq!(CompiledTheme::clear_cache(&dir).unwrap(), 1);
+        assert!(CompiledTheme::load_cached(&theme, &dir).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
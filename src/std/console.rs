@@ -0,0 +1,45 @@
+//! Windows console setup for ANSI output
+//!
+//! Older Windows consoles print escape sequences literally unless virtual
+//! terminal processing is switched on for the output handle. Elsewhere, and
+//! when stdout is redirected (a file or pipe keeps its escapes), there is
+//! nothing to do.
+
+/// Turn on ANSI rendering for stdout where the console needs it; `false` means
+/// stdout is a console that can't render ANSI, so output should be plain
+pub fn enable_ansi() -> bool {
+    #[cfg(all(windows, feature = "windows"))]
+    {
+        use windows_sys::Win32::System::Console::{
+            GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE,
+        };
+        // SAFETY: the handle comes from GetStdHandle and `mode` outlives both calls
+        unsafe {
+            let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+            let mut mode: CONSOLE_MODE = 0;
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                // Not a console (redirected): escapes pass through untouched
+                return true;
+            }
+            mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+    #[cfg(not(all(windows, feature = "windows")))]
+    {
+        true
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enable_ansi_is_idempotent() {
+        // Under `cargo test` stdout may or may not be a console; either way a
+        // second call sees the mode the first one left behind
+        let first = enable_ansi();
+        assert_eq!(enable_ansi(), first);
+    }
+}
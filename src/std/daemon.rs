@@ -0,0 +1,131 @@
+//! `jynx serve` / `jynx send`: highlighting over a Unix socket
+//!
+//! Scripts that pipe one line at a time into `jynx` pay for theme loading and
+//! compilation on every call. `Daemon` binds a socket once and runs each
+//! connection through an already-built `JynxApp`; the protocol is just lines:
+//! the client writes newline-delimited input, then shuts down its write half,
+//! while reading back the highlighted lines. Connections are served one at a time.
+
+use std::io::{self, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use super::{JynxApp, JynxError};
+
+pub struct Daemon {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Daemon {
+    /// Bind `socket`, replacing a stale socket file left by a daemon that is gone
+    pub fn bind(socket: &Path) -> Result<Self, JynxError> {
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(JynxError::ProcessingError(format!("{} is already being served", socket.display())));
+            }
+            std::fs::remove_file(socket)?;
+        }
+        Ok(Self { listener: UnixListener::bind(socket)?, path: socket.to_path_buf() })
+    }
+
+    /// Serve connections until the process is stopped; a failed connection is
+    /// reported and the daemon keeps going
+    pub fn serve(&self, app: &JynxApp) -> Result<(), JynxError> {
+        for stream in self.listener.incoming() {
+            if let Err(e) = stream.map_err(JynxError::from).and_then(|stream| Self::handle(app, stream)) {
+                eprintln!("jynx serve: connection failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for one connection and serve it
+    pub fn serve_one(&self, app: &JynxApp) -> Result<(), JynxError> {
+        let (stream, _) = self.listener.accept()?;
+        Self::handle(app, stream)
+    }
+
+    fn handle(app: &JynxApp, stream: UnixStream) -> Result<(), JynxError> {
+        app.run_io(BufReader::new(&stream), &stream)?;
+        stream.shutdown(Shutdown::Write)?;
+        Ok(())
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Send `input` to the daemon at `socket` and copy the highlighted lines to `output`
+///
+/// Input is written from a separate thread while replies are read, so output
+/// larger than the socket buffer can't leave both ends blocked on write.
+pub fn send<R: Read + Send, W: Write>(socket: &Path, mut input: R, mut output: W) -> Result<(), JynxError> {
+    let mut stream = UnixStream::connect(socket)?;
+    let mut writer = stream.try_clone()?;
+    std::thread::scope(|scope| {
+        let sent = scope.spawn(move || -> io::Result<()> {
+            io::copy(&mut input, &mut writer)?;
+            writer.shutdown(Shutdown::Write)
+        });
+        let received = io::copy(&mut stream, &mut output).and_then(|_| output.flush());
+        let sent = sent.join().unwrap_or_else(|_| Err(io::Error::other("input thread panicked")));
+        received.and(sent)
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_through_socket() {
+        let socket = std::env::temp_dir().join(format!("jynx-daemon-{}.sock", std::process::id()));
+        let daemon = Daemon::bind(&socket).unwrap();
+        let app = JynxApp::with_theme(None, None, Some(12), "right".to_string()).with_auto_detection(false);
+
+        let client = {
+            let socket = socket.clone();
+            std::thread::spawn(move || {
+                let mut output = Vec::new();
+                send(&socket, "%c:red(hello)\n".as_bytes(), &mut output).unwrap();
+                String::from_utf8(output).unwrap()
+            })
+        };
+        daemon.serve_one(&app).unwrap();
+
+        let red = crate::extended_colors::get_extended_color_code("red");
+        assert_eq!(client.join().unwrap(), format!("       {}hello\x1B[0m\n", red));
+
+        // A second daemon refuses a live socket, and dropping the first removes it
+        assert!(Daemon::bind(&socket).is_err());
+        drop(daemon);
+        assert!(!socket.exists());
+    }
+
+    #[test]
+    fn test_round_trip_larger_than_socket_buffer() {
+        let socket = std::env::temp_dir().join(format!("jynx-daemon-big-{}.sock", std::process::id()));
+        let daemon = Daemon::bind(&socket).unwrap();
+        let app = JynxApp::new().with_auto_detection(false);
+
+        // About 4 MB each way, well past any socket buffer
+        let input: String = (0..100_000).map(|i| format!("line {:08} of the round trip\n", i)).collect();
+        let client = {
+            let socket = socket.clone();
+            let input = input.clone();
+            std::thread::spawn(move || {
+                let mut output = Vec::new();
+                send(&socket, input.as_bytes(), &mut output).unwrap();
+                String::from_utf8(output).unwrap()
+            })
+        };
+        daemon.serve_one(&app).unwrap();
+        assert_eq!(client.join().unwrap(), input);
+    }
+}
@@ -0,0 +1,99 @@
+//! `jynx demo`: a capabilities sample and manual smoke test
+//!
+//! Prints raw truecolor and 256-color swatches (to check the terminal), then
+//! runs sample lines through the real `JynxApp` pipeline: text attributes and
+//! icon mappings from a built-in theme, a `%c:` template, and every
+//! auto-detector. What the demo shows is what jynx actually does.
+
+use std::io::Write;
+
+use super::auto_detect::{IconMode, EXTRA_DETECTORS};
+use super::theme::{AnsiCodes, Theme};
+use super::{JynxApp, JynxError};
+
+/// Style groups for each text attribute, and icons with ASCII fallbacks
+const DEMO_THEME: &str = r#"
+metadata: { name: "demo", version: "1.0.0", description: "jynx demo" }
+filters:
+  demo:
+    icon_mappings:
+      critical: { icon: "🔥", icon_fallback: "[!]", color: "crimson" }
+      success: { icon: "✅", icon_fallback: "[ok]", color: "green" }
+      warn-high: { icon: "⚡", icon_fallback: "[~]", color: "amber" }
+    styles:
+      bold: { keywords: ["bold"], color: "white", bold: true }
+      dim: { keywords: ["dim"], color: "white", dim: true }
+      italic: { keywords: ["italic"], color: "white", italic: true }
+      underline: { keywords: ["underline"], color: "white", underline: true }
+      curly: { keywords: ["curly"], color: "white", underline_style: curly, underline_color: "crimson" }
+      strikethrough: { keywords: ["strikethrough"], color: "white", strikethrough: true }
+      reverse: { keywords: ["reverse"], color: "white", reverse: true }
+      blink: { keywords: ["blink"], color: "white", blink: true }
+"#;
+
+const THEMED_SAMPLE: &str = "\
+attributes: bold dim italic underline curly strikethrough reverse blink
+icons:      :critical: :success: :warn-high:
+template:   %c:crimson(crimson) %c:amber(amber) %c:violet(violet)
+";
+
+const DETECTION_SAMPLE: &str = "\
+urls:       docs at https://github.com/oodx/jynx
+versions:   upgraded to v2.1.0
+paths:      config in /etc/jynx/theme.yml
+filelines:  error at src/main.rs:42:10
+keyval:     level=info user=alice
+numbers:    3 retries, 250 items
+strings:    msg=\"all done\"
+";
+
+/// Write the demo, rendering icons in `icon_mode`
+pub fn write_demo<W: Write>(mut writer: W, icon_mode: IconMode) -> Result<(), JynxError> {
+    let heading = |title: &str| format!("{}{}{}", AnsiCodes::BOLD, title, AnsiCodes::RESET);
+
+    writeln!(writer, "{}", heading("truecolor (a smooth gradient if supported)"))?;
+    let gradient: String = (0..48)
+        .map(|i| {
+            let red = 255 - i * 5;
+            let blue = i * 5;
+            format!("\x1B[48;2;{};80;{}m ", red, blue)
+        })
+        .collect();
+    writeln!(writer, "{}{}", gradient, AnsiCodes::RESET)?;
+
+    writeln!(writer, "{}", heading("256-color"))?;
+    // Every sixth entry of the 6x6x6 color cube (codes 16-231)
+    let cube: String = (0..36).map(|i| format!("\x1B[48;5;{}m ", 16 + i * 6)).collect();
+    writeln!(writer, "{}{}", cube, AnsiCodes::RESET)?;
+
+    let theme = Theme::from_yaml_str(DEMO_THEME)?;
+    let themed = JynxApp::with_theme(Some(theme), Some("demo".to_string()), None, "left".to_string())
+        .with_auto_detection(false)
+        .with_icon_mode(icon_mode);
+    writeln!(writer, "{}", heading(&format!("theme styles, icons ({:?}) and templates", icon_mode).to_lowercase()))?;
+    themed.run_io(THEMED_SAMPLE.as_bytes(), &mut writer)?;
+
+    let detecting = JynxApp::new()
+        .with_extra_detectors(EXTRA_DETECTORS.iter().map(|name| name.to_string()).collect())
+        .with_icon_mode(icon_mode);
+    writeln!(writer, "{}", heading("auto-detection"))?;
+    detecting.run_io(DETECTION_SAMPLE.as_bytes(), &mut writer)?;
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demo_runs() {
+        let mut output = Vec::new();
+        write_demo(&mut output, IconMode::Ascii).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("[!]") && output.contains("auto-detection"));
+        assert!(!output.contains("%c:"));
+        assert!(output.lines().count() > 10);
+    }
+}
@@ -0,0 +1,52 @@
+//! Standalone HTML document around highlighted output (`--format html --output FILE`)
+//!
+//! The `Html` renderer only emits `<span>`s, which is what embedding needs.
+//! Written to a file, the lines are wrapped in a complete page instead: a
+//! `<pre>` keeps alignment and whitespace, and `finish` closes the document.
+
+use std::io::{self, Write};
+
+const START: &str = "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n<body>\n<pre>";
+const END: &str = "</pre>\n</body>\n</html>\n";
+
+pub struct HtmlDocument<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> HtmlDocument<W> {
+    /// Start a document on `inner`, writing everything up to the opening `<pre>`
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(START.as_bytes())?;
+        Ok(Self { inner })
+    }
+
+    /// Close the `<pre>` and the document, and hand back the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(END.as_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for HtmlDocument<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_wraps_lines() {
+        let mut document = HtmlDocument::new(Vec::new()).unwrap();
+        document.write_all(b"<span style=\"color:#ff0000\">a &lt; b</span>\n").unwrap();
+        let html = String::from_utf8(document.finish().unwrap()).unwrap();
+        assert_eq!(html, format!("{}<span style=\"color:#ff0000\">a &lt; b</span>\n{}", START, END));
+    }
+}
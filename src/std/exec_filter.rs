@@ -0,0 +1,115 @@
+//! External command filter (`--exec-filter`)
+//!
+//! Pipes each line through a long-lived child process and uses its reply as
+//! the line to highlight. The child is spawned once and driven in lockstep:
+//! one line in, one line out, so it must flush after every line (`sed -u`,
+//! `awk '{ ...; fflush() }'`). If it can't be started, exits, or stops
+//! answering, remaining lines pass through unchanged.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+pub struct ExecFilter {
+    command: String,
+    child: Option<Child>,
+    stdin: Option<ChildStdin>,
+    stdout: Option<BufReader<ChildStdout>>,
+}
+
+impl ExecFilter {
+    /// Spawn `command` through the shell
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut child = Self::shell(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().map(BufReader::new);
+
+        Ok(Self {
+            command: command.to_string(),
+            child: Some(child),
+            stdin,
+            stdout,
+        })
+    }
+
+    #[cfg(unix)]
+    fn shell(command: &str) -> Command {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+
+    #[cfg(not(unix))]
+    fn shell(command: &str) -> Command {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    }
+
+    /// Send one line to the child and return its reply, or the line itself once the child is gone
+    pub fn filter(&mut self, line: &str) -> String {
+        match self.exchange(line) {
+            Ok(Some(reply)) => reply,
+            Ok(None) | Err(_) => {
+                if self.stdout.is_some() {
+                    eprintln!("Warning: --exec-filter '{}' stopped responding; passing lines through", self.command);
+                    self.shutdown();
+                }
+                line.to_string()
+            }
+        }
+    }
+
+    fn exchange(&mut self, line: &str) -> io::Result<Option<String>> {
+        let (Some(stdin), Some(stdout)) = (self.stdin.as_mut(), self.stdout.as_mut()) else {
+            return Ok(None);
+        };
+        writeln!(stdin, "{}", line)?;
+        stdin.flush()?;
+
+        let mut reply = String::new();
+        if stdout.read_line(&mut reply)? == 0 {
+            return Ok(None);
+        }
+        let trimmed = reply.trim_end_matches(['\n', '\r']).len();
+        reply.truncate(trimmed);
+        Ok(Some(reply))
+    }
+
+    /// Close the pipes and reap the child
+    fn shutdown(&mut self) {
+        self.stdin = None;
+        self.stdout = None;
+        if let Some(mut child) = self.child.take() {
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for ExecFilter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_filter_streams_lines() {
+        let mut filter = ExecFilter::spawn("sed -u 's/foo/bar/'").unwrap();
+        assert_eq!(filter.filter("foo one"), "bar one");
+        assert_eq!(filter.filter("two foo"), "two bar");
+    }
+
+    #[test]
+    fn test_exec_filter_child_exit_falls_back() {
+        let mut filter = ExecFilter::spawn("read line; echo \"[$line]\"").unwrap();
+        assert_eq!(filter.filter("first"), "[first]");
+        assert_eq!(filter.filter("second"), "second");
+        assert_eq!(filter.filter("third"), "third");
+    }
+}
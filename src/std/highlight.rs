@@ -1,5 +1,58 @@
 //! Line highlighting logic
-//! 
-//! Applies patterns and styling to individual lines
+//!
+//! Applies patterns and styling to individual lines without touching
+//! stdin/stdout or the filesystem, so it can be embedded (e.g. wasm32)
 
-// TODO: Implement line highlighting
\ No newline at end of file
+use std::borrow::Cow;
+
+use super::JynxApp;
+use super::theme::Theme;
+
+/// Highlight text using a theme given as a YAML string
+///
+/// No filesystem or stream access: the theme is parsed from `yaml`, the
+/// pipeline is built in memory and each line of `input` is processed.
+/// An unparseable theme degrades to auto-detection only.
+pub fn highlight_with_theme_yaml(yaml: &str, filter: Option<&str>, input: &str) -> String {
+    let theme = Theme::from_yaml_str(yaml).ok();
+    let app = JynxApp::with_theme(theme, filter.map(|f| f.to_string()), None, "left".to_string());
+
+    input
+        .split('\n')
+        .map(|line| app.process_line(line).map_or_else(|_| line.to_string(), Cow::into_owned))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THEME_YAML: &str = r#"
+metadata: { name: "inline", version: "1.0.0", description: "in-memory theme" }
+filters:
+  logs:
+    icon_mappings:
+      critical: { icon: "🔥", color: "red" }
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson", bold: true }
+"#;
+
+    #[test]
+    fn test_highlight_with_theme_yaml() {
+        let output = highlight_with_theme_yaml(THEME_YAML, Some("logs"), "ERROR :critical: here\nplain line\n");
+        let lines: Vec<&str> = output.split('\n').collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("\x1b[38;5;196m\x1b[1mERROR\x1b[0m"));
+        assert!(lines[0].contains("🔥") && !lines[0].contains(":critical:"));
+        assert_eq!(lines[1], "plain line");
+        assert_eq!(lines[2], "");
+    }
+
+    #[test]
+    fn test_highlight_with_invalid_theme_yaml() {
+        let output = highlight_with_theme_yaml("not: [valid", Some("logs"), "ERROR here");
+        assert_eq!(output, "ERROR here");
+    }
+}
@@ -0,0 +1,167 @@
+//! `--json-pretty`: indent and colorize JSON lines
+//!
+//! A line holding a JSON object or array is re-serialized with two-space
+//! indentation, each value colored by its type. Colors come from the theme's
+//! `json` filter, whose style groups are looked up by convention (`key`,
+//! `string`, `number`, `boolean`, `null`), falling back to a built-in set.
+//! Anything else, including bare scalars and invalid JSON, is left alone.
+
+use serde_json::Value;
+
+use super::theme::Theme;
+use crate::text_styles::{Renderer, Style};
+
+/// Theme filter whose style groups color JSON
+pub const JSON_FILTER: &str = "json";
+
+/// Style per JSON token type (plain = uncolored)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonColors {
+    pub key: Style,
+    pub string: Style,
+    pub number: Style,
+    pub boolean: Style,
+    pub null: Style,
+}
+
+impl JsonColors {
+    /// The theme's `json` filter groups where defined, built-in colors otherwise
+    pub fn for_theme(theme: Option<&Theme>) -> Self {
+        let filter = theme.and_then(|theme| theme.filters.get(JSON_FILTER));
+        let style = |group: &str, fallback: &str| match filter.and_then(|filter| filter.styles.get(group)) {
+            Some(style) => style.style(),
+            None => Style::new().color(fallback),
+        };
+        Self {
+            key: style("key", "azure"),
+            string: style("string", "green"),
+            number: style("number", "amber"),
+            boolean: style("boolean", "violet"),
+            null: style("null", "slate"),
+        }
+    }
+}
+
+/// `line` pretty-printed and colored by `renderer`, or `None` if it isn't a JSON object or array
+pub fn pretty(line: &str, colors: &JsonColors, renderer: &dyn Renderer) -> Option<String> {
+    let trimmed = line.trim();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    let value: Value = serde_json::from_str(trimmed).ok()?;
+    let mut out = String::new();
+    write_value(&mut out, &value, 0, colors, renderer);
+    Some(out)
+}
+
+fn write_value(out: &mut String, value: &Value, depth: usize, colors: &JsonColors, renderer: &dyn Renderer) {
+    let paint = |out: &mut String, style: &Style, text: &str| out.push_str(&renderer.paint(style, text));
+    match value {
+        Value::Null => paint(out, &colors.null, "null"),
+        Value::Bool(b) => paint(out, &colors.boolean, &b.to_string()),
+        Value::Number(n) => paint(out, &colors.number, &n.to_string()),
+        Value::String(s) => paint(out, &colors.string, &quoted(s)),
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                indent(out, depth + 1);
+                write_value(out, item, depth + 1, colors, renderer);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            indent(out, depth);
+            out.push(']');
+        },
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (key, item)) in map.iter().enumerate() {
+                indent(out, depth + 1);
+                paint(out, &colors.key, &quoted(key));
+                out.push_str(": ");
+                write_value(out, item, depth + 1, colors, renderer);
+                out.push_str(if i + 1 < map.len() { ",\n" } else { "\n" });
+            }
+            indent(out, depth);
+            out.push('}');
+        },
+    }
+}
+
+/// A JSON string literal, escaped as serde_json would
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_else(|_| format!("\"{}\"", text))
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.push_str(&"  ".repeat(depth));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_styles::Ansi;
+
+    /// `<color>text</>`, so expected output reads at a glance
+    struct Tags;
+
+    impl Renderer for Tags {
+        fn color_open(&self, style: &Style) -> String {
+            style.color.as_ref().map(|color| format!("<{}>", color)).unwrap_or_default()
+        }
+
+        fn reset(&self) -> String {
+            "</>".to_string()
+        }
+    }
+
+    #[test]
+    fn test_nested_object_indented_and_colored() {
+        let colors = JsonColors {
+            key: Style::new().color("k"),
+            string: Style::new().color("s"),
+            number: Style::new().color("n"),
+            boolean: Style::new().color("b"),
+            null: Style::new().color("0"),
+        };
+        let out = pretty(r#"{"user":{"name":"ada","tags":[1,true]},"gone":null,"empty":[]}"#, &colors, &Tags).unwrap();
+        let r = "</>";
+        let expected = [
+            "{".to_string(),
+            format!("  <k>\"user\"{r}: {{"),
+            format!("    <k>\"name\"{r}: <s>\"ada\"{r},"),
+            format!("    <k>\"tags\"{r}: ["),
+            format!("      <n>1{r},"),
+            format!("      <b>true{r}"),
+            "    ]".to_string(),
+            "  },".to_string(),
+            format!("  <k>\"gone\"{r}: <0>null{r},"),
+            format!("  <k>\"empty\"{r}: []"),
+            "}".to_string(),
+        ].join("\n");
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_non_json_passes_through() {
+        let colors = JsonColors::default();
+        assert_eq!(pretty("plain text", &colors, &Ansi::default()), None);
+        assert_eq!(pretty("{broken", &colors, &Ansi::default()), None);
+        assert_eq!(pretty("42", &colors, &Ansi::default()), None);
+        assert_eq!(pretty(r#"["a\"b"]"#, &colors, &Ansi::default()).unwrap(), "[\n  \"a\\\"b\"\n]");
+    }
+
+    #[test]
+    fn test_theme_json_filter_colors() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "j", version: "1.0.0", description: "json colors" }
+filters:
+  json:
+    styles:
+      key: { keywords: [], color: "crimson", bold: true }
+"#).unwrap();
+        let colors = JsonColors::for_theme(Some(&theme));
+        assert_eq!(colors.key, theme.filters["json"].styles["key"].style());
+        assert_eq!(colors.string, Style::new().color("green"));
+    }
+}
@@ -0,0 +1,174 @@
+//! `--markdown`: render inline emphasis instead of showing its markers
+//!
+//! `**bold**`, `*italic*` / `_italic_` and `` `code` `` lose their markers and
+//! take the matching style. Emphasis nests (`**bold _and italic_**`), while
+//! code spans are literal inside. A marker without a valid partner stays as
+//! typed: `2 ** 3`, an unclosed `**`, `snake_case_names`, a lone backtick.
+//! The code style comes from the theme's `markdown` filter (`code` group, and
+//! `bold` / `italic` to override those), falling back to a built-in color.
+
+use std::ops::Range;
+
+use super::theme::Theme;
+use crate::text_styles::{Renderer, Style};
+
+/// Theme filter whose style groups style markdown emphasis
+pub const MARKDOWN_FILTER: &str = "markdown";
+
+/// Style per emphasis kind (plain = markers stripped, text unstyled)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownStyles {
+    pub bold: Style,
+    pub italic: Style,
+    pub code: Style,
+}
+
+impl MarkdownStyles {
+    /// The theme's `markdown` filter groups where defined, bold/italic and a built-in code color otherwise
+    pub fn for_theme(theme: Option<&Theme>) -> Self {
+        let filter = theme.and_then(|theme| theme.filters.get(MARKDOWN_FILTER));
+        let style = |group: &str, fallback: Style| match filter.and_then(|filter| filter.styles.get(group)) {
+            Some(style) => style.style(),
+            None => fallback,
+        };
+        Self {
+            bold: style("bold", Style::new().bold()),
+            italic: style("italic", Style::new().italic()),
+            code: style("code", Style::new().color("amber")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Span {
+    Bold,
+    Italic,
+    Code,
+}
+
+/// `line` with its emphasis rendered by `renderer`, or `None` if it has no markers at all
+pub fn render(line: &str, styles: &MarkdownStyles, renderer: &dyn Renderer) -> Option<String> {
+    if !line.contains(['*', '_', '`']) {
+        return None;
+    }
+    let mut out = String::with_capacity(line.len());
+    render_into(&mut out, line, styles, renderer);
+    Some(out)
+}
+
+fn render_into(out: &mut String, text: &str, styles: &MarkdownStyles, renderer: &dyn Renderer) {
+    let mut i = 0;
+    while i < text.len() {
+        match span_at(text, i) {
+            Some((span, content, end)) => {
+                let content = &text[content];
+                match span {
+                    Span::Code => out.push_str(&renderer.paint(&styles.code, content)),
+                    // An inner reset (from nested emphasis) re-applies the outer style
+                    Span::Bold | Span::Italic => {
+                        let mut inner = String::new();
+                        render_into(&mut inner, content, styles, renderer);
+                        let style = if span == Span::Bold { &styles.bold } else { &styles.italic };
+                        out.push_str(&renderer.paint_over(style, &inner));
+                    },
+                }
+                i = end;
+            },
+            // An unmatched `**` stays literal as a pair, so its second `*` can't open italic
+            None if text[i..].starts_with("**") => {
+                out.push_str("**");
+                i += 2;
+            },
+            None => {
+                let ch = text[i..].chars().next().unwrap_or_default();
+                out.push(ch);
+                i += ch.len_utf8();
+            },
+        }
+    }
+}
+
+/// The span opening at byte `i`: its kind, content range and end offset
+fn span_at(text: &str, i: usize) -> Option<(Span, Range<usize>, usize)> {
+    let bytes = text.as_bytes();
+    let rest = &text[i..];
+
+    if let Some(code) = rest.strip_prefix('`') {
+        let close = i + 1 + code.find('`')?;
+        return (close > i + 1).then_some((Span::Code, i + 1..close, close + 1));
+    }
+
+    // Content must start right after the opener and end right before the closer
+    let opens = |start: usize| text[start..].chars().next().is_some_and(|ch| !ch.is_whitespace());
+    let closes = |k: usize| !bytes[k - 1].is_ascii_whitespace();
+
+    if rest.starts_with("**") {
+        let start = i + 2;
+        if !opens(start) {
+            return None;
+        }
+        let close = text[start..].match_indices("**").map(|(k, _)| start + k).find(|&k| k > start && closes(k))?;
+        return Some((Span::Bold, start..close, close + 2));
+    }
+
+    let marker = *bytes.get(i).filter(|&&b| b == b'*' || b == b'_')?;
+    // `_` inside a word (snake_case) is not emphasis
+    let in_word = |k: Option<&u8>| marker == b'_' && k.is_some_and(u8::is_ascii_alphanumeric);
+    if (i > 0 && in_word(bytes.get(i - 1))) || !opens(i + 1) {
+        return None;
+    }
+    let close = (i + 2..text.len()).find(|&k| {
+        bytes[k] == marker && closes(k) && bytes[k - 1] != marker
+            && bytes.get(k + 1) != Some(&marker) && !in_word(bytes.get(k + 1))
+    })?;
+    Some((Span::Italic, i + 1..close, close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_styles::Ansi;
+
+    /// `<color>text</>`, so expected output reads at a glance
+    struct Tags;
+
+    impl Renderer for Tags {
+        fn color_open(&self, style: &Style) -> String {
+            style.color.as_ref().map(|color| format!("<{}>", color)).unwrap_or_default()
+        }
+
+        fn reset(&self) -> String {
+            "</>".to_string()
+        }
+    }
+
+    fn styles() -> MarkdownStyles {
+        MarkdownStyles { bold: Style::new().color("b"), italic: Style::new().color("i"), code: Style::new().color("c") }
+    }
+
+    fn render(line: &str, styles: &MarkdownStyles) -> Option<String> {
+        super::render(line, styles, &Tags)
+    }
+
+    #[test]
+    fn test_emphasis_and_code() {
+        let r = "</>";
+        let styles = styles();
+        assert_eq!(render("a **bold** b", &styles).unwrap(), format!("a <b>bold{r} b"));
+        assert_eq!(render("*it* and _it_", &styles).unwrap(), format!("<i>it{r} and <i>it{r}"));
+        assert_eq!(render("run `make **all**` now", &styles).unwrap(), format!("run <c>make **all**{r} now"));
+        assert_eq!(render("**bold _both_ bold**", &styles).unwrap(), format!("<b>bold <i>both{r}<b> bold{r}"));
+        assert_eq!(render("*a **b** c*", &styles).unwrap(), format!("<i>a <b>b{r}<i> c{r}"));
+        assert_eq!(render("plain", &styles), None);
+    }
+
+    #[test]
+    fn test_unbalanced_markers_stay_literal() {
+        let styles = styles();
+        for text in ["an **unclosed marker", "2 ** 3 ** 4", "snake_case_name", "a lone ` tick", "``", "* item", "**bold*"] {
+            assert_eq!(render(text, &styles).unwrap(), text);
+        }
+        // Stripping only, as in no-color mode
+        assert_eq!(super::render("**ok** `x`", &MarkdownStyles::default(), &Ansi::default()).unwrap(), "ok x");
+    }
+}
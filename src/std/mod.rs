@@ -6,18 +6,66 @@
 //! - Performance-optimized regex compilation
 //! - Memory-efficient processing
 
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::io;
+#[cfg(feature = "themes")]
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::error::Error;
 use std::fmt;
+use std::path::PathBuf;
+#[cfg(feature = "themes")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "themes")]
+use std::sync::Arc;
+#[cfg(feature = "themes")]
+use std::borrow::Cow;
 
+#[cfg(feature = "themes")]
 pub mod theme;
+#[cfg(feature = "themes")]
 pub mod highlight;
 pub mod auto_detect;
+#[cfg(feature = "compiled")]
 pub mod compiled;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod exec_filter;
+pub mod document;
+#[cfg(feature = "themes")]
+pub mod summary;
+#[cfg(feature = "themes")]
+pub mod stream;
+#[cfg(feature = "themes")]
+pub mod profile;
+#[cfg(feature = "themes")]
+pub mod json_pretty;
+#[cfg(feature = "themes")]
+pub mod markdown;
+#[cfg(all(feature = "daemon", unix))]
+pub mod daemon;
+#[cfg(feature = "themes")]
+pub mod demo;
+pub mod console;
 
-use auto_detect::AutoDetector;
+#[cfg(feature = "themes")]
+pub use highlight::highlight_with_theme_yaml;
+#[cfg(feature = "themes")]
+pub use summary::SummaryOutput;
+#[cfg(feature = "themes")]
+pub use stream::HighlightStream;
+pub use document::HtmlDocument;
+
+#[cfg(feature = "themes")]
+use auto_detect::{AutoDetector, DetectorConfig};
+#[cfg(feature = "themes")]
+use profile::{Profile, Stage};
+pub use auto_detect::IconMode;
+#[cfg(feature = "themes")]
 use std::collections::HashMap;
+#[cfg(feature = "themes")]
 use crate::template_parser::ColorTemplateParser;
+#[cfg(feature = "themes")]
+use crate::text_styles::{Ansi, PreEscaped, Renderer, SgrState, Style};
+#[cfg(feature = "themes")]
+use crate::extended_colors::{BoldMode, Palette};
 
 #[derive(Debug)]
 pub enum JynxError {
@@ -36,7 +84,26 @@ impl fmt::Display for JynxError {
     }
 }
 
-impl Error for JynxError {}
+impl Error for JynxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JynxError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl JynxError {
+    /// Annotate an error with the 1-based input line it happened on
+    /// (`Processing error: line 4523: ...`); an I/O error keeps its kind
+    pub fn line_context(self, line: usize) -> Self {
+        match self {
+            JynxError::IoError(e) => JynxError::IoError(io::Error::new(e.kind(), format!("line {}: {}", line, e))),
+            JynxError::ThemeError(e) => JynxError::ThemeError(format!("line {}: {}", line, e)),
+            JynxError::ProcessingError(e) => JynxError::ProcessingError(format!("line {}: {}", line, e)),
+        }
+    }
+}
 
 impl From<io::Error> for JynxError {
     fn from(error: io::Error) -> Self {
@@ -44,28 +111,144 @@ impl From<io::Error> for JynxError {
     }
 }
 
+impl From<regex::Error> for JynxError {
+    fn from(error: regex::Error) -> Self {
+        JynxError::ThemeError(format!("invalid pattern: {}", error))
+    }
+}
+
+#[cfg(feature = "themes")]
+impl From<serde_yaml::Error> for JynxError {
+    fn from(error: serde_yaml::Error) -> Self {
+        JynxError::ThemeError(error.to_string())
+    }
+}
+
+/// Theme loading reports errors as `Box<dyn Error>`
+impl From<Box<dyn Error>> for JynxError {
+    fn from(error: Box<dyn Error>) -> Self {
+        match error.downcast::<io::Error>() {
+            Ok(e) => JynxError::IoError(*e),
+            Err(e) => JynxError::ThemeError(e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "themes")]
 use theme::Theme;
+#[cfg(feature = "compiled")]
 use compiled::CompiledTheme;
+#[cfg(feature = "themes")]
 use regex::Regex;
 
+#[cfg(feature = "themes")]
+#[derive(Clone)]
 pub struct JynxApp {
     // Core stream processor - Unix philosophy: do one thing well
     detector: Option<AutoDetector>,
     theme: Option<Theme>,
+    #[cfg(feature = "compiled")]
     compiled_theme: Option<CompiledTheme>,
     filter: Option<String>,
     // Compiled regex for :word: pattern detection
     icon_pattern: Regex,
     // Pre-compiled keyword regex patterns for performance (legacy)
-    keyword_patterns: HashMap<String, (Regex, String)>, // (regex, ansi_style)
+    keyword_patterns: HashMap<String, KeywordPattern>,
+    skip_regions: Option<Regex>, // Legacy path: spans of the active filter keywords skip
     // Color template parser for %c:colorname(text) patterns
     template_parser: ColorTemplateParser,
     // Output formatting options
     width: Option<usize>,
     align: TextAlign,
+    truncate: TruncateSide,
     // Performance optimization flags
     use_compiled: bool,
     no_color: bool,
+    // Auto-detector settings (icons, opt-in detectors, --match, hyperlinks)
+    detector_config: DetectorConfig,
+    // Input line window (1-based, inclusive)
+    line_range: Option<LineRange>,
+    // Honor a `#jynx:` directive on the first input line
+    auto_filter: bool,
+    // Visible columns that keyword/auto-detect processing is limited to
+    column_range: Option<ColumnRange>,
+    // Header line emitted before output, and again every N emitted lines
+    header: Option<String>,
+    repeat_every: Option<usize>,
+    // Keyword styling growth cap per line (0 = unlimited)
+    max_expand: usize,
+    // `--idempotent`: keywords and detections skip text that is already styled
+    idempotent: bool,
+    // External command each input line is piped through before highlighting
+    exec_filter: Option<String>,
+    // Footer with keyword-group and detection counts at EOF
+    summary: Option<SummaryOutput>,
+    // Only print per-group line/match counts, no highlighted output
+    count_only: bool,
+    // `--squeeze`: runs of identical input lines are emitted once with an `(xN)` suffix
+    pub(crate) squeeze: bool,
+    // Marker for stderr lines in a merged stream, and the style they get
+    stderr_prefix: Option<String>,
+    stderr_style: Style,
+    // `whole_line` style groups of the active filter, highest priority first: (keywords, style)
+    whole_line_groups: Vec<(Regex, Style)>,
+    // `--columns`: per-field widths/alignments and the delimiter fields are split on
+    columns: Option<(Vec<ColumnSpec>, String)>,
+    // `--rainbow-brackets`: styles per nesting depth, resolved from metadata.bracket_colors
+    rainbow_brackets: bool,
+    bracket_styles: Vec<Style>,
+    // `--json-pretty`: JSON lines are indented and colored by type with these styles
+    json_pretty: bool,
+    json_colors: Option<json_pretty::JsonColors>,
+    // `--markdown`: inline emphasis rendered with these styles, markers stripped
+    markdown: bool,
+    markdown_styles: Option<markdown::MarkdownStyles>,
+    // `--reset-lines`: SGR resets around every output line
+    reset_lines: Option<ResetLines>,
+    // `--gutter`: severity marks from metadata.summary_groups, outside the width
+    gutter: bool,
+    gutter_marks: Option<summary::Gutter>,
+    // `--search`: occurrences in the visible text get reverse video
+    search: Option<Regex>,
+    // Substitutions over the colored line, in order, before trim/width
+    replacements: Vec<Replacement>,
+    // Strip trailing visible whitespace before width/alignment
+    trim: bool,
+    // Truncation indicator (`--ellipsis`) and its style from metadata.ellipsis_style
+    ellipsis: String,
+    ellipsis_style: Style,
+    // `--coalesce`: drop resets that are immediately followed by the same style
+    coalesce: bool,
+    // Line number prefix settings and its resolved style
+    line_numbers: Option<LineNumbers>,
+    number_style: Style,
+    // Style groups (besides the theme's `metadata.bell_groups`) that ring the bell,
+    // and their keywords compiled for the active filter
+    bell_on: Vec<String>,
+    bell_pattern: Option<Regex>,
+    // Expand `%c:` templates over the whole input so spans can cross lines
+    multiline_templates: bool,
+    // `--default-mode`: auto-detect or pass through when there is no theme and no filter
+    default_mode: DefaultMode,
+    // Output buffering: flush policy and `BufWriter` capacity (None = std default)
+    flush_mode: FlushMode,
+    buffer_size: Option<usize>,
+    // `--gzip`: decompress every input, not just files named `*.gz`
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    // `--profile`: time per pipeline stage, shared with directive/theme-map clones
+    profile: Option<Arc<Profile>>,
+    // `--theme-dir`: searched before $JYNX_THEME_PATH for directive/theme-map themes
+    #[cfg(not(target_arch = "wasm32"))]
+    theme_dirs: Vec<PathBuf>,
+    // `--theme-var` overrides for those themes' `{{name}}` placeholders
+    #[cfg(not(target_arch = "wasm32"))]
+    theme_vars: std::collections::BTreeMap<String, String>,
+    // Output format every styling stage renders with (ANSI unless `with_renderer` changes it)
+    renderer: Arc<dyn Renderer>,
+    // `renderer` for the styling passes: a line's literal text is escaped once, on
+    // the way in, so what they paint is never escaped again
+    escaped: Arc<dyn Renderer>,
 }
 
 #[derive(Debug, Clone)]
@@ -76,6 +259,7 @@ pub enum TextAlign {
 }
 
 impl TextAlign {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "center" | "centre" => Self::Center,
@@ -85,302 +269,3326 @@ impl TextAlign {
     }
 }
 
-impl JynxApp {
-    pub fn new() -> Self {
-        Self::with_theme(None, None, None, "left".to_string())
+/// When buffered output is flushed to the underlying writer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushMode {
+    /// After every line: most responsive, right for `tail -f`
+    #[default]
+    EveryLine,
+    /// Only when the buffer fills and at end of input: fewest syscalls for bulk work
+    OnEof,
+}
+
+impl FlushMode {
+    /// Parse a CLI value: `line` or `eof`
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "line" => Some(Self::EveryLine),
+            "eof" => Some(Self::OnEof),
+            _ => None,
+        }
     }
-    
-    pub fn with_theme_and_options(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String, no_color: bool) -> Self {
-        Self::with_theme_internal(theme, filter, width, align, no_color)
+}
+
+/// What jynx does with a line when neither a theme nor a filter is given
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DefaultMode {
+    /// Run the built-in auto-detectors (URLs, paths, versions, ...)
+    #[default]
+    Auto,
+    /// Pass text through like `cat`, expanding only `%c:` templates
+    Plain,
+}
+
+impl DefaultMode {
+    /// Parse a CLI value: `auto` or `plain`
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "plain" => Some(Self::Plain),
+            _ => None,
+        }
+    }
+}
+
+/// `--replace PATTERN=>REPLACEMENT`: a regex substitution over the finished,
+/// already colored line (`$1`/`${name}` expand captures)
+#[cfg(feature = "themes")]
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pattern: Regex,
+    replacement: String,
+}
+
+#[cfg(feature = "themes")]
+impl Replacement {
+    /// Parse `PATTERN=>REPLACEMENT` (split at the first `=>`; the replacement may be empty)
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (pattern, replacement) = spec.split_once("=>")
+            .ok_or_else(|| format!("expected PATTERN=>REPLACEMENT, got '{}'", spec))?;
+        let pattern = RegexLimits::default().compile(pattern).map_err(|e| e.to_string())?;
+        Ok(Self { pattern, replacement: replacement.to_string() })
     }
     
-    pub fn with_theme(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String) -> Self {
-        Self::with_theme_internal(theme, filter, width, align, false)
+    /// Replace every match in `text`
+    pub fn apply(&self, text: &str) -> String {
+        self.pattern.replace_all(text, self.replacement.as_str()).into_owned()
+    }
+}
+
+/// Which part of an over-wide line `--width` cuts away
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncateSide {
+    /// Keep the beginning: `very/long/pa...`
+    #[default]
+    End,
+    /// Keep the end: `...long/path/file.rs`
+    Start,
+    /// Keep both ends: `very/lo...file.rs`
+    Middle,
+}
+
+impl TruncateSide {
+    /// Parse a CLI value: `end`, `start` or `middle`
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "end" => Some(Self::End),
+            "start" => Some(Self::Start),
+            "middle" => Some(Self::Middle),
+            _ => None,
+        }
+    }
+}
+
+/// One field of `--columns`: pad to `width` visible columns (0 = natural width) with `align`
+#[derive(Debug, Clone)]
+pub struct ColumnSpec {
+    pub width: usize,
+    pub align: TextAlign,
+}
+
+impl ColumnSpec {
+    /// Parse a CLI value such as `20:left,10:right,0` (alignment defaults to left)
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        value.split(',').map(|field| {
+            let (width, align) = field.trim().split_once(':').unwrap_or((field.trim(), "left"));
+            let width = width.parse()
+                .map_err(|_| format!("invalid column width '{}' in '{}'", width, field))?;
+            let align = match align.to_lowercase().as_str() {
+                "left" | "center" | "centre" | "right" => TextAlign::from_str(align),
+                _ => return Err(format!("invalid column alignment '{}' (expected left, center or right)", align)),
+            };
+            Ok(Self { width, align })
+        }).collect()
+    }
+}
+
+/// Truncation indicator when no `--ellipsis` is given
+#[cfg(feature = "themes")]
+const DEFAULT_ELLIPSIS: &str = "...";
+
+/// `--rainbow-brackets` depth colors when the theme sets no `metadata.bracket_colors`
+#[cfg(feature = "themes")]
+const DEFAULT_BRACKET_COLORS: [&str; 3] = ["gold", "violet", "azure"];
+
+/// `--reset-lines`: where each output line gets an SGR reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetLines {
+    /// Before the line, so styling leaked by a previous program can't bleed in
+    Start,
+    /// Before and after the line
+    Both,
+}
+
+impl ResetLines {
+    /// Parse a CLI value: `start` or `both`
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "start" => Some(Self::Start),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+}
+
+/// `--width` value: a column count, a percentage of the terminal width, or `auto`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidthSpec {
+    Columns(usize),
+    Percent(usize),
+    Auto,
+}
+
+/// Terminal width assumed when it can't be detected (not a TTY)
+pub const DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+impl WidthSpec {
+    /// Parse `80`, `80%` (1-100) or `auto`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        if let Some(percent) = s.strip_suffix('%') {
+            return match percent.trim().parse::<usize>() {
+                Ok(n) if (1..=100).contains(&n) => Ok(Self::Percent(n)),
+                _ => Err(format!("invalid width percentage '{}' (expected 1%-100%)", s)),
+            };
+        }
+        s.parse().map(Self::Columns).map_err(|_| format!("invalid width '{}' (expected N, N% or auto)", s))
     }
     
-    fn with_theme_internal(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String, no_color: bool) -> Self {
-        // Try to initialize auto-detector, but gracefully fall back if it fails
-        let detector = match AutoDetector::new() {
-            Ok(d) => Some(d),
-            Err(e) => {
-                eprintln!("Warning: Failed to initialize auto-detection: {}", e);
-                None
-            }
+    /// Concrete column count given the detected terminal width (at least 1)
+    pub fn resolve(self, terminal_width: Option<usize>) -> usize {
+        let terminal = terminal_width.filter(|&w| w > 0).unwrap_or(DEFAULT_TERMINAL_WIDTH);
+        match self {
+            Self::Columns(columns) => columns,
+            Self::Percent(percent) => (terminal * percent / 100).max(1),
+            Self::Auto => terminal,
+        }
+    }
+}
+
+/// `--number` prefix: a right-aligned input line number and a space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineNumbers {
+    /// Minimum digits; longer numbers widen the field rather than being cut
+    pub width: usize,
+    /// Color name (theme palette or built-in); falls back to `metadata.line_number_color`
+    pub color: Option<String>,
+    /// Count the prefix as part of the `--width` column instead of adding it in front
+    pub inside: bool,
+}
+
+impl Default for LineNumbers {
+    fn default() -> Self {
+        Self { width: 6, color: None, inside: false }
+    }
+}
+
+/// Inclusive 1-based line window for `--lines START:END`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl LineRange {
+    /// Parse `START:END`, `START:` or `START` (END is optional, open-ended)
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (start_str, end_str) = match s.split_once(':') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (s.trim(), ""),
         };
         
-        // Compile regex for :word: pattern detection
-        let icon_pattern = Regex::new(r":([a-zA-Z_][a-zA-Z0-9_]*):").unwrap();
-        
-        // Enable compiled theme optimization for ~150x performance boost
-        let (compiled_theme, use_compiled) = if let Some(ref theme) = theme {
-            match CompiledTheme::from_theme(theme) {
-                Ok(mut compiled) => {
-                    if let Err(e) = compiled.init_runtime() {
-                        eprintln!("Warning: Failed to initialize compiled theme: {}", e);
-                        (None, false)
-                    } else {
-                        (Some(compiled), true)
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Warning: Failed to compile theme: {}", e);
-                    (None, false)
-                }
-            }
+        let start = if start_str.is_empty() {
+            1
         } else {
-            (None, false)
+            start_str.parse::<usize>().map_err(|_| format!("Invalid line range start '{}'", start_str))?
         };
+        if start == 0 {
+            return Err("Line numbers are 1-based".to_string());
+        }
         
-        // Pre-compile keyword patterns for legacy fallback
-        let keyword_patterns = if !use_compiled {
-            if let (Some(ref theme), Some(ref filter_name)) = (&theme, &filter) {
-                Self::compile_keyword_patterns(theme, filter_name)
-            } else {
-                HashMap::new()
-            }
+        let end = if end_str.is_empty() {
+            None
         } else {
-            HashMap::new() // Not needed when using compiled theme
+            let end = end_str.parse::<usize>().map_err(|_| format!("Invalid line range end '{}'", end_str))?;
+            if end < start {
+                return Err(format!("Line range end {} is before start {}", end, start));
+            }
+            Some(end)
         };
         
-        // Initialize template parser
-        let template_parser = ColorTemplateParser::new(no_color);
-        
-        Self { 
-            detector,
-            theme,
-            compiled_theme,
-            filter,
-            icon_pattern,
-            keyword_patterns,
-            template_parser,
-            width,
-            align: TextAlign::from_str(&align),
-            use_compiled,
-            no_color,
-        }
+        Ok(Self { start, end })
     }
     
-    /// Pre-compile all keyword patterns for performance
-    fn compile_keyword_patterns(theme: &Theme, filter_name: &str) -> HashMap<String, (Regex, String)> {
-        let mut patterns = HashMap::new();
-        
-        if let Some(filter) = theme.filters.get(filter_name) {
-            for style_group in filter.styles.values() {
-                let ansi_style = style_group.to_ansi() + &theme::AnsiCodes::RESET;
-                
-                for keyword in &style_group.keywords {
-                    // Create appropriate regex pattern
-                    let pattern = if keyword.contains(":") || keyword.contains(" ") {
-                        // For phrases or patterns with colons, use literal matching
-                        format!(r"(?i){}", regex::escape(keyword))
-                    } else {
-                        // For single words, use word boundaries
-                        format!(r"(?i)\b{}\b", regex::escape(keyword))
-                    };
-                    
-                    if let Ok(regex) = Regex::new(&pattern) {
-                        patterns.insert(keyword.clone(), (regex, ansi_style.clone()));
-                    }
-                }
-            }
-        }
-        
-        patterns
+    /// True once the line number is beyond the end of the range
+    pub fn is_past_end(&self, line_number: usize) -> bool {
+        self.end.is_some_and(|end| line_number > end)
     }
+}
 
-    /// Main entry point - stream processor that reads stdin and writes to stdout
-    pub fn run(&self) -> Result<(), JynxError> {
-        // Lock stdin and stdout once for the entire session - more efficient
-        let stdin = io::stdin();
-        let stdout = io::stdout();
-        
-        let reader = BufReader::new(stdin.lock());
-        let mut writer = BufWriter::new(stdout.lock());
-        
-        // Stream processing: line by line, immediate output
-        for line_result in reader.lines() {
-            let line = line_result?;
-            
-            // Process the line - this is where the magic happens
-            let processed_line = self.process_line(&line)?;
-            
-            // Write immediately and flush for pipe compatibility
-            writeln!(writer, "{}", processed_line)?;
-            writer.flush()?;
-        }
-        
-        Ok(())
-    }
-    
-    /// Process a single line - the core transformation logic
-    /// Implements the complete 5-layer processing pipeline
-    fn process_line(&self, line: &str) -> Result<String, JynxError> {
-        let mut result = line.to_string();
-        
-        // FIRST: Apply color templates (%c:colorname(text) patterns) - highest priority
-        result = self.template_parser.process(&result);
-        
-        // Skip other color processing if in no-color mode
-        if !self.no_color {
-            // Use compiled theme for optimal performance if available
-            if self.use_compiled {
-                if let (Some(ref compiled_theme), Some(ref filter_name)) = (&self.compiled_theme, &self.filter) {
-                    // High-performance compiled processing
-                    result = compiled_theme.process_text(&result, filter_name);
-                } else if let Some(detector) = &self.detector {
-                    // Fallback to basic auto-detection only
-                    result = detector.highlight_line(&result);
-                }
-            } else {
-                // Legacy processing pipeline (layers 2-4)
-                // 2. Apply auto-detection if available
-                if let Some(detector) = &self.detector {
-                    result = detector.highlight_line(&result);
-                }
-                
-                // 3. Apply icon mappings (:word: patterns) if theme is available
-                if let (Some(theme), Some(filter_name)) = (&self.theme, &self.filter) {
-                    result = self.apply_icon_patterns(&result, theme, filter_name);
-                }
-                
-                // 4. Apply keyword highlighting if theme and filter are available  
-                if let (Some(theme), Some(filter_name)) = (&self.theme, &self.filter) {
-                    result = self.apply_keyword_highlighting(&result, theme, filter_name);
+/// `--theme-map '*.rs:rust,*.log:logs'`: theme per input file, by glob on the file name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeMap {
+    entries: Vec<(String, String)>,
+}
+
+impl ThemeMap {
+    /// Parse comma-separated `GLOB:THEME` pairs (`*` and `?` wildcards)
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let entries = s.split(',')
+            .map(|entry| {
+                let (glob, theme) = entry.split_once(':')
+                    .ok_or_else(|| format!("Expected GLOB:THEME, got '{}'", entry))?;
+                let (glob, theme) = (glob.trim(), theme.trim());
+                if glob.is_empty() || theme.is_empty() {
+                    return Err(format!("Expected GLOB:THEME, got '{}'", entry));
                 }
-            }
-        }
-        
-        // 5. Apply width and alignment formatting if specified (always last)
-        if let Some(width) = self.width {
-            result = self.format_line_width(&result, width);
-        }
-        
-        Ok(result)
+                Ok((glob.to_string(), theme.to_string()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { entries })
     }
     
-    /// Apply :word: icon pattern replacements
-    fn apply_icon_patterns(&self, text: &str, theme: &Theme, filter_name: &str) -> String {
-        self.icon_pattern.replace_all(text, |caps: &regex::Captures| {
-            let word = &caps[1];
-            
-            if let Some(icon_mapping) = theme.get_icon_mapping(filter_name, word) {
-                // Replace :word: with colored icon + word (e.g. ":critical:" -> "🔥 critical")
-                icon_mapping.formatted_icon(word)
-            } else {
-                // Keep original if no mapping found (graceful degradation)
-                caps[0].to_string()
-            }
-        }).to_string()
+    /// Theme for the first glob matching the file name of `path`
+    pub fn theme_for(&self, path: &std::path::Path) -> Option<&str> {
+        let name = path.file_name()?.to_string_lossy();
+        self.entries.iter()
+            .find(|(glob, _)| glob_match(glob, &name))
+            .map(|(_, theme)| theme.as_str())
     }
-    
-    /// Apply keyword highlighting based on theme styles (using pre-compiled patterns)
-    fn apply_keyword_highlighting(&self, text: &str, _theme: &Theme, _filter_name: &str) -> String {
-        let mut result = text.to_string();
-        
-        // Use pre-compiled patterns for much better performance
-        for (_keyword, (regex, styled_replacement)) in &self.keyword_patterns {
-            result = regex.replace_all(&result, |caps: &regex::Captures| {
-                let matched = &caps[0];
-                format!("{}{}{}", 
-                    styled_replacement.replace(theme::AnsiCodes::RESET, ""),
-                    matched, 
-                    theme::AnsiCodes::RESET
-                )
-            }).to_string();
+}
+
+/// Match `name` against a glob with `*` (any run) and `?` (any one character)
+fn glob_match(glob: &str, name: &str) -> bool {
+    let (glob, name): (Vec<char>, Vec<char>) = (glob.chars().collect(), name.chars().collect());
+    let (mut g, mut n) = (0, 0);
+    // Last `*` seen and the name position it is currently absorbing up to
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == name[n]) {
+            g += 1;
+            n += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, n));
+            g += 1;
+        } else if let Some((star_g, star_n)) = star {
+            g = star_g + 1;
+            n = star_n + 1;
+            star = Some((star_g, star_n + 1));
+        } else {
+            return false;
         }
-        
-        result
     }
-    
-    /// Format line to specified width with alignment
-    /// Handles ANSI escape codes properly to calculate visible text length
-    fn format_line_width(&self, text: &str, width: usize) -> String {
-        // Calculate visible text length by removing ANSI escape codes
-        let visible_len = Self::get_visible_length(text);
-        
-        if visible_len >= width {
-            // If text is already wider than target, truncate it gracefully
-            return Self::truncate_to_width(text, width);
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+/// Disk cache for compiled themes (`--cache-dir`, `--recompile`)
+#[derive(Debug, Clone)]
+pub struct ThemeCache {
+    pub dir: PathBuf,
+    pub recompile: bool,
+}
+
+impl ThemeCache {
+    /// Cache in `dir`, or the default location when `None`
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir: dir.unwrap_or_else(Self::default_dir),
+            recompile: false,
         }
-        
-        let padding_needed = width - visible_len;
-        
-        match self.align {
-            TextAlign::Left => {
-                // Left align: add padding to the right
-                format!("{}{}", text, " ".repeat(padding_needed))
-            },
-            TextAlign::Right => {
-                // Right align: add padding to the left  
-                format!("{}{}", " ".repeat(padding_needed), text)
-            },
-            TextAlign::Center => {
-                // Center align: split padding between left and right
-                let left_padding = padding_needed / 2;
-                let right_padding = padding_needed - left_padding;
-                format!("{}{}{}", " ".repeat(left_padding), text, " ".repeat(right_padding))
+    }
+    
+    /// `$JYNX_CACHE_DIR`, else `~/.cache/rsb/jynx`
+    pub fn default_dir() -> PathBuf {
+        if let Ok(dir) = std::env::var("JYNX_CACHE_DIR") {
+            if !dir.is_empty() {
+                return PathBuf::from(dir);
             }
         }
+        match std::env::var("HOME") {
+            Ok(home) => PathBuf::from(home).join(".cache/rsb/jynx"),
+            Err(_) => PathBuf::from(".cache/rsb/jynx"),
+        }
     }
     
-    /// Get visible length of text (excluding ANSI escape codes)
-    /// More accurate than strip_ansi_codes for length calculation
-    fn get_visible_length(text: &str) -> usize {
-        let ansi_regex = regex::Regex::new(r"\x1B\[[0-9;]*m").unwrap();
-        let stripped = ansi_regex.replace_all(text, "");
-        
-        // Count Unicode grapheme clusters for accurate character width
-        stripped.chars().count()
+    /// Ignore cached compilations and always rebuild (the cache is still refreshed)
+    pub fn with_recompile(mut self, recompile: bool) -> Self {
+        self.recompile = recompile;
+        self
     }
-    
-    /// Truncate text to specified width while preserving ANSI codes
-    fn truncate_to_width(text: &str, width: usize) -> String {
-        if width == 0 {
-            return String::new();
-        }
-        
-        let ansi_regex = regex::Regex::new(r"\x1B\[[0-9;]*m").unwrap();
-        let mut result = String::new();
-        let mut visible_chars = 0;
-        let mut i = 0;
+}
+
+/// First-line `#jynx: theme=<name> filter=<name>` directive (`--auto-filter`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub theme: Option<String>,
+    pub filter: Option<String>,
+}
+
+impl Directive {
+    /// Parse a directive line strictly: `#jynx:` then one or more space-separated
+    /// `theme=`/`filter=` pairs, each at most once, with name-like values.
+    /// Anything else is not a directive.
+    pub fn parse(line: &str) -> Option<Self> {
+        let body = line.strip_prefix("#jynx:")?;
+        let mut directive = Self { theme: None, filter: None };
         
-        while i < text.len() && visible_chars < width {
-            // Check for ANSI escape sequence
-            if let Some(mat) = ansi_regex.find(&text[i..]) {
-                if mat.start() == 0 {
-                    // Add ANSI sequence without counting toward visible length
-                    result.push_str(mat.as_str());
-                    i += mat.len();
-                    continue;
-                }
+        for pair in body.split_whitespace() {
+            let (key, value) = pair.split_once('=')?;
+            let valid_value = !value.is_empty()
+                && value.chars().all(|c| c.is_ascii_alphanumeric() || "_-./".contains(c));
+            if !valid_value {
+                return None;
             }
-            
-            // Add regular character
-            if let Some(ch) = text[i..].chars().next() {
-                result.push(ch);
-                visible_chars += 1;
-                i += ch.len_utf8();
-            } else {
-                break;
+            let slot = match key {
+                "theme" => &mut directive.theme,
+                "filter" => &mut directive.filter,
+                _ => return None,
+            };
+            if slot.replace(value.to_string()).is_some() {
+                return None;
             }
         }
         
-        // Add ellipsis if truncated (within width limit)
-        if i < text.len() && width > 3 && visible_chars == width {
-            // Remove last 3 characters and add ellipsis
-            let mut chars: Vec<char> = result.chars().collect();
-            chars.truncate(chars.len().saturating_sub(3));
-            result = chars.into_iter().collect();
-            result.push_str("...");
+        if directive.theme.is_none() && directive.filter.is_none() {
+            return None;
         }
-        
-        result
+        Some(directive)
     }
+}
+
+/// Compiled-size caps for theme and user regexes, so an oversized pattern
+/// fails with an error instead of bloating memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "themes", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegexLimits {
+    /// Max compiled program size in bytes (`RegexBuilder::size_limit`)
+    pub size_limit: usize,
+    /// Max lazy DFA cache size in bytes (`RegexBuilder::dfa_size_limit`)
+    pub dfa_size_limit: usize,
+}
+
+impl RegexLimits {
+    /// The `regex` crate's own default, so no pattern it accepts is rejected here
+    pub const DEFAULT_SIZE_LIMIT: usize = 10 * (1 << 20);
+    /// The `regex` crate's own default lazy DFA cache size
+    pub const DEFAULT_DFA_SIZE_LIMIT: usize = 2 * (1 << 20);
     
-    /// Strip ANSI escape codes completely (for compatibility)
-    #[allow(dead_code)]
-    fn strip_ansi_codes(text: &str) -> String {
-        let ansi_regex = regex::Regex::new(r"\x1B\[[0-9;]*m").unwrap();
-        ansi_regex.replace_all(text, "").to_string()
+    /// Compile `pattern` within these limits
+    pub fn compile(&self, pattern: &str) -> Result<regex::Regex, regex::Error> {
+        regex::RegexBuilder::new(pattern)
+            .size_limit(self.size_limit)
+            .dfa_size_limit(self.dfa_size_limit)
+            .build()
+    }
+    
+    /// Compile a `RegexSet` within these limits
+    pub fn compile_set(&self, patterns: &[String]) -> Result<regex::RegexSet, regex::Error> {
+        regex::RegexSetBuilder::new(patterns)
+            .size_limit(self.size_limit)
+            .dfa_size_limit(self.dfa_size_limit)
+            .build()
+    }
+}
+
+impl Default for RegexLimits {
+    fn default() -> Self {
+        Self {
+            size_limit: Self::DEFAULT_SIZE_LIMIT,
+            dfa_size_limit: Self::DEFAULT_DFA_SIZE_LIMIT,
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Inclusive 1-based visible column window for `--column-range`, same syntax as `LineRange`
+pub type ColumnRange = LineRange;
+
+#[cfg(feature = "themes")]
+/// A legacy-path keyword: (regex, style, replace_with, icon, icon_fallback)
+type KeywordPattern = (Regex, Style, Option<String>, Option<String>, Option<String>);
+
+#[cfg(feature = "themes")]
+/// `:word:` icon references: a letter or `_`, then word characters, `-` or `.`
+/// (`:warn-high:`, `:db.error:`), never ending in `-`/`.`; `http://` can't match
+pub const ICON_WORD_PATTERN: &str = r":([a-zA-Z_](?:[a-zA-Z0-9_.-]*[a-zA-Z0-9_])?):";
+
+#[cfg(feature = "themes")]
+static ICON_WORD_REGEX: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+#[cfg(feature = "themes")]
+/// `ICON_WORD_PATTERN`, compiled once
+pub(crate) fn icon_word_regex() -> &'static Regex {
+    ICON_WORD_REGEX.get_or_init(|| Regex::new(ICON_WORD_PATTERN).unwrap())
+}
+
+#[cfg(feature = "themes")]
+/// Default `--max-expand`: keyword styling may grow a line to 10x its length
+pub const DEFAULT_MAX_EXPAND: usize = 10;
+
+#[cfg(feature = "themes")]
+/// Lines shorter than this are budgeted as if they were this long, so a short
+/// but legitimately styled line (`ERROR`) isn't mistaken for a runaway pattern
+const MIN_EXPAND_BASE: usize = 64;
+
+#[cfg(feature = "themes")]
+static EXPAND_WARNED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "themes")]
+/// OSC sequences, e.g. an OSC 8 hyperlink's `ESC]8;;url ESC\` opener and closer
+static OSC_PATTERN: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+#[cfg(feature = "themes")]
+/// SGR and OSC sequences: everything on a line that takes up no columns
+/// (and what `--idempotent` keeps keywords out of)
+static ANSI_PATTERN: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+#[cfg(feature = "themes")]
+/// `ANSI_PATTERN` plus the tags and entities of a markup renderer's output
+/// (its literal text is escaped, so every `<` or `&` left starts one)
+static MARKUP_PATTERN: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+#[cfg(feature = "themes")]
+/// SGR sequences only, for the passes that track the style in effect
+static SGR_PATTERN: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+#[cfg(feature = "themes")]
+fn ansi_pattern() -> &'static Regex {
+    ANSI_PATTERN.get_or_init(|| Regex::new(r"\x1b\[[0-9;:]*m|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)").unwrap())
+}
+
+#[cfg(feature = "themes")]
+fn sgr_pattern() -> &'static Regex {
+    SGR_PATTERN.get_or_init(|| Regex::new(r"\x1b\[[0-9;:]*m").unwrap())
+}
+
+#[cfg(feature = "themes")]
+fn markup_pattern() -> &'static Regex {
+    MARKUP_PATTERN.get_or_init(|| Regex::new(r"\x1b\[[0-9;:]*m|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|<[^>]*>|&[#0-9A-Za-z]+;").unwrap())
+}
+
+#[cfg(feature = "themes")]
+/// True when `skip_region_segments` would return `text` whole, as one unskipped segment
+pub(crate) fn unsegmented(text: &str, skip: Option<&Regex>, skip_styled: bool, markup: bool) -> bool {
+    skip.is_none()
+        && !text.contains(if skip_styled || markup { "\x1b" } else { "\x1b]" })
+        && (!markup || !text.contains(['<', '&']))
+}
+
+#[cfg(feature = "themes")]
+/// `text` cut into `(segment, skipped)` pieces, in order; keyword styling only
+/// touches unskipped ones. OSC sequences are always skipped, so a hyperlink's
+/// URL stays intact while its visible text can be styled; the rest is cut at
+/// the matches of `skip` (a filter's `skip_regions`). With `skip_styled`
+/// (`--idempotent`) SGR sequences are skipped as well, along with any text
+/// between a style and its reset, so already highlighted input stays as it is.
+/// With `markup` (a markup renderer's output) tags and entities are always
+/// skipped, and `skip_styled` skips the text inside spans.
+pub(crate) fn skip_region_segments(text: &str, skip: Option<&Regex>, skip_styled: bool, markup: bool) -> Vec<(String, bool)> {
+    if unsegmented(text, skip, skip_styled, markup) {
+        return vec![(text.to_string(), false)];
+    }
+    let escapes = match (markup, skip_styled) {
+        (true, _) => markup_pattern(),
+        (false, true) => ansi_pattern(),
+        (false, false) => OSC_PATTERN.get_or_init(|| Regex::new(r"\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)").unwrap()),
+    };
+    
+    let mut segments = Vec::new();
+    let push_piece = |segments: &mut Vec<(String, bool)>, piece: &str, styled: bool| {
+        if styled {
+            segments.push((piece.to_string(), true));
+            return;
+        }
+        let mut last = 0;
+        for m in skip.iter().flat_map(|skip| skip.find_iter(piece)).filter(|m| !m.is_empty()) {
+            segments.push((piece[last..m.start()].to_string(), false));
+            segments.push((m.as_str().to_string(), true));
+            last = m.end();
+        }
+        segments.push((piece[last..].to_string(), false));
+    };
+    let mut last = 0;
+    let mut sgr = SgrState::default();
+    for m in escapes.find_iter(text) {
+        push_piece(&mut segments, &text[last..m.start()], sgr.is_styled());
+        segments.push((m.as_str().to_string(), true));
+        if skip_styled {
+            sgr.apply(m.as_str());
+        }
+        last = m.end();
+    }
+    push_piece(&mut segments, &text[last..], sgr.is_styled());
+    segments
+}
+
+#[cfg(feature = "themes")]
+/// `segments` with the matches of `regex` in the unskipped ones rendered by
+/// `render`. For `markup`, a segment a match was rendered into is split again,
+/// so the tags it now holds are skipped by the patterns that come after.
+pub(crate) fn replace_in_segments<F>(segments: Vec<(String, bool)>, regex: &Regex, skip_styled: bool, markup: bool, render: F) -> Vec<(String, bool)>
+where
+    F: Fn(&regex::Captures) -> String,
+{
+    segments.into_iter().flat_map(|(segment, skipped)| {
+        let replaced = match skipped {
+            true => None,
+            false => match regex.replace_all(&segment, &render) {
+                Cow::Owned(replaced) => Some(replaced),
+                Cow::Borrowed(_) => None,
+            },
+        };
+        match replaced {
+            Some(replaced) if markup => skip_region_segments(&replaced, None, skip_styled, markup),
+            Some(replaced) => vec![(replaced, false)],
+            None => vec![(segment, skipped)],
+        }
+    }).collect()
+}
+
+#[cfg(feature = "themes")]
+/// True when keyword styling has grown `original` bytes to `current` bytes past
+/// the `max_expand` multiplier (0 disables the cap); warns on stderr the first time
+pub(crate) fn expansion_exceeded(original: usize, current: usize, max_expand: usize) -> bool {
+    if max_expand == 0 || current <= original.max(MIN_EXPAND_BASE).saturating_mul(max_expand) {
+        return false;
+    }
+    if !EXPAND_WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!("Warning: keyword styling grew a line past {}x its length; passing such lines through unstyled (see --max-expand)", max_expand);
+    }
+    true
+}
+
+#[cfg(feature = "themes")]
+impl Default for JynxApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "themes")]
+impl JynxApp {
+    pub fn new() -> Self {
+        Self::with_theme(None, None, None, "left".to_string())
+    }
+    
+    pub fn with_theme_and_options(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String, no_color: bool) -> Self {
+        Self::with_theme_internal(theme, filter, width, align, no_color, None)
+    }
+    
+    /// Like `with_theme_and_options`, reusing compiled themes from a disk cache
+    pub fn with_theme_cached(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String, no_color: bool, cache: &ThemeCache) -> Self {
+        Self::with_theme_internal(theme, filter, width, align, no_color, Some(cache))
+    }
+    
+    pub fn with_theme(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String) -> Self {
+        Self::with_theme_internal(theme, filter, width, align, false, None)
+    }
+    
+    fn with_theme_internal(theme: Option<Theme>, filter: Option<String>, width: Option<usize>, align: String, no_color: bool, cache: Option<&ThemeCache>) -> Self {
+        // Emoji icons only when the terminal looks like it can render them
+        let detector_config = DetectorConfig { icon_mode: IconMode::detect(), ..Default::default() };
+        
+        let mut app = Self { 
+            // Built by set_theme, which knows whether theme patterns run on the legacy path
+            detector: None,
+            theme: None,
+            #[cfg(feature = "compiled")]
+            compiled_theme: None,
+            filter: None,
+            // Compile regex for :word: pattern detection
+            icon_pattern: icon_word_regex().clone(),
+            keyword_patterns: HashMap::new(),
+            skip_regions: None,
+            template_parser: ColorTemplateParser::new(no_color),
+            width,
+            align: TextAlign::from_str(&align),
+            truncate: TruncateSide::default(),
+            use_compiled: false,
+            no_color,
+            detector_config,
+            line_range: None,
+            auto_filter: false,
+            column_range: None,
+            header: None,
+            repeat_every: None,
+            max_expand: DEFAULT_MAX_EXPAND,
+            idempotent: false,
+            exec_filter: None,
+            summary: None,
+            stderr_prefix: None,
+            stderr_style: Style::new(),
+            whole_line_groups: Vec::new(),
+            columns: None,
+            ellipsis: DEFAULT_ELLIPSIS.to_string(),
+            ellipsis_style: Style::new(),
+            coalesce: false,
+            rainbow_brackets: false,
+            bracket_styles: Vec::new(),
+            json_pretty: false,
+            json_colors: None,
+            markdown: false,
+            markdown_styles: None,
+            gutter: false,
+            gutter_marks: None,
+            reset_lines: None,
+            search: None,
+            replacements: Vec::new(),
+            trim: false,
+            line_numbers: None,
+            number_style: Style::new(),
+            bell_on: Vec::new(),
+            bell_pattern: None,
+            count_only: false,
+            squeeze: false,
+            multiline_templates: false,
+            default_mode: DefaultMode::default(),
+            flush_mode: FlushMode::default(),
+            buffer_size: None,
+            #[cfg(feature = "gzip")]
+            gzip: false,
+            profile: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            theme_dirs: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            theme_vars: Default::default(),
+            // ANSI at the process-wide palette and bold mode, like `Style::paint`,
+            // until `with_ansi` or `with_renderer` sets this app's own
+            renderer: Arc::new(Ansi::current()),
+            escaped: Arc::new(PreEscaped(Arc::new(Ansi::current()))),
+        };
+        app.set_theme(theme, filter, cache);
+        app
+    }
+    
+    /// (Re)build everything that depends on the theme and filter
+    fn set_theme(&mut self, theme: Option<Theme>, filter: Option<String>, cache: Option<&ThemeCache>) {
+        // Enable compiled theme optimization for ~150x performance boost
+        #[cfg(feature = "compiled")]
+        {
+            let (compiled_theme, use_compiled) = Self::compile_theme(theme.as_ref(), cache);
+            let config = &self.detector_config;
+            self.compiled_theme = compiled_theme.map(|c| c
+                .with_icon_mode(config.icon_mode)
+                .with_auto_detection(!config.disabled)
+                .with_max_expand(self.max_expand)
+                .with_idempotent(self.idempotent));
+            self.use_compiled = use_compiled;
+        }
+        #[cfg(not(feature = "compiled"))]
+        let _ = cache;
+        
+        // Pre-compile keyword patterns for legacy fallback
+        self.keyword_patterns = if !self.use_compiled {
+            if let (Some(ref theme), Some(ref filter_name)) = (&theme, &filter) {
+                Self::compile_keyword_patterns(theme, filter_name)
+            } else {
+                HashMap::new()
+            }
+        } else {
+            HashMap::new() // Not needed when using compiled theme
+        };
+        self.skip_regions = match (&theme, &filter) {
+            (Some(theme), Some(filter_name)) if !self.use_compiled => theme.filters.get(filter_name)
+                .and_then(|filter| filter.skip_regex(&theme.regex_limits())
+                    .map_err(|e| eprintln!("Warning: Ignoring skip_regions of filter '{}': {}", filter_name, e))
+                    .ok()?),
+            _ => None,
+        };
+        
+        // Initialize template parser
+        let mut template_parser = ColorTemplateParser::new(self.no_color).with_renderer(self.escaped.clone());
+        if let Some(ref theme) = theme {
+            template_parser = template_parser.with_palette(&theme.palette);
+        }
+        self.template_parser = template_parser;
+        
+        self.theme = theme;
+        self.filter = filter;
+        self.bell_pattern = self.compile_bell_pattern();
+        self.number_style = self.resolve_number_style();
+        self.whole_line_groups = self.compile_whole_line_groups();
+        self.stderr_style = self.resolve_stderr_style();
+        self.ellipsis_style = self.resolve_ellipsis_style();
+        self.json_colors = self.resolve_json_colors();
+        self.markdown_styles = self.resolve_markdown_styles();
+        self.gutter_marks = self.resolve_gutter();
+        self.bracket_styles = self.resolve_bracket_styles();
+        self.detector = self.build_detector();
+    }
+    
+    /// Keyword regexes and styles of `whole_line` groups, in precedence order
+    fn compile_whole_line_groups(&self) -> Vec<(Regex, Style)> {
+        let Some((theme, filter)) = self.theme.as_ref().zip(self.filter.as_ref()) else {
+            return Vec::new();
+        };
+        let Some(filter) = theme.filters.get(filter) else {
+            return Vec::new();
+        };
+        let mut groups: Vec<&theme::StyleGroup> = filter.styles.values()
+            .filter(|style| style.whole_line && !style.keywords.is_empty())
+            .collect();
+        // Stable sort: equal priorities keep name order
+        groups.sort_by_key(|style| std::cmp::Reverse(style.rank()));
+        groups.into_iter()
+            .filter_map(|style| {
+                let keywords: Vec<String> = style.keywords.iter().map(|k| theme::StyleGroup::keyword_regex(k)).collect();
+                let regex = theme.regex_limits().compile(&keywords.join("|")).ok()?;
+                Some((regex, style.style()))
+            })
+            .collect()
+    }
+    
+    /// Wrap the highlighted line in the style of the first `whole_line` group
+    /// matching the input; inner resets re-apply it so the whole line stays styled
+    fn apply_whole_line<'a>(&self, input: &str, highlighted: Cow<'a, str>) -> Cow<'a, str> {
+        match self.whole_line_groups.iter().find(|(regex, _)| regex.is_match(input)) {
+            Some((_, style)) => Cow::Owned(self.escaped.paint_over(style, &highlighted)),
+            None => highlighted,
+        }
+    }
+    
+    /// One regex over the keywords of every bell group present in the active filter
+    fn compile_bell_pattern(&self) -> Option<Regex> {
+        let (theme, filter) = self.theme.as_ref().zip(self.filter.as_ref())?;
+        let styles = &theme.filters.get(filter)?.styles;
+        let keywords: Vec<String> = theme.metadata.bell_groups.iter()
+            .chain(&self.bell_on)
+            .filter_map(|group| styles.get(group))
+            .flat_map(|style| style.keywords.iter().map(|k| theme::StyleGroup::keyword_regex(k)))
+            .collect();
+        if keywords.is_empty() {
+            return None;
+        }
+        theme.regex_limits().compile(&keywords.join("|")).ok()
+    }
+    
+    /// Append a terminal bell (`\x07`) to output lines matching these style groups,
+    /// in addition to the theme's `metadata.bell_groups`
+    pub fn with_bell_on(mut self, groups: Vec<String>) -> Self {
+        self.bell_on = groups;
+        self.bell_pattern = self.compile_bell_pattern();
+        self
+    }
+    
+    /// Which side of lines wider than `--width` is cut and replaced by `...`
+    pub fn with_truncate(mut self, truncate: TruncateSide) -> Self {
+        self.truncate = truncate;
+        self
+    }
+    
+    /// Prefix every output line with its input line number (headers are not numbered)
+    pub fn with_line_numbers(mut self, line_numbers: Option<LineNumbers>) -> Self {
+        self.line_numbers = line_numbers;
+        self.number_style = self.resolve_number_style();
+        self
+    }
+    
+    /// Style for line numbers: `--number-color`, else the theme's `line_number_color`
+    fn resolve_number_style(&self) -> Style {
+        if self.no_color {
+            return Style::new();
+        }
+        let color = self.line_numbers.as_ref().and_then(|numbers| numbers.color.as_deref())
+            .or_else(|| self.theme.as_ref()?.metadata.line_number_color.as_deref());
+        color.map(|color| Style::new().color(self.palette_color(color))).unwrap_or_default()
+    }
+    
+    /// A theme palette name resolved to its color; other names are returned as-is
+    fn palette_color<'s>(&'s self, name: &'s str) -> &'s str {
+        self.theme.as_ref().and_then(|theme| theme.palette.get(name)).map_or(name, String::as_str)
+    }
+    
+    /// Treat lines starting with `prefix` as stderr output: the marker is removed
+    /// and the line is styled with the theme's `metadata.stderr_style` (bold red
+    /// by default), so a wrapper can tag stderr before merging it into one stream
+    pub fn with_stderr_prefix(mut self, prefix: Option<String>) -> Self {
+        self.stderr_prefix = prefix.filter(|prefix| !prefix.is_empty());
+        self.stderr_style = self.resolve_stderr_style();
+        self
+    }
+    
+    fn resolve_stderr_style(&self) -> Style {
+        if self.no_color || self.stderr_prefix.is_none() {
+            return Style::new();
+        }
+        match self.theme.as_ref().and_then(|theme| theme.metadata.stderr_style.as_deref()) {
+            Some(color) => Style::new().color(self.palette_color(color)),
+            None => Style::new().color("red").bold(),
+        }
+    }
+    
+    /// `   42 `, colored when a number style is set
+    fn number_prefix(&self, number: usize, width: usize) -> String {
+        format!("{} ", self.renderer.paint(&self.number_style, &format!("{:>width$}", number, width = width)))
+    }
+    
+    /// Whether an input line matches a bell group
+    fn rings_bell(&self, line: &str) -> bool {
+        self.bell_pattern.as_ref().is_some_and(|pattern| pattern.is_match(line))
+    }
+    
+    /// Limit highlighting (not `%c:` templates) to these visible columns of each line
+    ///
+    /// A range starting past the end of a line leaves it untouched; an end past
+    /// the end of the line just means "to the end".
+    pub fn with_column_range(mut self, column_range: Option<ColumnRange>) -> Self {
+        self.column_range = column_range;
+        self
+    }
+    
+    /// Emit `header` (highlighted like any line) before the first output line,
+    /// and again before every `repeat_every` further lines
+    pub fn with_header(mut self, header: Option<String>, repeat_every: Option<usize>) -> Self {
+        self.header = header;
+        self.repeat_every = repeat_every.filter(|&n| n > 0);
+        self
+    }
+    
+    /// Pass lines through unstyled when keyword styling would grow them past
+    /// `max_expand` times their length (0 disables the cap)
+    pub fn with_max_expand(mut self, max_expand: usize) -> Self {
+        self.max_expand = max_expand;
+        #[cfg(feature = "compiled")]
+        {
+            self.compiled_theme = self.compiled_theme.map(|c| c.with_max_expand(max_expand));
+        }
+        self
+    }
+    
+    /// Leave text that is already styled (an SGR code up to its reset) and the
+    /// escapes themselves to keywords and theme detections, so piping jynx output
+    /// through jynx again changes nothing
+    pub fn with_idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = idempotent;
+        #[cfg(feature = "compiled")]
+        {
+            self.compiled_theme = self.compiled_theme.map(|c| c.with_idempotent(idempotent));
+        }
+        self
+    }
+    
+    /// Let a `#jynx: theme=<name> filter=<name>` first line reconfigure the pipeline
+    pub fn with_auto_filter(mut self, auto_filter: bool) -> Self {
+        self.auto_filter = auto_filter;
+        self
+    }
+    
+    /// Directories searched first when a directive or `--theme-map` names a theme
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_theme_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.theme_dirs = dirs;
+        self
+    }
+    
+    /// `--theme-var` overrides for themes named by a directive or `--theme-map`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_theme_vars(mut self, vars: std::collections::BTreeMap<String, String>) -> Self {
+        self.theme_vars = vars;
+        self
+    }
+    
+    /// Copy of this pipeline with the directive's theme and/or filter applied
+    fn with_directive(&self, directive: &Directive) -> Result<Self, JynxError> {
+        let theme = match &directive.theme {
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(name) => Some(Theme::load_theme_with_path_in(Some(name), false, &Theme::theme_search_path_with(&self.theme_dirs), &self.theme_vars)?.0),
+            #[cfg(target_arch = "wasm32")]
+            Some(name) => return Err(JynxError::ThemeError(format!("Cannot load theme '{}' without a filesystem", name))),
+            None => self.theme.clone(),
+        };
+        let filter = directive.filter.clone().or_else(|| self.filter.clone());
+        
+        let mut app = self.clone();
+        app.set_theme(theme, filter, None);
+        Ok(app)
+    }
+    
+    /// Pipe every input line through a long-lived external command and highlight its reply
+    /// (the command must answer each line with one line and flush; see `exec_filter`)
+    pub fn with_exec_filter(mut self, command: Option<String>) -> Self {
+        self.exec_filter = command;
+        self
+    }
+    
+    /// Print a count of summary-group keywords and auto-detections after the last line
+    pub fn with_summary(mut self, summary: Option<SummaryOutput>) -> Self {
+        self.summary = summary;
+        self
+    }
+    
+    /// Collapse runs of identical input lines (compared before coloring) into the
+    /// first one, followed by a dim `(xN)` repeat count, like `uniq -c`
+    pub fn with_squeeze(mut self, squeeze: bool) -> Self {
+        self.squeeze = squeeze;
+        self
+    }
+    
+    /// Suppress highlighted output and print a per-style-group count table instead
+    pub fn with_count_only(mut self, count_only: bool) -> Self {
+        self.count_only = count_only;
+        self
+    }
+    
+    /// Run these substitutions, in order, on each line after highlighting and
+    /// before `--trim`/width formatting; they see escape codes, so patterns
+    /// should avoid matching ANSI sequences
+    pub fn with_replacements(mut self, replacements: Vec<Replacement>) -> Self {
+        self.replacements = replacements;
+        self
+    }
+    
+    /// Split each line on `delimiter` and lay the fields out as columns: every field
+    /// is highlighted on its own, then padded (or truncated) to its spec's width and
+    /// alignment. Fields are rejoined with a single space; fields beyond the specs keep
+    /// their natural width
+    pub fn with_columns(mut self, columns: Option<Vec<ColumnSpec>>, delimiter: String) -> Self {
+        self.columns = columns.map(|specs| (specs, delimiter));
+        self
+    }
+    
+    /// Color `()`, `[]` and `{}` by nesting depth within each line, cycling through
+    /// the theme's `metadata.bracket_colors` (built-in colors otherwise); brackets
+    /// without a partner on the line get an error style
+    pub fn with_rainbow_brackets(mut self, rainbow_brackets: bool) -> Self {
+        self.rainbow_brackets = rainbow_brackets;
+        self.bracket_styles = self.resolve_bracket_styles();
+        self
+    }
+    
+    fn resolve_bracket_styles(&self) -> Vec<Style> {
+        if !self.rainbow_brackets || self.no_color {
+            return Vec::new();
+        }
+        let colors = self.theme.as_ref().map(|theme| &theme.metadata.bracket_colors).filter(|colors| !colors.is_empty());
+        match colors {
+            Some(colors) => colors.iter()
+                .map(|color| Style::new().color(self.palette_color(color)))
+                .collect(),
+            None => DEFAULT_BRACKET_COLORS.iter()
+                .map(|color| Style::new().color(*color))
+                .collect(),
+        }
+    }
+    
+    /// Pretty-print lines holding a JSON object or array, colored by value type from
+    /// the theme's `json` filter (`key`, `string`, `number`, `boolean`, `null` groups)
+    /// or built-in colors. Such lines skip the rest of the pipeline; other lines don't
+    pub fn with_json_pretty(mut self, json_pretty: bool) -> Self {
+        self.json_pretty = json_pretty;
+        self.json_colors = self.resolve_json_colors();
+        self
+    }
+    
+    fn resolve_json_colors(&self) -> Option<json_pretty::JsonColors> {
+        self.json_pretty.then(|| match self.no_color {
+            true => json_pretty::JsonColors::default(),
+            false => json_pretty::JsonColors::for_theme(self.theme.as_ref()),
+        })
+    }
+    
+    /// Render markdown emphasis (`**bold**`, `*italic*`, `_italic_`, `` `code` ``) and
+    /// strip its markers; inline code is styled by the theme's `markdown` filter `code` group
+    pub fn with_markdown(mut self, markdown: bool) -> Self {
+        self.markdown = markdown;
+        self.markdown_styles = self.resolve_markdown_styles();
+        self
+    }
+    
+    fn resolve_markdown_styles(&self) -> Option<markdown::MarkdownStyles> {
+        self.markdown.then(|| match self.no_color {
+            true => markdown::MarkdownStyles::default(),
+            false => markdown::MarkdownStyles::for_theme(self.theme.as_ref()),
+        })
+    }
+    
+    /// Prefix every line with a one-character severity column (`E`, `W`, `I`, ...)
+    /// from the theme's `metadata.summary_groups` in the active filter, colored
+    /// like the group; lines matching no group get a blank column. The gutter is
+    /// added after `--width`, so it never counts against the content width
+    pub fn with_gutter(mut self, gutter: bool) -> Self {
+        self.gutter = gutter;
+        self.gutter_marks = self.resolve_gutter();
+        self
+    }
+    
+    fn resolve_gutter(&self) -> Option<summary::Gutter> {
+        self.gutter.then(|| summary::Gutter::for_theme(self.theme.as_ref(), self.filter.as_deref(), self.no_color))
+    }
+    
+    /// Text marking where `--width` cut a line (default `...`); its width is
+    /// reserved out of the line, and the theme's `metadata.ellipsis_style` colors it
+    pub fn with_ellipsis(mut self, ellipsis: Option<String>) -> Self {
+        self.ellipsis = ellipsis.unwrap_or_else(|| DEFAULT_ELLIPSIS.to_string());
+        self
+    }
+    
+    /// `metadata.ellipsis_style`: a color (palette or built-in) with optional
+    /// attribute words before it, e.g. `dim red`
+    fn resolve_ellipsis_style(&self) -> Style {
+        let Some(spec) = self.theme.as_ref().and_then(|theme| theme.metadata.ellipsis_style.as_deref()).filter(|_| !self.no_color) else {
+            return Style::new();
+        };
+        spec.split_whitespace().fold(Style::new(), |style, word| match word {
+            "bold" => style.bold(),
+            "dim" => style.dim(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            "reverse" => style.reverse(),
+            color => style.color(self.palette_color(color)),
+        })
+    }
+    
+    /// The truncation indicator as drawn: styled and reset, or plain
+    fn ellipsis_indicator(&self) -> String {
+        self.renderer.paint(&self.ellipsis_style, &self.ellipsis)
+    }
+    
+    /// Merge adjacent spans with the same style into one: a reset directly followed
+    /// by the style it just ended is dropped along with that style (lossless)
+    pub fn with_coalesce(mut self, coalesce: bool) -> Self {
+        self.coalesce = coalesce;
+        self
+    }
+    
+    /// Start (and with `Both`, also end) every output line with an SGR reset so it
+    /// renders from a clean state; zero-width, so `--width` math is unaffected
+    pub fn with_reset_lines(mut self, reset_lines: Option<ResetLines>) -> Self {
+        self.reset_lines = reset_lines;
+        self
+    }
+    
+    /// Show every match of `search` in reverse video, like a search in `less`,
+    /// on top of whatever coloring the line already has (matches only see
+    /// visible text, never escape codes)
+    pub fn with_search(mut self, search: Option<Regex>) -> Self {
+        self.search = search;
+        self
+    }
+    
+    /// Drop trailing whitespace from the visible text (escape sequences such as
+    /// resets are kept) so width and alignment start from the real content
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+    
+    /// Buffer the whole input and expand `%c:` templates across newlines before
+    /// the per-line pipeline; memory grows with input size, so output only starts at EOF
+    pub fn with_multiline_templates(mut self, multiline_templates: bool) -> Self {
+        self.multiline_templates = multiline_templates;
+        self
+    }
+    
+    /// Render styling with `renderer` (ANSI escapes by default): templates, detections,
+    /// icons, keywords and every theme-derived style go through it. A markup
+    /// renderer's output can't take the options that edit SGR sequences (see
+    /// `check_renderer`); highlighting such an app fails up front.
+    pub fn with_renderer(mut self, renderer: Arc<dyn Renderer>) -> Self {
+        self.escaped = Arc::new(PreEscaped(renderer.clone()));
+        self.template_parser = self.template_parser.with_renderer(self.escaped.clone());
+        self.renderer = renderer;
+        self
+    }
+    
+    /// Fail when the renderer is markup (`Html`, `Pango`) and an option that only
+    /// works on SGR output is set: widths and truncation, column ranges,
+    /// `--search`, `--rainbow-brackets`, `--coalesce` and `--reset-lines`
+    pub fn check_renderer(&self) -> Result<(), JynxError> {
+        if !self.renderer.is_markup() {
+            return Ok(());
+        }
+        let sgr_only: Vec<&str> = [
+            (self.width.is_some(), "--width"),
+            (self.columns.as_ref().is_some_and(|(specs, _)| specs.iter().any(|spec| spec.width > 0)), "--columns widths"),
+            (self.column_range.is_some(), "--column-range"),
+            (self.search.is_some(), "--search"),
+            (self.rainbow_brackets, "--rainbow-brackets"),
+            (self.coalesce, "--coalesce"),
+            (self.reset_lines.is_some(), "--reset-lines"),
+        ].into_iter().filter(|(set, _)| *set).map(|(_, option)| option).collect();
+        match sgr_only.is_empty() {
+            true => Ok(()),
+            false => Err(JynxError::ProcessingError(format!("{} can't be used with markup output", sgr_only.join(", ")))),
+        }
+    }
+    
+    /// ANSI output with named colors at `palette` depth (`--palette`) and `bold`
+    /// rendered per `bold_mode` (`--bold-mode`); replaces any renderer set before
+    pub fn with_ansi(self, palette: Palette, bold_mode: BoldMode) -> Self {
+        self.with_renderer(Arc::new(Ansi::new(palette, bold_mode)))
+    }
+    
+    /// Time each pipeline stage (templates, auto-detection, icons, keywords, width);
+    /// read the breakdown with `profile_report` once the input is done
+    pub fn with_profile(mut self, profile: bool) -> Self {
+        self.profile = profile.then(Arc::default);
+        self
+    }
+    
+    /// `--profile` breakdown of the time spent per stage so far
+    pub fn profile_report(&self) -> Option<String> {
+        self.profile.as_ref().map(|profile| profile.render())
+    }
+    
+    /// Run `f`, counting its time towards `stage` when profiling
+    fn timed<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        match &self.profile {
+            Some(profile) => profile.time(stage, f),
+            None => f(),
+        }
+    }
+    
+    /// Without a theme and a filter, run the built-in auto-detectors (`Auto`, the
+    /// default) or only expand `%c:` templates (`Plain`); either given means `Auto`
+    pub fn with_default_mode(mut self, default_mode: DefaultMode) -> Self {
+        self.default_mode = default_mode;
+        self
+    }
+    
+    /// Flush output after every line (default) or only when the buffer fills / at EOF
+    pub fn with_flush_mode(mut self, flush_mode: FlushMode) -> Self {
+        self.flush_mode = flush_mode;
+        self
+    }
+    
+    /// Output buffer capacity in bytes; a large buffer with `FlushMode::OnEof` is fastest
+    pub fn with_buffer_size(mut self, buffer_size: Option<usize>) -> Self {
+        self.buffer_size = buffer_size.filter(|&n| n > 0);
+        self
+    }
+    
+    /// Gzip-decompress every input of `run_files` (stdin included); files whose
+    /// name ends in `.gz` are always decompressed
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+    
+    /// Only process and emit input lines inside this range; others are suppressed
+    pub fn with_line_range(mut self, line_range: Option<LineRange>) -> Self {
+        self.line_range = line_range;
+        self
+    }
+    
+    /// Render icons as emoji, text fallbacks, or not at all (`:word:` and detected values are only colored)
+    pub fn with_icon_mode(mut self, icon_mode: IconMode) -> Self {
+        self.detector_config.icon_mode = icon_mode;
+        self.detector = self.build_detector();
+        #[cfg(feature = "compiled")]
+        {
+            self.compiled_theme = self.compiled_theme.map(|c| c.with_icon_mode(icon_mode));
+        }
+        self
+    }
+    
+    /// Turn all auto-detection on or off, built-in and theme patterns alike
+    pub fn with_auto_detection(mut self, enabled: bool) -> Self {
+        self.detector_config.disabled = !enabled;
+        self.detector = self.build_detector();
+        #[cfg(feature = "compiled")]
+        {
+            self.compiled_theme = self.compiled_theme.map(|c| c.with_auto_detection(enabled));
+        }
+        self
+    }
+    
+    /// Enable opt-in auto-detectors such as `keyval`, `numbers` and `strings`
+    pub fn with_extra_detectors(mut self, names: Vec<String>) -> Self {
+        self.detector_config.extra_detectors = names;
+        self.detector = self.build_detector();
+        self
+    }
+    
+    /// Emit detected file:line references as OSC 8 `file://` hyperlinks
+    pub fn with_hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.detector_config.hyperlinks = hyperlinks;
+        self.detector = self.build_detector();
+        self
+    }
+    
+    /// Give each distinct match of these regexes its own stable color (request IDs etc.)
+    pub fn with_hash_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.detector_config.hash_patterns = patterns;
+        self.detector = self.build_detector();
+        self
+    }
+    
+    /// Highlight ad-hoc regexes for this run, like `grep --color`
+    pub fn with_match_patterns(mut self, patterns: Vec<String>, color: Option<String>) -> Self {
+        self.detector_config.match_patterns = patterns;
+        self.detector_config.match_color = color;
+        self.detector = self.build_detector();
+        self
+    }
+    
+    /// Try to initialize auto-detector, but gracefully fall back if it fails
+    ///
+    /// On the legacy path the theme's `auto_detection` patterns (those enabled
+    /// for it) replace the built-in ones, as the compiled theme does.
+    fn build_detector(&self) -> Option<AutoDetector> {
+        let config = &self.detector_config;
+        if config.disabled {
+            return None;
+        }
+        let theme_patterns: Vec<(String, String, Style)> = match &self.theme {
+            Some(theme) if !self.use_compiled => theme.auto_detection.iter()
+                .filter(|(_, pattern)| pattern.enabled_for(theme::ProcessingPath::Legacy))
+                .map(|(name, pattern)| (name.clone(), pattern.anchored_pattern(), pattern.style()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let limits = self.theme.as_ref().map(Theme::regex_limits).unwrap_or_default();
+        let detector = AutoDetector::from_config(config)
+            .and_then(|detector| detector.with_theme_patterns(&theme_patterns, config.icon_mode, &limits));
+        match detector {
+            Ok(d) => Some(d),
+            Err(e) => {
+                eprintln!("Warning: Failed to initialize auto-detection: {}", e);
+                None
+            }
+        }
+    }
+    
+    /// Compile theme into the optimized runtime form, or report why we can't
+    #[cfg(feature = "compiled")]
+    fn compile_theme(theme: Option<&Theme>, cache: Option<&ThemeCache>) -> (Option<CompiledTheme>, bool) {
+        let Some(theme) = theme else {
+            return (None, false);
+        };
+        
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = cache {
+            return match CompiledTheme::load_or_compile(theme, cache) {
+                Ok(compiled) => (Some(compiled), true),
+                Err(e) => {
+                    Self::report_degraded(&*e);
+                    (None, false)
+                }
+            };
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = cache;
+        
+        match CompiledTheme::from_theme(theme) {
+            Ok(mut compiled) => {
+                if let Err(e) = compiled.init_runtime() {
+                    Self::report_degraded(&*e);
+                    (None, false)
+                } else {
+                    (Some(compiled), true)
+                }
+            },
+            Err(e) => {
+                Self::report_degraded(&*e);
+                (None, false)
+            }
+        }
+    }
+    
+    /// Say what broke and what still works when the compiled path is lost
+    #[cfg(feature = "compiled")]
+    fn report_degraded(error: &dyn Error) {
+        eprintln!("Warning: Failed to compile theme: {}", error);
+        eprintln!("Warning: Falling back to per-pattern highlighting (slower; theme patterns that fail again are skipped)");
+    }
+    
+    /// Pre-compile all keyword patterns for performance
+    fn compile_keyword_patterns(theme: &Theme, filter_name: &str) -> HashMap<String, KeywordPattern> {
+        let mut patterns = HashMap::new();
+        
+        let limits = theme.regex_limits();
+        if let Some(filter) = theme.filters.get(filter_name) {
+            for style_group in filter.styles.values() {
+                let style = style_group.style();
+                
+                for keyword in &style_group.keywords {
+                    let pattern = theme::StyleGroup::keyword_regex(keyword);
+                    
+                    match limits.compile(&pattern) {
+                        Ok(regex) => {
+                            patterns.insert(keyword.clone(), (regex, style.clone(), style_group.replace_with.clone(),
+                                style_group.icon.clone(), style_group.icon_fallback.clone()));
+                        },
+                        Err(e) => eprintln!("Warning: Skipping keyword '{}': {}", keyword, e),
+                    }
+                }
+            }
+        }
+        
+        patterns
+    }
+
+    /// Main entry point - stream processor that reads stdin and writes to stdout
+    pub fn run(&self) -> Result<(), JynxError> {
+        // Lock stdin and stdout once for the entire session - more efficient
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        
+        self.run_io(BufReader::new(stdin.lock()), stdout.lock())
+    }
+    
+    /// Stream processor over any reader/writer pair
+    pub fn run_io<R: BufRead, W: Write>(&self, reader: R, writer: W) -> Result<(), JynxError> {
+        let mut writer = match self.buffer_size {
+            Some(capacity) => BufWriter::with_capacity(capacity, writer),
+            None => BufWriter::new(writer),
+        };
+        
+        if self.count_only {
+            return self.count_keywords(reader, writer);
+        }
+        
+        // Stream processing: line by line, immediate output
+        let mut stream = self.highlight_stream(reader);
+        let mut terminated = true;
+        for line in stream.by_ref() {
+            // Write immediately and flush for pipe compatibility (unless bulk mode)
+            let line = line?;
+            terminated = line.ends_with('\n');
+            writer.write_all(line.as_bytes())?;
+            if self.flush_mode == FlushMode::EveryLine {
+                writer.flush()?;
+            }
+        }
+        
+        match self.summary {
+            // Not on the end of an unterminated last line
+            Some(SummaryOutput::Stdout) => writeln!(writer, "{}{}", if terminated { "" } else { "\n" }, stream.summary())?,
+            Some(SummaryOutput::Stderr) => eprintln!("{}", stream.summary()),
+            None => {},
+        }
+        
+        writer.flush()?;
+        Ok(())
+    }
+    
+    /// Run each file (`-` is stdin) through the pipeline in order, like `cat`.
+    /// With a theme map, a file whose name matches a glob gets that theme; each
+    /// mapped theme is loaded and compiled once. Unreadable files are reported
+    /// and skipped, then fail the run.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_files<W: Write>(&self, files: &[PathBuf], theme_map: Option<&ThemeMap>, mut writer: W) -> Result<(), JynxError> {
+        let mut themed: HashMap<String, JynxApp> = HashMap::new();
+        let mut failed = 0;
+        
+        for path in files {
+            let app = match theme_map.and_then(|map| map.theme_for(path)) {
+                Some(name) => {
+                    if !themed.contains_key(name) {
+                        let directive = Directive { theme: Some(name.to_string()), filter: None };
+                        match self.with_directive(&directive) {
+                            Ok(app) => {
+                                themed.insert(name.to_string(), app);
+                            },
+                            Err(e) => eprintln!("Warning: --theme-map theme '{}': {}", name, e),
+                        }
+                    }
+                    themed.get(name).unwrap_or(self)
+                },
+                None => self,
+            };
+            
+            let result = self.open_input(path)
+                .map_err(JynxError::from)
+                .and_then(|reader| app.run_io(BufReader::new(reader), &mut writer));
+            if let Err(e) = result {
+                eprintln!("jynx: {}: {}", path.display(), e);
+                failed += 1;
+            }
+        }
+        
+        if failed > 0 {
+            return Err(JynxError::ProcessingError(format!("{} of {} input files failed", failed, files.len())));
+        }
+        Ok(())
+    }
+    
+    /// A `run_files` input (`-` is stdin), gzip-decompressed if it is named `*.gz` or `--gzip` is set
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_input(&self, path: &std::path::Path) -> io::Result<Box<dyn io::Read>> {
+        let reader: Box<dyn io::Read> = if path.as_os_str() == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(std::fs::File::open(path)?)
+        };
+        #[cfg(feature = "gzip")]
+        if self.gzip || path.extension().is_some_and(|ext| ext == "gz") {
+            return Ok(Box::new(flate2::read::GzDecoder::new(reader)));
+        }
+        Ok(reader)
+    }
+    
+    /// Where `--output` sends highlighted lines: the file at `path` (created or
+    /// truncated), or stdout for `None` and `-`. ANSI codes are written either
+    /// way, so a file keeps its colors unless `--no-color` is given.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_output(path: Option<&std::path::Path>) -> io::Result<Box<dyn Write>> {
+        match path {
+            Some(path) if path.as_os_str() != "-" => Ok(Box::new(std::fs::File::create(path)?)),
+            _ => Ok(Box::new(io::stdout().lock())),
+        }
+    }
+    
+    /// `--count-only`: tally style group hits over the (`--lines`-limited) input
+    fn count_keywords<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> Result<(), JynxError> {
+        let mut counts = summary::KeywordCounts::for_filter(self.theme.as_ref(), self.filter.as_deref());
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if let Some(range) = &self.line_range {
+                if range.is_past_end(index + 1) {
+                    break;
+                }
+                if index + 1 < range.start {
+                    continue;
+                }
+            }
+            counts.count(&line);
+        }
+        
+        write!(writer, "{}", counts.render())?;
+        writer.flush()?;
+        Ok(())
+    }
+    
+    /// Highlighted lines of `reader` as an iterator (what `run_io` writes out)
+    pub fn highlight_stream<R: BufRead>(&self, reader: R) -> HighlightStream<'_, R> {
+        HighlightStream::new(self, reader)
+    }
+    
+    /// Process a single line - the core transformation logic
+    /// Implements the complete 5-layer processing pipeline
+    fn process_line<'a>(&self, line: &'a str) -> Result<Cow<'a, str>, JynxError> {
+        Ok(self.render_line(line, true, None))
+    }
+    
+    /// `process_line`, optionally skipping templates already expanded over the whole input,
+    /// with `number` as the line number prefix when `--number` is on
+    ///
+    /// Each stage only allocates when it changes the line, so a line nothing
+    /// applies to comes back borrowed
+    fn render_line<'a>(&self, line: &'a str, templates: bool, number: Option<usize>) -> Cow<'a, str> {
+        // Pre-pass: a stderr marker is stripped here and styles the line below
+        let (line, from_stderr) = match self.stderr_prefix.as_deref().and_then(|prefix| line.strip_prefix(prefix)) {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if let Some(pretty) = self.json_colors.as_ref().and_then(|colors| json_pretty::pretty(line, colors, self.renderer.as_ref())) {
+            return Cow::Owned(pretty);
+        }
+        // Literal text is escaped once, here (input expanded for multiline templates already is)
+        let mut result = match templates {
+            true => self.renderer.escape(line),
+            false => Cow::Borrowed(line),
+        };
+        
+        if let Some((specs, delimiter)) = &self.columns {
+            result = Cow::Owned(self.render_columns(&result, templates, specs, delimiter));
+        } else if templates {
+            // FIRST: Apply color templates (%c:colorname(text) patterns) - highest priority
+            result = match self.timed(Stage::Template, || self.template_parser.process(&result)) {
+                Cow::Owned(expanded) => Cow::Owned(expanded),
+                Cow::Borrowed(_) => result,
+            };
+        }
+        
+        if let Some(rendered) = self.markdown_styles.as_ref().and_then(|styles| markdown::render(&result, styles, self.escaped.as_ref())) {
+            result = Cow::Owned(rendered);
+        }
+        
+        // Skip other color processing if in no-color mode
+        if !self.no_color {
+            result = match &self.column_range {
+                // Fields were already highlighted one by one
+                _ if self.columns.is_some() => result,
+                // Only the selected visible columns are highlighted; prefix and suffix pass through
+                Some(range) => {
+                    let (prefix, rest) = Self::split_at_column(&result, range.start - 1);
+                    let (middle, suffix) = match range.end {
+                        Some(end) => Self::split_at_column(rest, end + 1 - range.start),
+                        None => (rest, ""),
+                    };
+                    Cow::Owned(format!("{}{}{}", prefix, self.highlight(middle), suffix))
+                },
+                None => match self.highlight(&result) {
+                    Cow::Owned(highlighted) => Cow::Owned(highlighted),
+                    Cow::Borrowed(_) => result,
+                },
+            };
+            if !self.bracket_styles.is_empty() {
+                let opens: Vec<String> = self.bracket_styles.iter().map(|style| self.escaped.color_open(style)).collect();
+                let error = self.escaped.color_open(&Style::new().color("red").bold().reverse());
+                result = Cow::Owned(Self::color_brackets(&result, &opens, &error));
+            }
+            result = self.apply_whole_line(line, result);
+            if from_stderr {
+                result = Cow::Owned(self.escaped.paint_over(&self.stderr_style, &result));
+            }
+        }
+        
+        for replacement in &self.replacements {
+            result = Cow::Owned(replacement.apply(&result));
+        }
+        
+        if let Some(search) = self.search.as_ref().filter(|_| !self.no_color) {
+            result = Cow::Owned(Self::reverse_matches(&result, search));
+        }
+        
+        if self.trim {
+            result = Cow::Owned(Self::trim_trailing_visible(&result));
+        }
+        
+        if self.coalesce {
+            result = Cow::Owned(Self::coalesce_spans(&result));
+        }
+        
+        let prefix = self.line_numbers.as_ref().zip(number)
+            .map(|(numbers, number)| (self.number_prefix(number, numbers.width), numbers.inside));
+        if let Some((prefix, true)) = &prefix {
+            result.to_mut().insert_str(0, prefix);
+        }
+        
+        // 5. Apply width and alignment formatting if specified (always last)
+        if let Some(width) = self.width {
+            result = Cow::Owned(self.timed(Stage::Width, || self.format_line_width(&result, width)));
+        }
+        
+        // Outside the content column: the number is added in front of the formatted line
+        if let Some((prefix, false)) = &prefix {
+            result.to_mut().insert_str(0, prefix);
+        }
+        
+        if let Some(gutter) = &self.gutter_marks {
+            result.to_mut().insert_str(0, &gutter.mark(line, self.renderer.as_ref()));
+        }
+        
+        if let Some(reset_lines) = self.reset_lines.filter(|_| !self.no_color) {
+            let result = result.to_mut();
+            result.insert_str(0, theme::AnsiCodes::RESET);
+            if reset_lines == ResetLines::Both {
+                result.push_str(theme::AnsiCodes::RESET);
+            }
+        }
+        
+        result
+    }
+    
+    /// `--columns`: templates and highlighting per field, then each field fitted to its spec
+    fn render_columns(&self, line: &str, templates: bool, specs: &[ColumnSpec], delimiter: &str) -> String {
+        line.split(delimiter).enumerate().map(|(index, field)| {
+            let mut field = match templates {
+                true => self.timed(Stage::Template, || self.template_parser.process(field)).into_owned(),
+                false => field.to_string(),
+            };
+            if !self.no_color {
+                field = self.highlight(&field).into_owned();
+            }
+            match specs.get(index) {
+                Some(spec) if spec.width > 0 => self.timed(Stage::Width, || self.fit_width(&field, spec.width, &spec.align)),
+                _ => field,
+            }
+        }).collect::<Vec<_>>().join(" ")
+    }
+    
+    /// Layers 2-4: auto-detection, icon mappings and keyword highlighting
+    /// (borrowed when none of them matched)
+    fn highlight<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.default_mode == DefaultMode::Plain && self.theme.is_none() && self.filter.is_none() {
+            return Cow::Borrowed(text);
+        }
+        
+        // Use compiled theme for optimal performance if available
+        if self.use_compiled {
+            return self.timed(Stage::Compiled, || self.apply_compiled_theme(text));
+        }
+        
+        // Legacy processing pipeline (layers 2-4)
+        let mut result = Cow::Borrowed(text);
+        
+        // 2. Apply auto-detection if available
+        if let Some(detector) = &self.detector {
+            result = self.timed(Stage::AutoDetect, || detector.highlight_line(text, self.escaped.as_ref()));
+        }
+        
+        // 3. Apply icon mappings (:word: patterns) if theme is available
+        if let (Some(theme), Some(filter_name)) = (&self.theme, &self.filter) {
+            if let Cow::Owned(replaced) = self.timed(Stage::Icon, || self.apply_icon_patterns(&result, theme, filter_name)) {
+                result = Cow::Owned(replaced);
+            }
+        }
+        
+        // 4. Apply keyword highlighting if theme and filter are available  
+        if let (Some(theme), Some(filter_name)) = (&self.theme, &self.filter) {
+            if let Cow::Owned(replaced) = self.timed(Stage::Keyword, || self.apply_keyword_highlighting(&result, theme, filter_name)) {
+                result = Cow::Owned(replaced);
+            }
+        }
+        
+        result
+    }
+    
+    /// High-performance compiled processing (layers 2-4 in one pass)
+    #[cfg(feature = "compiled")]
+    fn apply_compiled_theme<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        // Without a filter the theme only matters if it brings its own detection patterns
+        let compiled_theme = self.compiled_theme.as_ref()
+            .filter(|compiled| self.filter.is_some() || !compiled.auto_detection.is_empty());
+        if let Some(compiled_theme) = compiled_theme {
+            let result = compiled_theme.process_text(text, self.filter.as_deref(), self.escaped.as_ref());
+            // The theme replaces the built-in detectors, but opt-in ones still apply
+            match self.detector.as_ref().map(|detector| detector.highlight_extras(&result, self.escaped.as_ref())) {
+                Some(Cow::Owned(extended)) => Cow::Owned(extended),
+                _ => result,
+            }
+        } else if let Some(detector) = &self.detector {
+            // Fallback to basic auto-detection only
+            detector.highlight_line(text, self.escaped.as_ref())
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+    
+    /// Without the `compiled` feature `use_compiled` is never set
+    #[cfg(not(feature = "compiled"))]
+    fn apply_compiled_theme<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(text)
+    }
+    
+    /// Apply :word: icon pattern replacements
+    fn apply_icon_patterns<'a>(&self, text: &'a str, theme: &Theme, filter_name: &str) -> Cow<'a, str> {
+        self.icon_pattern.replace_all(text, |caps: &regex::Captures| {
+            let word = &caps[1];
+            
+            if let Some(icon_mapping) = theme.get_icon_mapping(filter_name, word) {
+                // Replace :word: with colored icon + word (e.g. ":critical:" -> "🔥 critical")
+                icon_mapping.formatted_icon(word, self.detector_config.icon_mode, self.escaped.as_ref())
+            } else {
+                // Keep original if no mapping found (graceful degradation)
+                caps[0].to_string()
+            }
+        })
+    }
+    
+    /// Apply keyword highlighting based on theme styles (using pre-compiled patterns)
+    fn apply_keyword_highlighting<'a>(&self, text: &'a str, _theme: &Theme, _filter_name: &str) -> Cow<'a, str> {
+        // A line that is one segment and matches no keyword comes out unchanged
+        if unsegmented(text, self.skip_regions.as_ref(), self.idempotent, self.escaped.is_markup())
+            && !self.keyword_patterns.values().any(|(regex, ..)| regex.is_match(text)) {
+            return Cow::Borrowed(text);
+        }
+        
+        // Keywords never match inside the filter's skip_regions
+        let mut segments = skip_region_segments(text, self.skip_regions.as_ref(), self.idempotent, self.escaped.is_markup());
+        
+        // Use pre-compiled patterns for much better performance
+        for (regex, style, replace_with, icon, icon_fallback) in self.keyword_patterns.values() {
+            segments = replace_in_segments(segments, regex, self.idempotent, self.escaped.is_markup(), |caps| {
+                theme::StyleGroup::styled_match(style, icon.as_deref(), icon_fallback.as_deref(), replace_with.as_deref(),
+                    &caps[0], self.detector_config.icon_mode, self.escaped.as_ref())
+            });
+            let styled_len: usize = segments.iter().map(|(segment, _)| segment.len()).sum();
+            if expansion_exceeded(text.len(), styled_len, self.max_expand) {
+                return Cow::Borrowed(text);
+            }
+        }
+        
+        Cow::Owned(segments.into_iter().map(|(segment, _)| segment).collect())
+    }
+    
+    /// Format line to specified width with alignment
+    /// Handles ANSI escape codes properly to calculate visible text length
+    fn format_line_width(&self, text: &str, width: usize) -> String {
+        self.fit_width(text, width, &self.align)
+    }
+    
+    /// Pad `text` to `width` visible columns with `align`, or truncate it per `--truncate`
+    fn fit_width(&self, text: &str, width: usize, align: &TextAlign) -> String {
+        // Calculate visible text length by removing ANSI escape codes
+        let visible_len = Self::get_visible_length(text);
+        
+        if visible_len >= width {
+            // If text is already wider than target, truncate it gracefully
+            let indicator = self.ellipsis_indicator();
+            return match self.truncate {
+                TruncateSide::End => Self::truncate_to_width(text, width, &indicator),
+                TruncateSide::Start => Self::truncate_keeping(text, width, 0, &indicator),
+                TruncateSide::Middle => {
+                    let available = width.saturating_sub(Self::get_visible_length(&indicator));
+                    Self::truncate_keeping(text, width, available - available / 2, &indicator)
+                },
+            };
+        }
+        
+        let padding_needed = width - visible_len;
+        
+        match align {
+            TextAlign::Left => {
+                // Left align: add padding to the right
+                format!("{}{}", text, " ".repeat(padding_needed))
+            },
+            TextAlign::Right => {
+                // Right align: add padding to the left  
+                format!("{}{}", " ".repeat(padding_needed), text)
+            },
+            TextAlign::Center => {
+                // Center align: split padding between left and right
+                let left_padding = padding_needed / 2;
+                let right_padding = padding_needed - left_padding;
+                format!("{}{}{}", " ".repeat(left_padding), text, " ".repeat(right_padding))
+            }
+        }
+    }
+    
+    /// Get visible length of text (excluding ANSI escape codes)
+    /// More accurate than strip_ansi_codes for length calculation
+    fn get_visible_length(text: &str) -> usize {
+        let stripped = ansi_pattern().replace_all(text, "");
+        
+        // Count Unicode grapheme clusters for accurate character width
+        stripped.chars().count()
+    }
+    
+    /// Truncate text to specified width while preserving ANSI codes, ending it
+    /// with `indicator` (reset first so the head's colors don't bleed into it)
+    fn truncate_to_width(text: &str, width: usize, indicator: &str) -> String {
+        let visible_len = Self::get_visible_length(text);
+        if visible_len <= width {
+            return text.to_string();
+        }
+        
+        // The indicator takes its own width out of the kept text; without room for it, just cut
+        let indicator_width = Self::get_visible_length(indicator);
+        let (head, indicator) = match width > indicator_width {
+            true => (Self::split_at_column(text, width - indicator_width).0, indicator),
+            false => (Self::split_at_column(text, width).0, ""),
+        };
+        
+        let mut result = head.to_string();
+        if head.contains('\x1B') {
+            result.push_str(theme::AnsiCodes::RESET);
+        }
+        result.push_str(indicator);
+        result
+    }
+    
+    /// Cut the middle of an over-wide line: keep `head` visible characters, an
+    /// ellipsis, and as many trailing characters as still fit in `width`
+    ///
+    /// Escape codes from the cut part are replayed before the tail so it keeps
+    /// its colors; the ellipsis (`indicator`) carries only its own style.
+    fn truncate_keeping(text: &str, width: usize, head: usize, indicator: &str) -> String {
+        let visible_len = Self::get_visible_length(text);
+        if visible_len <= width {
+            return text.to_string();
+        }
+        let indicator_width = Self::get_visible_length(indicator);
+        if width <= indicator_width {
+            // No room for an ellipsis: just the rightmost columns
+            let (_, tail) = Self::split_at_column(text, visible_len - width);
+            return tail.to_string();
+        }
+        
+        let tail_len = width - indicator_width - head;
+        // Escape codes ahead of a cut-away start belong with the cut, not the (empty) head
+        let head_text = if head == 0 { "" } else { Self::split_at_column(text, head).0 };
+        let (cut, tail_text) = Self::split_at_column(text, visible_len - tail_len);
+        let ansi_regex = ansi_pattern();
+        let replay: String = ansi_regex.find_iter(cut).map(|m| m.as_str()).collect();
+        
+        let mut result = head_text.to_string();
+        if ansi_regex.is_match(head_text) {
+            result.push_str(theme::AnsiCodes::RESET);
+        }
+        result.push_str(indicator);
+        result.push_str(&replay);
+        result.push_str(tail_text);
+        result
+    }
+    
+    /// Split text after `column` visible characters; escape codes before the
+    /// split stay with the left part. Columns past the end give `(text, "")`.
+    fn split_at_column(text: &str, column: usize) -> (&str, &str) {
+        let ansi_regex = ansi_pattern();
+        let mut visible_chars = 0;
+        let mut i = 0;
+        
+        while i < text.len() {
+            if text[i..].starts_with('\x1b') {
+                if let Some(mat) = ansi_regex.find(&text[i..]).filter(|mat| mat.start() == 0) {
+                    i += mat.len();
+                    continue;
+                }
+            }
+            if visible_chars == column {
+                break;
+            }
+            match text[i..].chars().next() {
+                Some(ch) => {
+                    visible_chars += 1;
+                    i += ch.len_utf8();
+                },
+                None => break,
+            }
+        }
+        
+        text.split_at(i)
+    }
+    
+    /// Wrap matches of `search` in the visible text with reverse video. The span
+    /// ends with "reverse off" (`\x1b[27m`) so surrounding colors carry on, and
+    /// reverse is re-asserted after any escape inside it (a reset would drop it).
+    fn reverse_matches(text: &str, search: &Regex) -> String {
+        const REVERSE_OFF: &str = "\x1b[27m";
+        let ansi_regex = ansi_pattern();
+        
+        // Visible text, and for each of its bytes the offset in `text`
+        let mut visible = String::new();
+        let mut offsets = Vec::new();
+        let mut last = 0;
+        for m in ansi_regex.find_iter(text).map(|m| m.range()).chain(std::iter::once(text.len()..text.len())) {
+            visible.push_str(&text[last..m.start]);
+            offsets.extend(last..m.start);
+            last = m.end;
+        }
+        
+        let spans: Vec<(usize, usize)> = search.find_iter(&visible)
+            .filter(|m| !m.is_empty())
+            .map(|m| (offsets[m.start()], offsets[m.end() - 1] + 1))
+            .collect();
+        if spans.is_empty() {
+            return text.to_string();
+        }
+        
+        let mut result = String::with_capacity(text.len() + spans.len() * 10);
+        let mut position = 0;
+        for (start, end) in spans {
+            result.push_str(&text[position..start]);
+            result.push_str(theme::AnsiCodes::REVERSE);
+            result.push_str(&ansi_regex.replace_all(&text[start..end], |caps: &regex::Captures| {
+                format!("{}{}", &caps[0], theme::AnsiCodes::REVERSE)
+            }));
+            result.push_str(REVERSE_OFF);
+            position = end;
+        }
+        result.push_str(&text[position..]);
+        result
+    }
+    
+    /// Style each bracket outside escape sequences by its depth (`styles` cycled), or
+    /// with `error` if it has no partner on the line; the styling in effect before
+    /// the bracket is restored after it
+    fn color_brackets(text: &str, styles: &[String], error: &str) -> String {
+        let ansi_regex = ansi_pattern();
+        
+        // Text split into escape sequences and visible runs, in order
+        let mut pieces: Vec<(usize, &str, bool)> = Vec::new();
+        let mut last = 0;
+        for m in ansi_regex.find_iter(text) {
+            pieces.push((last, &text[last..m.start()], false));
+            pieces.push((m.start(), m.as_str(), true));
+            last = m.end();
+        }
+        pieces.push((last, &text[last..], false));
+        
+        // Match brackets: offset -> depth, or None when unbalanced
+        let mut depths: HashMap<usize, Option<usize>> = HashMap::new();
+        let mut open: Vec<(usize, char)> = Vec::new();
+        for (start, piece, _) in pieces.iter().filter(|(_, _, escape)| !escape) {
+            for (i, c) in piece.char_indices() {
+                let offset = start + i;
+                match c {
+                    '(' | '[' | '{' => open.push((offset, c)),
+                    ')' | ']' | '}' => {
+                        let opener = match c { ')' => '(', ']' => '[', _ => '{' };
+                        if open.last().is_some_and(|&(_, top)| top == opener) {
+                            let (start, _) = open.pop().unwrap_or_default();
+                            depths.insert(start, Some(open.len()));
+                            depths.insert(offset, Some(open.len()));
+                        } else {
+                            depths.insert(offset, None);
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+        for (offset, _) in open {
+            depths.insert(offset, None);
+        }
+        if depths.is_empty() {
+            return text.to_string();
+        }
+        
+        let reset = theme::AnsiCodes::RESET;
+        let mut result = String::with_capacity(text.len() + depths.len() * 20);
+        // SGR sequences since the last reset, replayed after each bracket
+        let mut active = String::new();
+        for (start, piece, escape) in pieces {
+            if escape {
+                result.push_str(piece);
+                if piece == reset || piece == "\x1b[m" {
+                    active.clear();
+                } else if piece.ends_with('m') {
+                    active.push_str(piece);
+                }
+                continue;
+            }
+            for (i, c) in piece.char_indices() {
+                match depths.get(&(start + i)) {
+                    Some(depth) => {
+                        let style = depth.map_or(error, |depth| &styles[depth % styles.len()]);
+                        result.push_str(&format!("{}{}{}{}", style, c, reset, active));
+                    },
+                    None => result.push(c),
+                }
+            }
+        }
+        result
+    }
+    
+    /// Drop every reset that is directly followed by SGR sequences re-opening exactly
+    /// the style it closed, together with those sequences
+    fn coalesce_spans(text: &str) -> String {
+        let sgr_regex = sgr_pattern();
+        let reset = theme::AnsiCodes::RESET;
+        
+        // Escape sequences (true) and text runs (false), in order
+        let mut tokens: Vec<(&str, bool)> = Vec::new();
+        let mut last = 0;
+        for m in sgr_regex.find_iter(text) {
+            if m.start() > last {
+                tokens.push((&text[last..m.start()], false));
+            }
+            tokens.push((m.as_str(), true));
+            last = m.end();
+        }
+        if last < text.len() {
+            tokens.push((&text[last..], false));
+        }
+        
+        let is_reset = |token: &str| token == reset || token == "\x1b[m";
+        let mut result = String::with_capacity(text.len());
+        // SGR sequences since the last reset
+        let mut active = String::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let (token, escape) = tokens[i];
+            i += 1;
+            if !escape {
+                result.push_str(token);
+                continue;
+            }
+            if !is_reset(token) {
+                active.push_str(token);
+                result.push_str(token);
+                continue;
+            }
+            // The sequences right after this reset, up to the next text or reset
+            let mut reopened = String::new();
+            let mut end = i;
+            while end < tokens.len() && tokens[end].1 && !is_reset(tokens[end].0) {
+                reopened.push_str(tokens[end].0);
+                end += 1;
+            }
+            if !active.is_empty() && reopened == active && end < tokens.len() {
+                i = end;
+            } else {
+                result.push_str(token);
+                active.clear();
+            }
+        }
+        result
+    }
+    
+    /// Remove trailing whitespace that would be visible, keeping any escape
+    /// sequences interleaved with it (a closing reset must survive)
+    fn trim_trailing_visible(text: &str) -> String {
+        let ansi_regex = ansi_pattern();
+        
+        // Visible text runs between escape sequences, in order
+        let mut pieces: Vec<(&str, bool)> = Vec::new();
+        let mut last = 0;
+        for m in ansi_regex.find_iter(text) {
+            pieces.push((&text[last..m.start()], false));
+            pieces.push((m.as_str(), true));
+            last = m.end();
+        }
+        pieces.push((&text[last..], false));
+        
+        // Trim visible runs from the end until one has content left
+        for (piece, is_escape) in pieces.iter_mut().rev() {
+            if *is_escape {
+                continue;
+            }
+            *piece = piece.trim_end();
+            if !piece.is_empty() {
+                break;
+            }
+        }
+        
+        pieces.into_iter().map(|(piece, _)| piece).collect()
+    }
+    
+    /// Strip ANSI escape codes completely (for compatibility)
+    #[allow(dead_code)]
+    fn strip_ansi_codes(text: &str) -> String {
+        ansi_pattern().replace_all(text, "").to_string()
+    }
+}
+
+#[cfg(all(test, feature = "themes"))]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    
+    fn run_to_string(app: &JynxApp, input: &str) -> String {
+        let mut output = Vec::new();
+        app.run_io(Cursor::new(input), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+    
+    #[test]
+    fn test_error_conversions() {
+        fn parse_theme(yaml: &str) -> Result<Theme, JynxError> {
+            Ok(serde_yaml::from_str(yaml)?)
+        }
+        assert!(matches!(parse_theme("metadata: ["), Err(JynxError::ThemeError(_))));
+        
+        let pattern: Result<Regex, JynxError> = RegexLimits::default().compile("(unclosed").map_err(JynxError::from);
+        assert!(matches!(pattern, Err(JynxError::ThemeError(e)) if e.starts_with("invalid pattern")));
+        
+        let boxed: Box<dyn Error> = Box::new(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert!(matches!(JynxError::from(boxed), JynxError::IoError(_)));
+        let boxed: Box<dyn Error> = "no such theme".into();
+        assert!(matches!(JynxError::from(boxed), JynxError::ThemeError(e) if e == "no such theme"));
+    }
+    
+    #[test]
+    fn test_error_line_context() {
+        let error = JynxError::ProcessingError("bad escape".to_string()).line_context(4523);
+        assert_eq!(error.to_string(), "Processing error: line 4523: bad escape");
+        
+        let error = JynxError::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "closed")).line_context(7);
+        assert_eq!(error.to_string(), "IO error: line 7: closed");
+        assert!(matches!(&error, JynxError::IoError(e) if e.kind() == io::ErrorKind::BrokenPipe));
+        assert!(error.source().is_some());
+    }
+    
+    #[test]
+    fn test_theme_palette_in_templates() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "p", version: "1.0.0", description: "palette" }
+palette: { accent: "crimson" }
+filters: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), None, None, "left".to_string());
+        let output = app.process_line("%c:accent(down)").unwrap();
+        assert_eq!(output, "\x1B[38;5;196mdown\x1B[0m");
+    }
+    
+    #[test]
+    fn test_line_range_parse() {
+        assert_eq!(LineRange::parse("2:4").unwrap(), LineRange { start: 2, end: Some(4) });
+        assert_eq!(LineRange::parse("3:").unwrap(), LineRange { start: 3, end: None });
+        assert_eq!(LineRange::parse("5").unwrap(), LineRange { start: 5, end: None });
+        assert_eq!(LineRange::parse(":2").unwrap(), LineRange { start: 1, end: Some(2) });
+        assert!(LineRange::parse("0:3").is_err());
+        assert!(LineRange::parse("4:2").is_err());
+        assert!(LineRange::parse("a:b").is_err());
+    }
+    
+    #[test]
+    fn test_bounded_line_range() {
+        let app = JynxApp::new().with_line_range(Some(LineRange::parse("2:3").unwrap()));
+        let output = run_to_string(&app, "one\ntwo\nthree\nfour\nfive\n");
+        assert_eq!(output, "two\nthree\n");
+    }
+    
+    #[test]
+    fn test_open_ended_line_range() {
+        let app = JynxApp::new().with_line_range(Some(LineRange::parse("4:").unwrap()));
+        let output = run_to_string(&app, "one\ntwo\nthree\nfour\nfive\n");
+        assert_eq!(output, "four\nfive\n");
+    }
+    
+    #[test]
+    fn test_no_icons() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "i", version: "1.0.0", description: "icons" }
+filters:
+  logs:
+    icon_mappings:
+      critical: { icon: "🔥", color: "red" }
+    styles: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_icon_mode(IconMode::None);
+        let red = crate::extended_colors::get_extended_color_code("red");
+        assert_eq!(app.process_line(":critical:").unwrap(), format!("{}critical\x1B[0m", red));
+        
+        let output = app.process_line("see https://example.com").unwrap();
+        assert!(!output.contains("🔗") && !output.contains("[URL]"));
+    }
+    
+    #[test]
+    fn test_icon_words_with_hyphens_and_dots() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "w", version: "1.0.0", description: "icon words" }
+filters:
+  logs:
+    icon_mappings:
+      warn-high: { icon: "⚠️", color: "orange" }
+      db.error: { icon: "💥", color: "red" }
+    styles: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false)
+            .with_icon_mode(IconMode::None);
+        let orange = crate::extended_colors::get_extended_color_code("orange");
+        let red = crate::extended_colors::get_extended_color_code("red");
+        assert_eq!(app.process_line(":warn-high: disk").unwrap(), format!("{}warn-high\x1B[0m disk", orange));
+        assert_eq!(app.process_line("x :db.error:").unwrap(), format!("x {}db.error\x1B[0m", red));
+        
+        // Same charset on the legacy path; trailing separators and URLs don't match
+        assert_eq!(&app.icon_pattern.captures(":warn-high:").unwrap()[1], "warn-high");
+        assert!(!app.icon_pattern.is_match(":warn-: :.x: http://example.com"));
+    }
+    
+    #[test]
+    fn test_icon_fallback_without_unicode() {
+        use auto_detect::Icons;
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "f", version: "1.0.0", description: "fallback" }
+auto_detection:
+  paths: { pattern: "/[a-z/]+\\.rs", color: "violet" }
+filters:
+  logs:
+    icon_mappings:
+      critical: { icon: "🔥", icon_fallback: "[!]", color: "red" }
+    styles: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_icon_mode(IconMode::Ascii);
+        let output = app.process_line(":critical:").unwrap();
+        assert!(output.contains("[!]") && output.contains("critical"));
+        assert!(!output.contains("🔥"));
+        
+        // The compiled theme's detection icons fall back too
+        let path = Style::new().color("violet").paint("/src/main.rs");
+        assert_eq!(app.process_line("see /src/main.rs").unwrap(), format!("see {} {}", Icons::PATH_FALLBACK, path));
+        assert_eq!(app.clone().with_icon_mode(IconMode::None).process_line("see /src/main.rs").unwrap(), format!("see {}", path));
+        
+        let unicode = app.with_icon_mode(IconMode::Unicode);
+        assert!(unicode.process_line(":critical:").unwrap().contains("🔥"));
+        assert_eq!(unicode.process_line("see /src/main.rs").unwrap(), format!("see {} {}", Icons::PATH, path));
+    }
+    
+    #[test]
+    fn test_directive_parse() {
+        assert_eq!(
+            Directive::parse("#jynx: theme=rust filter=compile"),
+            Some(Directive { theme: Some("rust".to_string()), filter: Some("compile".to_string()) })
+        );
+        assert!(Directive::parse("#jynx:").is_none());
+        assert!(Directive::parse("#jynx: filter logs").is_none());
+        assert!(Directive::parse("#jynx: colour=red").is_none());
+        assert!(Directive::parse("#jynx: filter=a filter=b").is_none());
+        assert!(Directive::parse(" #jynx: filter=logs").is_none());
+    }
+    
+    #[test]
+    fn test_auto_filter_directive() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "d", version: "1.0.0", description: "directive" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), None, None, "left".to_string()).with_auto_filter(true);
+        
+        // Valid directive: applied and stripped
+        let output = run_to_string(&app, "#jynx: filter=logs\nERROR here\n");
+        assert_eq!(output, "\x1B[38;5;196mERROR\x1B[0m here\n");
+        
+        // Malformed directive: passed through, pipeline unchanged
+        let output = run_to_string(&app, "#jynx: filter logs\nERROR here\n");
+        assert_eq!(output, "#jynx: filter logs\nERROR here\n");
+    }
+    
+    #[test]
+    fn test_column_range() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "c", version: "1.0.0", description: "columns" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_column_range(Some(ColumnRange::parse("4:12").unwrap()));
+        let red = "\x1B[38;5;196m";
+        
+        // First ERROR straddles the start boundary and is left alone; the second is inside
+        assert_eq!(app.process_line("ERROR  ERROR").unwrap(), format!("ERROR  {}ERROR\x1B[0m", red));
+        assert_eq!(app.process_line("..ERROR").unwrap(), "..ERROR");
+        // Range past the end of the line: nothing to highlight
+        assert_eq!(app.process_line("ab").unwrap(), "ab");
+    }
+    
+    #[test]
+    fn test_repeated_header() {
+        let input: String = (1..=10).map(|n| format!("row {}\n", n)).collect();
+        
+        let app = JynxApp::new().with_header(Some("NAME".to_string()), Some(4));
+        let output = run_to_string(&app, &input);
+        // Before rows 1, 5 and 9
+        assert_eq!(output.lines().filter(|line| *line == "NAME").count(), 3);
+        assert!(output.starts_with("NAME\nrow 1\n"));
+        
+        let app = JynxApp::new().with_header(Some("NAME".to_string()), None);
+        assert_eq!(run_to_string(&app, &input).matches("NAME").count(), 1);
+    }
+    
+    #[test]
+    fn test_no_detect() {
+        let url = "see https://example.com/docs";
+        assert_ne!(JynxApp::new().process_line(url).unwrap(), url);
+        assert_eq!(JynxApp::new().with_auto_detection(false).process_line(url).unwrap(), url);
+        
+        // Theme-defined detection is skipped too
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "n", version: "1.0.0", description: "no detect" }
+auto_detection:
+  urls: { pattern: "(https?://\\S+)", color: "azure" }
+filters:
+  logs:
+    styles: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string());
+        assert_ne!(app.process_line(url).unwrap(), url);
+        assert_eq!(app.with_auto_detection(false).process_line(url).unwrap(), url);
+    }
+    
+    #[test]
+    fn test_default_mode() {
+        let line = "docs at https://example.com/guide %c:red(now)";
+        let red = crate::extended_colors::get_extended_color_code("red");
+        
+        let auto = JynxApp::new().process_line(line).unwrap();
+        assert!(!auto.contains("https://example.com/guide %"), "{:?}", auto);
+        
+        let plain = JynxApp::new().with_default_mode(DefaultMode::Plain).process_line(line).unwrap();
+        assert_eq!(plain, format!("docs at https://example.com/guide {}now\x1B[0m", red));
+        
+        // A filter (even without a theme) opts back into highlighting
+        let filtered = JynxApp::with_theme(None, Some("logs".to_string()), None, "left".to_string())
+            .with_default_mode(DefaultMode::Plain);
+        assert_eq!(filtered.process_line(line).unwrap(), auto);
+    }
+    
+    #[test]
+    fn test_unchanged_line_is_borrowed() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "b", version: "1.0.0", description: "borrowed" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let compiled = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string());
+        let mut legacy = compiled.clone();
+        legacy.use_compiled = false;
+        legacy.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        legacy.detector = legacy.build_detector();
+        
+        for app in [&compiled, &legacy, &JynxApp::new()] {
+            assert!(matches!(app.process_line("all quiet here").unwrap(), Cow::Borrowed(_)));
+            assert!(matches!(app.process_line("ERROR at https://example.com").unwrap(), Cow::Owned(_)));
+        }
+        let padded = JynxApp::with_theme(None, None, Some(20), "left".to_string());
+        assert!(matches!(padded.process_line("all quiet").unwrap(), Cow::Owned(_)));
+    }
+    
+    /// Records the size of every write that reaches the underlying writer
+    struct WriteSizes(Vec<usize>);
+    
+    impl Write for WriteSizes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn test_buffer_size() {
+        let input = "line\n".repeat(20);
+        let app = JynxApp::new().with_auto_detection(false).with_flush_mode(FlushMode::OnEof);
+        
+        let mut sizes = WriteSizes(Vec::new());
+        app.clone().run_io(input.as_bytes(), &mut sizes).unwrap();
+        assert_eq!(sizes.0, vec![input.len()]);
+        
+        let mut sizes = WriteSizes(Vec::new());
+        app.with_buffer_size(Some(16)).run_io(input.as_bytes(), &mut sizes).unwrap();
+        assert!(sizes.0.len() > 1);
+        assert!(sizes.0.iter().all(|&n| n <= 16));
+        assert_eq!(sizes.0.iter().sum::<usize>(), input.len());
+    }
+    
+    #[test]
+    fn test_multiline_templates() {
+        let input = "%c:red(one\ntwo) done\n";
+        let red = crate::extended_colors::get_extended_color_code("red");
+        let app = JynxApp::new().with_auto_detection(false);
+        
+        // Per-line processing can't balance the span: left literal
+        let mut output = Vec::new();
+        app.clone().run_io(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "%c:red(one\ntwo) done\n");
+        
+        let mut output = Vec::new();
+        app.with_multiline_templates(true).run_io(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), format!("{}one\x1B[0m\n{}two\x1B[0m done\n", red, red));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_filter() {
+        let app = JynxApp::new().with_auto_detection(false).with_exec_filter(Some("sed -u 's/^/> /'".to_string()));
+        let mut output = Vec::new();
+        app.run_io("one\ntwo\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "> one\n> two\n");
+        
+        // A command that exits without answering, or that fails to run, leaves lines untouched
+        for command in ["exit 0", "jynx-no-such-command"] {
+            let app = JynxApp::new().with_auto_detection(false).with_exec_filter(Some(command.to_string()));
+            let mut output = Vec::new();
+            app.run_io("one\ntwo\n".as_bytes(), &mut output).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), "one\ntwo\n");
+        }
+    }
+    
+    #[test]
+    fn test_summary() {
+        let theme = Theme::from_yaml_str(r#"
+metadata:
+  name: "s"
+  version: "1.0.0"
+  description: "summary"
+  summary_groups: { errors: [errors, fatal], warnings: [warnings] }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+      fatal: { keywords: ["FATAL"], color: "crimson" }
+      warnings: { keywords: ["WARN*"], color: "amber" }
+"#).unwrap();
+        let input = "ERROR one\nWARN two WARNING\nFATAL at https://example.com/x\nok\n";
+        
+        let mut counts = summary::Summary::for_theme(Some(&theme), Some("logs"));
+        let detector = AutoDetector::new().unwrap();
+        for line in input.lines() {
+            counts.count(line, Some(&detector));
+        }
+        assert_eq!(counts.total("errors"), 2);
+        assert_eq!(counts.total("warnings"), 2);
+        assert_eq!(counts.total("urls"), 1);
+        
+        let app = JynxApp::with_theme_and_options(Some(theme), Some("logs".to_string()), None, "left".to_string(), true)
+            .with_summary(Some(SummaryOutput::Stdout));
+        let mut output = Vec::new();
+        app.run_io(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().last(), Some("2 errors, 2 warnings, 1 URLs."));
+    }
+    
+    #[test]
+    fn test_gutter() {
+        let theme = Theme::from_yaml_str(r#"
+metadata:
+  name: "g"
+  version: "1.0.0"
+  description: "gutter"
+  summary_groups: { errors: [errors], warnings: [warnings], info: [info] }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+      warnings: { keywords: ["WARN"], color: "amber" }
+      info: { keywords: ["INFO"], color: "azure" }
+"#).unwrap();
+        let plain = JynxApp::with_theme_and_options(Some(theme.clone()), Some("logs".to_string()), Some(8), "left".to_string(), true)
+            .with_gutter(true);
+        let mut output = Vec::new();
+        plain.run_io("ERROR disk full\nWARN after ERROR\nINFO ok\nneutral\n".as_bytes(), &mut output).unwrap();
+        // The mark sits outside the 8 content columns; a neutral line gets a blank one
+        assert_eq!(String::from_utf8(output).unwrap(), "E ERROR...\nW WARN ...\nI INFO ok \n  neutral \n");
+        
+        let colored = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false)
+            .with_gutter(true);
+        let mut output = Vec::new();
+        colored.run_io("ERROR disk\n".as_bytes(), &mut output).unwrap();
+        let error_style = theme.filters["logs"].styles["errors"].to_ansi();
+        assert!(String::from_utf8(output).unwrap().starts_with(&format!("{}E{} ", error_style, theme::AnsiCodes::RESET)));
+    }
+    
+    #[test]
+    fn test_regex_limits() {
+        let alternation = (0..2000).map(|i| format!("keyword{}", i)).collect::<Vec<_>>().join("|");
+        let tight = RegexLimits { size_limit: 10_000, ..Default::default() };
+        let err = tight.compile(&alternation).unwrap_err();
+        assert!(err.to_string().contains("size limit"));
+        assert!(RegexLimits::default().compile(&alternation).is_ok());
+    }
+    
+    #[test]
+    fn test_trim() {
+        assert_eq!(JynxApp::trim_trailing_visible("\x1B[31mERROR  \x1B[0m  "), "\x1B[31mERROR\x1B[0m");
+        assert_eq!(JynxApp::trim_trailing_visible("  \x1B[1m \x1B[0m"), "\x1B[1m\x1B[0m");
+        assert_eq!(JynxApp::trim_trailing_visible("a b \t"), "a b");
+        
+        // Width math starts from the trimmed content
+        let app = JynxApp::with_theme(None, None, Some(6), "right".to_string()).with_auto_detection(false);
+        assert_eq!(app.process_line("ok    ").unwrap(), "ok    ");
+        assert_eq!(app.with_trim(true).process_line("ok    ").unwrap(), "    ok");
+    }
+    
+    #[test]
+    fn test_theme_map() {
+        let map = ThemeMap::parse("*.rs:rust, build-??.log:logs").unwrap();
+        assert_eq!(map.theme_for(std::path::Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(map.theme_for(std::path::Path::new("build-01.log")), Some("logs"));
+        assert_eq!(map.theme_for(std::path::Path::new("build-1.log")), None);
+        assert!(ThemeMap::parse("*.rs").is_err());
+        
+        let dir = std::env::temp_dir().join(format!("jynx-theme-map-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let theme = |keyword: &str, color: &str| format!(
+            "metadata: {{ name: \"{0}\", version: \"1.0.0\", description: \"map\" }}\nfilters:\n  main:\n    styles:\n      words: {{ keywords: [\"{0}\"], color: \"{1}\" }}\n",
+            keyword, color
+        );
+        std::fs::write(dir.join("rust.yml"), theme("fn", "crimson")).unwrap();
+        std::fs::write(dir.join("theme_map-logs.yml"), theme("ERROR", "amber")).unwrap();
+        std::fs::write(dir.join("main.rs"), "fn ERROR\n").unwrap();
+        std::fs::write(dir.join("app.log"), "fn ERROR\n").unwrap();
+        
+        // By path, and by name from a `--theme-dir`
+        let map = ThemeMap::parse(&format!("*.rs:{},*.log:map-logs", dir.join("rust.yml").display())).unwrap();
+        let app = JynxApp::with_theme(None, Some("main".to_string()), None, "left".to_string())
+            .with_auto_detection(false)
+            .with_theme_dirs(vec![dir.clone()]);
+        let mut output = Vec::new();
+        app.run_files(&[dir.join("main.rs"), dir.join("app.log")], Some(&map), &mut output).unwrap();
+        
+        let output = String::from_utf8(output).unwrap();
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines, vec![format!("{}fn\x1B[0m ERROR", crimson), format!("fn {}ERROR\x1B[0m", amber)]);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_output_file() {
+        let path = std::env::temp_dir().join(format!("jynx-output-{}.log", std::process::id()));
+        let app = JynxApp::with_theme(None, None, None, "left".to_string()).with_auto_detection(false);
+        let output = JynxApp::open_output(Some(&path)).unwrap();
+        app.run_io(BufReader::new("%c:red(hot)\nplain\n".as_bytes()), output).unwrap();
+        
+        let red = crate::extended_colors::get_extended_color_code("red");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("{}hot\x1B[0m\nplain\n", red));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_output_file_html_document() {
+        let path = std::env::temp_dir().join(format!("jynx-output-{}.html", std::process::id()));
+        let app = JynxApp::with_theme(None, None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_renderer(Arc::new(crate::text_styles::Html));
+        let mut document = HtmlDocument::new(JynxApp::open_output(Some(&path)).unwrap()).unwrap();
+        app.run_io(BufReader::new("%c:red(hot) a<b\n".as_bytes()), &mut document).unwrap();
+        document.finish().unwrap();
+        
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<pre><span style=\"color:#ff0000\">hot</span> a&lt;b\n</pre>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_input() {
+        // gzip of "ERROR disk full\nINFO retrying\n"
+        const GZIPPED: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x73, 0x0d, 0x0a, 0xf2, 0x0f, 0x52,
+            0x48, 0xc9, 0x2c, 0xce, 0x56, 0x48, 0x2b, 0xcd, 0xc9, 0xe1, 0xf2, 0xf4, 0x73, 0xf3, 0x57, 0x28,
+            0x4a, 0x2d, 0x29, 0xaa, 0xcc, 0xcc, 0x4b, 0xe7, 0x02, 0x00, 0xb5, 0xcf, 0xb6, 0xac, 0x1e, 0x00,
+            0x00, 0x00,
+        ];
+        let app = JynxApp::with_theme_and_options(None, None, None, "left".to_string(), true);
+        let mut output = Vec::new();
+        app.run_io(BufReader::new(flate2::read::GzDecoder::new(GZIPPED)), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "ERROR disk full\nINFO retrying\n");
+        
+        // run_files decompresses `*.gz` by name, anything else only with --gzip
+        let dir = std::env::temp_dir().join(format!("jynx-gzip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.log.gz"), GZIPPED).unwrap();
+        std::fs::write(dir.join("app.log.old"), GZIPPED).unwrap();
+        let mut output = Vec::new();
+        app.run_files(&[dir.join("app.log.gz")], None, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "ERROR disk full\nINFO retrying\n");
+        let mut output = Vec::new();
+        app.with_gzip(true).run_files(&[dir.join("app.log.old")], None, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "ERROR disk full\nINFO retrying\n");
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_count_only() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "c", version: "1.0.0", description: "counts" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR", "FATAL"], color: "crimson" }
+      warnings: { keywords: ["WARN"], color: "amber" }
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_count_only(true);
+        let output = run_to_string(&app, "ERROR and ERROR\nWARN\nFATAL after WARN\nok\n");
+        assert_eq!(output, "\
+group       lines  matches
+errors          2        3
+warnings        2        2
+");
+    }
+    
+    #[test]
+    fn test_renderer_styles_every_stage() {
+        use crate::text_styles::Html;
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "r", version: "1.0.0", description: "renderer" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let expected = format!("{} {} now", Html.paint(&Style::new().color("red"), "boom"), Html.paint(&Style::new().color("crimson"), "ERROR"));
+        
+        let app = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_renderer(Arc::new(Html));
+        assert_eq!(app.process_line("%c:red(boom) ERROR now").unwrap(), expected);
+        
+        let mut legacy = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_renderer(Arc::new(Html));
+        legacy.use_compiled = false;
+        legacy.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        legacy.detector = legacy.build_detector();
+        assert_eq!(legacy.process_line("%c:red(boom) ERROR now").unwrap(), expected);
+        assert_eq!(legacy.process_line("at main.rs:7").unwrap(), format!("at {}{}",
+            Html.paint(&Style::new().color(crate::extended_colors::indexed_color_name(6)), "main.rs"),
+            Html.paint(&Style::new().dim(), ":7")));
+    }
+    
+    #[test]
+    fn test_markup_escapes_once_and_passes_skip_tags() {
+        use crate::text_styles::Html;
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "m", version: "1.0.0", description: "markup" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+      words: { keywords: ["span", "style", "amp"], color: "amber" }
+"#).unwrap();
+        let word = |text| Html.paint(&Style::new().color("amber"), text);
+        let expected = format!("{} &amp;&amp; a &lt; b: {}x&lt;y</span> {} {}", word("span"), Html.color_open(&Style::new().color("red")),
+            Html.paint(&Style::new().color("crimson"), "ERROR"), word("style"));
+        
+        let compiled = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_renderer(Arc::new(Html));
+        let mut legacy = compiled.clone();
+        legacy.use_compiled = false;
+        legacy.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        for app in [&compiled, &legacy] {
+            let out = run_to_string(app, "span && a < b: %c:red(x<y) ERROR style\n");
+            assert_eq!(out, format!("{}\n", expected));
+            assert_eq!(out.matches("<span").count(), out.matches("</span>").count());
+        }
+        
+        // Options that edit SGR sequences are refused rather than left to corrupt the markup
+        let searching = compiled.clone().with_search(Some(Regex::new("a").unwrap())).with_coalesce(true);
+        let mut output = Vec::new();
+        let error = searching.run_io("a\n".as_bytes(), &mut output).unwrap_err();
+        assert!(error.to_string().contains("--search, --coalesce can't be used with markup output"), "{}", error);
+        assert!(output.is_empty());
+    }
+    
+    #[test]
+    fn test_ansi_settings_per_app() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "a", version: "1.0.0", description: "ansi" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "red", bold: true }
+"#).unwrap();
+        let app = |palette, bold_mode| JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_ansi(palette, bold_mode);
+        
+        // Two apps in one process keep their own palette and bold mode
+        let bright = app(Palette::Ansi256, BoldMode::Bright);
+        let weight = app(Palette::Ansi256, BoldMode::Weight);
+        assert_eq!(bright.process_line("ERROR").unwrap(), "\x1B[91mERROR\x1b[0m");
+        assert_eq!(weight.process_line("ERROR").unwrap(), "\x1B[38;5;9m\x1B[1mERROR\x1b[0m");
+    }
+    
+    #[test]
+    fn test_whole_line_groups() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "w", version: "1.0.0", description: "whole line" }
+filters:
+  logs:
+    styles:
+      failed: { keywords: ["FAILED"], color: "red", whole_line: true }
+      panic: { keywords: ["panic"], color: "crimson", whole_line: true, priority: high }
+      ok: { keywords: ["ok"], color: "green" }
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        let red = crate::extended_colors::get_extended_color_code("red");
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        
+        // The keyword's own reset re-applies the line style
+        assert_eq!(
+            app.process_line("test a FAILED").unwrap(),
+            format!("{red}test a {red}FAILED\x1b[0m{red}\x1b[0m"),
+        );
+        // Competing groups: higher priority wins regardless of position
+        let both = app.process_line("FAILED after panic").unwrap();
+        assert!(both.starts_with(crimson) && !both.starts_with(red));
+        assert_eq!(theme::Priority::Rank(10).rank(), 10);
+        assert!(theme::Priority::Named("critical".into()).rank() > theme::Priority::Named("low".into()).rank());
+        // Plain keyword groups still only color the token
+        assert!(app.process_line("ok").unwrap().starts_with(crate::extended_colors::get_extended_color_code("green")));
+    }
+    
+    #[test]
+    fn test_stderr_prefix() {
+        let app = JynxApp::with_theme(None, None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_stderr_prefix(Some("!! ".to_string()));
+        let style = crate::text_styles::Style::new().color("red").bold().to_ansi();
+        assert_eq!(app.process_line("!! failed to open").unwrap(), format!("{}failed to open\x1b[0m", style));
+        assert_eq!(app.process_line("compiled ok").unwrap(), "compiled ok");
+        // Only a leading marker counts
+        assert_eq!(app.process_line("a !! b").unwrap(), "a !! b");
+        
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "e", version: "1.0.0", description: "stderr", stderr_style: "warn" }
+palette: { warn: "amber" }
+filters: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_stderr_prefix(Some("E|".to_string()));
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        assert_eq!(app.process_line("E|oops").unwrap(), format!("{}oops\x1b[0m", amber));
+    }
+    
+    #[test]
+    fn test_replacements() {
+        let app = JynxApp::with_theme(None, None, Some(12), "left".to_string())
+            .with_auto_detection(false)
+            .with_replacements(vec![
+                Replacement::parse("/home/ci/build=>$$ROOT").unwrap(),
+                Replacement::parse(r"v(\d+)\.(\d+)=>$1.$2").unwrap(),
+            ]);
+        // Applied in order, and before width padding
+        assert_eq!(app.process_line("/home/ci/build/a v2.1").unwrap(), "$ROOT/a 2.1 ");
+        
+        assert!(Replacement::parse("no arrow").is_err());
+        assert!(Replacement::parse("(=>x").is_err());
+        assert_eq!(Replacement::parse("secret=>").unwrap().apply("a secret b"), "a  b");
+    }
+    
+    #[test]
+    fn test_search_reverse_video() {
+        let search = Regex::new(&regex::escape("38")).unwrap();
+        let app = JynxApp::with_theme(None, None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_search(Some(search.clone()));
+        assert_eq!(app.process_line("port 38 and 380").unwrap(), "port \x1b[7m38\x1b[27m and \x1b[7m38\x1b[27m0");
+        assert_eq!(app.process_line("none here").unwrap(), "none here");
+        
+        // Escape codes are never matched; a match spanning a reset keeps reverse on
+        let red = "\x1b[38;5;9m";
+        let colored = format!("{red}a3\x1b[0m8b");
+        assert_eq!(
+            JynxApp::reverse_matches(&colored, &search),
+            format!("{red}a\x1b[7m3\x1b[0m\x1b[7m8\x1b[27mb"),
+        );
+        
+        // Regex variant over a colored token: the color resumes after the span
+        let search = Regex::new(r"\d+").unwrap();
+        assert_eq!(
+            JynxApp::reverse_matches(&format!("{red}id 42\x1b[0m"), &search),
+            format!("{red}id \x1b[7m42\x1b[27m\x1b[0m"),
+        );
+    }
+    
+    #[test]
+    fn test_width_spec() {
+        assert_eq!(WidthSpec::parse("80"), Ok(WidthSpec::Columns(80)));
+        assert_eq!(WidthSpec::parse("75%"), Ok(WidthSpec::Percent(75)));
+        assert_eq!(WidthSpec::parse("AUTO"), Ok(WidthSpec::Auto));
+        assert!(WidthSpec::parse("0%").is_err() && WidthSpec::parse("150%").is_err());
+        assert!(WidthSpec::parse("wide").is_err());
+        
+        assert_eq!(WidthSpec::Columns(40).resolve(Some(200)), 40);
+        assert_eq!(WidthSpec::Percent(50).resolve(Some(120)), 60);
+        assert_eq!(WidthSpec::Auto.resolve(Some(132)), 132);
+        // Not a terminal: percentages of the 80-column default
+        assert_eq!(WidthSpec::Percent(50).resolve(None), 40);
+        assert_eq!(WidthSpec::Percent(1).resolve(Some(10)), 1);
+    }
+    
+    #[test]
+    fn test_truncate_sides() {
+        let (red, reset) = ("\x1B[31m", "\x1B[0m");
+        let line = format!("{red}src/very/long{reset}/path/{red}file.rs{reset}");
+        let app = JynxApp::with_theme(None, None, Some(12), "left".to_string()).with_auto_detection(false);
+        
+        let end = app.format_line_width(&line, 12);
+        assert_eq!(JynxApp::strip_ansi_codes(&end), "src/very/...");
+        
+        // Start: the cut part's codes are replayed so `file.rs` stays red
+        let start = app.clone().with_truncate(TruncateSide::Start).format_line_width(&line, 12);
+        assert_eq!(start, format!("...{red}{reset}h/{red}file.rs{reset}"));
+        
+        // Middle: the head is reset before the unstyled ellipsis
+        let middle = app.clone().with_truncate(TruncateSide::Middle).format_line_width(&line, 12);
+        assert_eq!(middle, format!("{red}src/v{reset}...{red}{reset}{red}e.rs{reset}"));
+        assert_eq!(JynxApp::get_visible_length(&middle), 12);
+        
+        // Narrower than the ellipsis: just the rightmost columns
+        assert_eq!(JynxApp::strip_ansi_codes(&app.clone().with_truncate(TruncateSide::Start).format_line_width(&line, 2)), "rs");
+        
+        // Hyperlinks take up no columns
+        let link = "\x1b]8;;https://example.com\x1b\\example.com\x1b]8;;\x1b\\ docs";
+        assert_eq!(JynxApp::get_visible_length(link), 16);
+        assert_eq!(JynxApp::strip_ansi_codes(&app.format_line_width(link, 12)), "example.c...");
+    }
+    
+    #[test]
+    fn test_columns() {
+        let specs = ColumnSpec::parse_list("8:left,6:right,0:left").unwrap();
+        let app = JynxApp::with_theme(None, None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_columns(Some(specs), "\t".to_string());
+        let out = app.render_line("alpha\tbeta\tgamma", true, None);
+        assert_eq!(out, "alpha      beta gamma");
+        
+        // Fields are highlighted on their own; padding counts visible columns only
+        let colored = app.render_line("%c:red(ok)\t42\tlast", true, None);
+        assert_eq!(JynxApp::strip_ansi_codes(&colored), "ok           42 last");
+        assert!(colored.starts_with("\x1B["));
+        
+        // Over-wide fields are truncated; fields past the specs keep their width
+        assert_eq!(app.render_line("abcdefghijk\tx\ty\tz", true, None), "abcde...      x y z");
+        
+        let centered = ColumnSpec::parse_list("4:center,2").unwrap();
+        assert!(matches!(centered[0].align, TextAlign::Center) && matches!(centered[1].align, TextAlign::Left));
+        assert!(ColumnSpec::parse_list("x:left").is_err());
+        assert!(ColumnSpec::parse_list("5:sideways").is_err());
+    }
+    
+    #[test]
+    fn test_coalesce_spans() {
+        let (red, blue, bold, reset) = ("\x1B[31m", "\x1B[34m", "\x1B[1m", "\x1B[0m");
+        
+        // Two adjacent red tokens become one span with a single reset
+        let line = format!("{red}foo{reset}{red}bar{reset} baz");
+        assert_eq!(JynxApp::coalesce_spans(&line), format!("{red}foobar{reset} baz"));
+        
+        // Compound styles must match exactly; different styles stay apart
+        let compound = format!("{bold}{red}a{reset}{bold}{red}b{reset}{red}c{reset}");
+        assert_eq!(JynxApp::coalesce_spans(&compound), format!("{bold}{red}ab{reset}{red}c{reset}"));
+        let different = format!("{red}a{reset}{blue}b{reset}");
+        assert_eq!(JynxApp::coalesce_spans(&different), different);
+        
+        // A trailing reset is kept even if it matches nothing after it
+        let app = JynxApp::with_theme(None, None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_coalesce(true);
+        let out = app.render_line("%c:red(one)%c:red(two)", true, None);
+        assert_eq!(out.matches(reset).count(), 1);
+        assert_eq!(JynxApp::strip_ansi_codes(&out), "onetwo");
+    }
+    
+    #[test]
+    fn test_rainbow_brackets() {
+        let styles: Vec<String> = ["<0>", "<1>"].iter().map(|s| s.to_string()).collect();
+        let r = theme::AnsiCodes::RESET;
+        
+        // Depth colors cycle; brackets inside an escape sequence are left alone
+        let balanced = JynxApp::color_brackets("f(a[1]{x})", &styles, "<!>");
+        assert_eq!(balanced, format!("f<0>({r}a<1>[{r}1<1>]{r}<1>{{{r}x<1>}}{r}<0>){r}"));
+        
+        // The surrounding color comes back after a bracket
+        let red = "\x1B[31m";
+        assert_eq!(JynxApp::color_brackets(&format!("{red}(x){r}"), &styles, "<!>"), format!("{red}<0>({r}{red}x<0>){r}{red}{r}"));
+        
+        // Unmatched closer, crossed pair and unclosed opener are all errors
+        assert_eq!(JynxApp::color_brackets("a) (]", &styles, "<!>"), format!("a<!>){r} <!>({r}<!>]{r}"));
+        assert_eq!(JynxApp::color_brackets("plain", &styles, "<!>"), "plain");
+        
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "b", version: "1.0.0", description: "brackets", bracket_colors: ["crimson"] }
+filters: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_rainbow_brackets(true);
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        assert_eq!(app.render_line("(())", true, None), format!("{crimson}({r}{crimson}({r}{crimson}){r}{crimson}){r}"));
+    }
+    
+    #[test]
+    fn test_json_pretty() {
+        let app = JynxApp::with_theme(None, None, None, "left".to_string()).with_json_pretty(true);
+        let mut output = Vec::new();
+        app.run_io("{\"a\":{\"b\":[1,null]}}\nnot json\n".as_bytes(), &mut output).unwrap();
+        let output = JynxApp::strip_ansi_codes(&String::from_utf8(output).unwrap());
+        assert_eq!(output, "{\n  \"a\": {\n    \"b\": [\n      1,\n      null\n    ]\n  }\n}\nnot json\n");
+        
+        let plain = JynxApp::with_theme_and_options(None, None, None, "left".to_string(), true).with_json_pretty(true);
+        assert_eq!(plain.render_line("[true]", true, None), "[\n  true\n]");
+    }
+    
+    #[test]
+    fn test_markdown() {
+        let app = JynxApp::with_theme(None, None, None, "left".to_string()).with_auto_detection(false).with_markdown(true);
+        let bold = theme::AnsiCodes::BOLD;
+        assert_eq!(app.process_line("a **b** c").unwrap(), format!("a {}b\x1B[0m c", bold));
+        assert_eq!(app.process_line("2 ** 3").unwrap(), "2 ** 3");
+        
+        let plain = JynxApp::with_theme_and_options(None, None, None, "left".to_string(), true).with_markdown(true);
+        assert_eq!(plain.process_line("**bold** _it_ `code`").unwrap(), "bold it code");
+    }
+    
+    #[test]
+    fn test_reset_lines() {
+        let reset = theme::AnsiCodes::RESET;
+        let app = JynxApp::with_theme(None, None, Some(8), "right".to_string())
+            .with_auto_detection(false)
+            .with_header(Some("H".to_string()), None)
+            .with_reset_lines(Some(ResetLines::Start));
+        let mut output = Vec::new();
+        app.run_io("one\n%c:red(two)\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.lines().all(|line| line.starts_with(reset)), "{:?}", output);
+        // The reset takes no columns: lines are still padded to the full width
+        assert_eq!(output.lines().nth(1).unwrap(), format!("{}     one", reset));
+        
+        let both = app.with_reset_lines(ResetLines::from_arg("both"));
+        assert_eq!(both.render_line("one", true, None), format!("{}     one{}", reset, reset));
+    }
+    
+    #[test]
+    fn test_profile_lists_stages() {
+        let app = JynxApp::with_theme(None, None, Some(20), "left".to_string());
+        assert!(app.profile_report().is_none());
+        
+        let app = app.with_profile(true);
+        let mut output = Vec::new();
+        app.run_io("%c:red(ERROR) at https://example.com\nplain\n".as_bytes(), &mut output).unwrap();
+        let report = app.profile_report().unwrap();
+        for stage in profile::Stage::ALL {
+            assert!(report.contains(&format!("  {:<12}", stage.name())), "{} missing from {}", stage.name(), report);
+        }
+        assert!(report.lines().last().unwrap().trim_start().starts_with("total"));
+    }
+    
+    #[test]
+    fn test_ellipsis_indicator() {
+        let line = "abcdefghijklmnop";
+        let app = JynxApp::with_theme(None, None, Some(10), "left".to_string())
+            .with_auto_detection(false)
+            .with_ellipsis(Some(" »»".to_string()));
+        
+        // The 3-column indicator is reserved out of the width, at each side
+        assert_eq!(app.format_line_width(line, 10), "abcdefg »»");
+        assert_eq!(app.clone().with_truncate(TruncateSide::Start).format_line_width(line, 10), " »»jklmnop");
+        let middle = app.clone().with_truncate(TruncateSide::Middle).format_line_width(line, 10);
+        assert_eq!(middle, "abcd »»nop");
+        assert_eq!(JynxApp::get_visible_length(&middle), 10);
+        let single = app.clone().with_ellipsis(Some("»".to_string())).format_line_width(line, 10);
+        assert_eq!(single, "abcdefghi»");
+        
+        // Exactly fitting lines are left alone, even with escape codes after the text
+        assert_eq!(app.format_line_width("abcdefghij\x1B[0m", 10), "abcdefghij\x1B[0m");
+        
+        // Styled from the theme, and still only 10 visible columns
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "e", version: "1.0.0", description: "ellipsis", ellipsis_style: "dim red" }
+filters: {}
+"#).unwrap();
+        let styled = JynxApp::with_theme(Some(theme), None, Some(10), "left".to_string())
+            .with_auto_detection(false)
+            .with_ellipsis(Some("…".to_string()))
+            .format_line_width(line, 10);
+        let dim_red = crate::text_styles::Style::new().dim().color("red").to_ansi();
+        assert_eq!(styled, format!("abcdefghi{}…\x1B[0m", dim_red));
+        assert_eq!(JynxApp::get_visible_length(&styled), 10);
+    }
+    
+    #[test]
+    fn test_line_numbers() {
+        let numbers = LineNumbers { width: 3, ..Default::default() };
+        let app = JynxApp::with_theme(None, None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_line_numbers(Some(numbers.clone()));
+        assert_eq!(run_to_string(&app, "one\ntwo\n"), "  1 one\n  2 two\n");
+        
+        // Outside the width column: content is padded to 6, the number comes on top
+        let app = JynxApp::with_theme(None, None, Some(6), "right".to_string())
+            .with_auto_detection(false)
+            .with_line_numbers(Some(numbers.clone()));
+        assert_eq!(run_to_string(&app, "ab\n"), "  1     ab\n");
+        
+        // Inside: the prefix eats into the 6 columns
+        let app = app.with_line_numbers(Some(LineNumbers { inside: true, ..numbers.clone() }));
+        assert_eq!(run_to_string(&app, "ab\n"), "  1 ab\n");
+        
+        // Colored from the theme, through its palette
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "n", version: "1.0.0", description: "numbers", line_number_color: "muted" }
+palette: { muted: "red" }
+filters: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), None, None, "left".to_string())
+            .with_auto_detection(false)
+            .with_line_numbers(Some(numbers));
+        let red = crate::extended_colors::get_extended_color_code("red");
+        assert_eq!(run_to_string(&app, "x\n"), format!("{}  1\x1b[0m x\n", red));
+    }
+    
+    #[test]
+    fn test_bell_on() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "b", version: "1.0.0", description: "bells", bell_groups: [fatal] }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+      fatal: { keywords: ["FATAL"], color: "crimson" }
+      warnings: { keywords: ["WARN"], color: "amber" }
+"#).unwrap();
+        let app = JynxApp::with_theme_and_options(Some(theme), Some("logs".to_string()), None, "left".to_string(), true)
+            .with_bell_on(vec!["errors".to_string()]);
+        let output = run_to_string(&app, "ERROR one\nWARN two\nFATAL three\n");
+        assert_eq!(output, "ERROR one\x07\nWARN two\nFATAL three\x07\n");
+        
+        // Off by default: no bell groups, no bell
+        let app = JynxApp::with_theme(None, None, None, "left".to_string()).with_auto_detection(false);
+        assert!(!run_to_string(&app, "ERROR one\n").contains('\x07'));
+    }
+    
+    #[cfg(feature = "compiled")]
+    #[test]
+    fn test_theme_auto_detection_without_filter() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "t", version: "1.0.0", description: "tickets" }
+auto_detection:
+  tickets: { pattern: "(JIRA-\\d+)", color: "violet" }
+filters: {}
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), None, None, "left".to_string());
+        let violet = crate::extended_colors::get_extended_color_code("violet");
+        assert_eq!(app.process_line("see JIRA-12").unwrap(), format!("see {}JIRA-12\x1b[0m", violet));
+    }
+    
+    #[cfg(feature = "compiled")]
+    #[test]
+    fn test_degraded_compiled_theme_keeps_keywords() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "d", version: "1.0.0", description: "broken pattern" }
+auto_detection:
+  broken: { pattern: "(JIRA-", color: "violet" }
+  hosts: { pattern: "host-\\d+", color: "amber" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let error = CompiledTheme::from_theme(&theme).unwrap().init_runtime().unwrap_err();
+        assert!(error.to_string().starts_with("auto_detection 'broken' (`(JIRA-`)"), "{}", error);
+        
+        // The legacy path still highlights keywords and the valid theme pattern
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string());
+        assert!(!app.use_compiled);
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        assert_eq!(
+            app.process_line("ERROR on host-3").unwrap(),
+            format!("{}ERROR\x1b[0m on {}host-3\x1b[0m", crimson, amber),
+        );
+    }
+    
+    #[cfg(feature = "compiled")]
+    #[test]
+    fn test_idempotent_rerun() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "i", version: "1.0.0", description: "idempotent" }
+auto_detection:
+  tickets: { pattern: "JIRA-\\d+", color: "violet" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+      codes: { keywords: ["38"], color: "amber" }
+"#).unwrap();
+        let compiled = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_idempotent(true);
+        let mut legacy = compiled.clone();
+        legacy.use_compiled = false;
+        legacy.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        
+        let input = "ERROR 38 in JIRA-7 at https://example.com/v1.2.3\n";
+        for app in [&compiled, &legacy] {
+            let once = run_to_string(app, input);
+            assert_ne!(once, input);
+            assert_eq!(run_to_string(app, &once), once);
+        }
+        
+
+        // Without the guard, keywords match inside the first run's escapes and spans
+        let once = run_to_string(&compiled, input);
+        assert_ne!(run_to_string(&compiled.with_idempotent(false), &once), once);
+    }
+    
+    #[test]
+    fn test_idempotent_attribute_resets() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "i", version: "1.0.0", description: "idempotent" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let compiled = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_idempotent(true);
+        let mut legacy = compiled.clone();
+        legacy.use_compiled = false;
+        legacy.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        
+        // Styling switched off attribute by attribute ends the skipped span like a reset
+        let error = theme.filters["logs"].styles["errors"].to_ansi();
+        for app in [&compiled, &legacy] {
+            let out = run_to_string(app, "\x1b[1;31mERROR\x1b[22;39m ERROR \x1b[4mERROR\x1b[m\n");
+            assert_eq!(out, format!("\x1b[1;31mERROR\x1b[22;39m {}ERROR\x1b[0m \x1b[4mERROR\x1b[m\n", error));
+        }
+    }
+    
+    #[cfg(feature = "compiled")]
+    #[test]
+    fn test_auto_detection_enabled_in() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "e", version: "1.0.0", description: "paths" }
+auto_detection:
+  tickets: { pattern: "JIRA-\\d+", color: "violet", enabled_in: [compiled] }
+  hosts: { pattern: "host-\\d+", color: "amber", enabled_in: [legacy] }
+filters: {}
+"#).unwrap();
+        let compiled = CompiledTheme::from_theme(&theme).unwrap();
+        let names: Vec<&str> = compiled.auto_detection.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["tickets"]);
+        
+        // Legacy path: theme patterns enabled for it replace the built-ins
+        let mut app = JynxApp::with_theme(Some(theme), None, None, "left".to_string());
+        app.use_compiled = false;
+        app.detector = app.build_detector();
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        assert_eq!(app.highlight("JIRA-12 on host-3"), format!("JIRA-12 on {}host-3\x1b[0m", amber));
+    }
+    
+    #[test]
+    fn test_theme_pattern_without_capture_group() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "g", version: "1.0.0", description: "non-capturing parens" }
+auto_detection:
+  codes: { pattern: '\(\d+\)', color: "amber" }
+  errs: { pattern: '(?:ERR)\d+', color: "violet" }
+filters: {}
+"#).unwrap();
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        let violet = crate::extended_colors::get_extended_color_code("violet");
+        
+        let mut app = JynxApp::with_theme(Some(theme), None, None, "left".to_string());
+        app.use_compiled = false;
+        app.detector = app.build_detector();
+        assert_eq!(app.highlight("exit (42) on ERR7"), format!("exit {}(42)\x1b[0m on {}ERR7\x1b[0m", amber, violet));
+    }
+    
+    #[cfg(feature = "compiled")]
+    #[test]
+    fn test_auto_detection_anchor() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "a", version: "1.0.0", description: "anchors" }
+auto_detection:
+  prompt: { pattern: "\\$ ", color: "violet", anchor: start }
+  ticket: { pattern: "T-\\d+", color: "amber", anchor: end }
+filters: {}
+"#).unwrap();
+        let violet = crate::extended_colors::get_extended_color_code("violet");
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        let line = "$ echo $ T-1 and T-2";
+        let expected = format!("{}$ \x1b[0mecho $ T-1 and {}T-2\x1b[0m", violet, amber);
+        
+        let compiled = JynxApp::with_theme(Some(theme.clone()), None, None, "left".to_string());
+        assert_eq!(compiled.highlight(line), expected);
+        
+        let mut legacy = JynxApp::with_theme(Some(theme), None, None, "left".to_string());
+        legacy.use_compiled = false;
+        legacy.detector = legacy.build_detector();
+        assert_eq!(legacy.highlight(line), expected);
+    }
+    
+    #[cfg(feature = "compiled")]
+    #[test]
+    fn test_osc8_link_text_highlighted_url_untouched() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "o", version: "1.0.0", description: "osc 8" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        let line = "see \x1b]8;;https://ci.example/ERROR\x1b\\ERROR log\x1b]8;;\x1b\\ now";
+        let expected = format!("see \x1b]8;;https://ci.example/ERROR\x1b\\{}ERROR\x1b[0m log\x1b]8;;\x1b\\ now", crimson);
+        
+        let compiled = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        assert_eq!(compiled.highlight(line), expected);
+        
+        let mut legacy = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        legacy.use_compiled = false;
+        legacy.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        assert_eq!(legacy.highlight(line), expected);
+    }
+    
+    #[test]
+    fn test_skip_regions() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "s", version: "1.0.0", description: "skip regions" }
+filters:
+  code:
+    skip_regions: ['"[^"]*"', '//.*']
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        let line = r#"log(ERROR, "ERROR here") // ERROR too"#;
+        let expected = format!("log({}ERROR\x1b[0m, \"ERROR here\") // ERROR too", crimson);
+        
+        let mut app = JynxApp::with_theme(Some(theme.clone()), Some("code".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        #[cfg(feature = "compiled")]
+        assert_eq!(app.highlight(line), expected);
+        
+        // Legacy path
+        app.use_compiled = false;
+        app.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "code");
+        app.skip_regions = theme.filters["code"].skip_regex(&RegexLimits::default()).unwrap();
+        assert_eq!(app.highlight(line), expected);
+    }
+    
+    #[test]
+    fn test_keyword_replace_with() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "r", version: "1.0.0", description: "replacements" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson", replace_with: "✗ {}" }
+      skipped: { keywords: ["SKIP"], color: "amber", replace_with: "--" }
+"#).unwrap();
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        let amber = crate::extended_colors::get_extended_color_code("amber");
+        let expected = format!("{}✗ ERROR\x1b[0m then {}--\x1b[0m", crimson, amber);
+        
+        let mut app = JynxApp::with_theme(Some(theme.clone()), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        #[cfg(feature = "compiled")]
+        {
+            assert!(app.use_compiled);
+            assert_eq!(app.highlight("ERROR then SKIP"), expected);
+        }
+        
+        // Legacy path
+        app.use_compiled = false;
+        app.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "logs");
+        assert_eq!(app.highlight("ERROR then SKIP"), expected);
+    }
+    
+    #[test]
+    fn test_style_group_icon() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "i", version: "1.0.0", description: "keyword icons" }
+filters:
+  tests:
+    styles:
+      failed: { keywords: ["FAILED"], color: "crimson", icon: "✗", icon_fallback: "x" }
+      passed: { keywords: ["ok"], color: "emerald" }
+"#).unwrap();
+        let crimson = crate::extended_colors::get_extended_color_code("crimson");
+        let emerald = crate::extended_colors::get_extended_color_code("emerald");
+        let line = "test a ... FAILED, test b ... ok";
+        let expected = format!("test a ... ✗ {}FAILED\x1b[0m, test b ... {}ok\x1b[0m", crimson, emerald);
+        let plain = format!("test a ... {}FAILED\x1b[0m, test b ... {}ok\x1b[0m", crimson, emerald);
+        let ascii = format!("test a ... x {}FAILED\x1b[0m, test b ... {}ok\x1b[0m", crimson, emerald);
+        
+        let app = JynxApp::with_theme(Some(theme.clone()), Some("tests".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        #[cfg(feature = "compiled")]
+        {
+            assert!(app.use_compiled);
+            let app = app.clone().with_icon_mode(IconMode::Unicode);
+            assert_eq!(app.highlight(line), expected);
+            assert_eq!(app.clone().with_icon_mode(IconMode::Ascii).highlight(line), ascii);
+            assert_eq!(app.with_icon_mode(IconMode::None).highlight(line), plain);
+        }
+        
+        // Legacy path
+        let mut app = app.with_icon_mode(IconMode::Unicode);
+        app.use_compiled = false;
+        app.keyword_patterns = JynxApp::compile_keyword_patterns(&theme, "tests");
+        assert_eq!(app.highlight(line), expected);
+        app.detector_config.icon_mode = IconMode::Ascii;
+        assert_eq!(app.highlight(line), ascii);
+        app.detector_config.icon_mode = IconMode::None;
+        assert_eq!(app.highlight(line), plain);
+        
+        // No fallback: a Unicode icon is dropped in ASCII mode, an ASCII one kept
+        let style = Style::new().color("crimson");
+        assert_eq!(theme::StyleGroup::styled_match(&style, Some("✗"), None, None, "FAILED", IconMode::Ascii, &Ansi::default()), format!("{}FAILED\x1b[0m", crimson));
+        assert_eq!(theme::StyleGroup::styled_match(&style, Some("!"), None, None, "FAILED", IconMode::Ascii, &Ansi::default()), format!("! {}FAILED\x1b[0m", crimson));
+    }
+    
+    #[test]
+    fn test_max_expand() {
+        // `*` globs to `\b\w*\b`, which styles every single word
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "m", version: "1.0.0", description: "runaway keyword" }
+filters:
+  logs:
+    styles:
+      everything: { keywords: ["*"], color: "crimson", bold: true, underline: true }
+"#).unwrap();
+        let app = JynxApp::with_theme(Some(theme), Some("logs".to_string()), None, "left".to_string())
+            .with_auto_detection(false);
+        
+        let line = "x ".repeat(100);
+        assert_eq!(app.clone().with_max_expand(3).process_line(&line).unwrap(), line);
+        assert_ne!(app.clone().with_max_expand(0).process_line(&line).unwrap(), line);
+        // Short lines get a minimum budget and are still styled
+        assert_ne!(app.with_max_expand(3).process_line("x").unwrap(), "x");
+    }
+}
@@ -0,0 +1,92 @@
+//! Per-stage wall-clock timing (`--profile`)
+//!
+//! `Profile` accumulates the time spent in each pipeline stage across every
+//! line (and every input file) of a run, then renders a breakdown such as
+//! `keyword  41.2ms  80.1%`. Timing only happens when a profile is attached,
+//! so a normal run pays nothing but an `Option` check per stage.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A timed pipeline stage, in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Template,
+    AutoDetect,
+    Icon,
+    Keyword,
+    /// The compiled theme's single pass (auto-detect, icons and keywords at once)
+    Compiled,
+    Width,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [Stage::Template, Stage::AutoDetect, Stage::Icon, Stage::Keyword, Stage::Compiled, Stage::Width];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Template => "template",
+            Stage::AutoDetect => "auto-detect",
+            Stage::Icon => "icon",
+            Stage::Keyword => "keyword",
+            Stage::Compiled => "compiled",
+            Stage::Width => "width",
+        }
+    }
+}
+
+/// Accumulated nanoseconds per stage; shared by an app and its `#jynx:`/theme-map clones
+#[derive(Debug, Default)]
+pub struct Profile {
+    nanos: [AtomicU64; Stage::ALL.len()],
+}
+
+impl Profile {
+    pub fn add(&self, stage: Stage, elapsed: Duration) {
+        self.nanos[stage as usize].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Run `f`, adding its wall-clock time to `stage`
+    pub fn time<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.add(stage, start.elapsed());
+        result
+    }
+
+    pub fn elapsed(&self, stage: Stage) -> Duration {
+        Duration::from_nanos(self.nanos[stage as usize].load(Ordering::Relaxed))
+    }
+
+    /// One line per stage with its time and share of the total, then the total
+    pub fn render(&self) -> String {
+        let total: Duration = Stage::ALL.iter().map(|&stage| self.elapsed(stage)).sum();
+        let mut report = String::from("jynx profile:\n");
+        for stage in Stage::ALL {
+            let elapsed = self.elapsed(stage);
+            let share = match total.is_zero() {
+                true => 0.0,
+                false => 100.0 * elapsed.as_secs_f64() / total.as_secs_f64(),
+            };
+            report.push_str(&format!("  {:<12}{:>10.3}ms {:>6.1}%\n", stage.name(), elapsed.as_secs_f64() * 1000.0, share));
+        }
+        report.push_str(&format!("  {:<12}{:>10.3}ms", "total", total.as_secs_f64() * 1000.0));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_accumulates() {
+        let profile = Profile::default();
+        profile.add(Stage::Keyword, Duration::from_millis(3));
+        profile.add(Stage::Keyword, Duration::from_millis(1));
+        profile.add(Stage::Width, Duration::from_millis(4));
+        assert_eq!(profile.elapsed(Stage::Keyword), Duration::from_millis(4));
+        assert!(profile.render().contains("keyword          4.000ms   50.0%"));
+        assert!(profile.render().ends_with("total            8.000ms"));
+    }
+}
@@ -0,0 +1,338 @@
+//! Streaming iterator over highlighted lines
+//!
+//! `HighlightStream` is the line loop behind `JynxApp::run_io`, exposed as an
+//! `Iterator` so embedders can pull highlighted lines from any `BufRead`.
+//! Each item ends with its input line's terminator (`\n`, `\r\n`, or nothing
+//! for a last line without one), so the items written back to back keep the
+//! input's line endings; a header ends like the line it precedes.
+//!
+//! Per-run state such as a `#jynx:` directive, `--lines`, repeated headers,
+//! `--exec-filter`, bells, `--squeeze` runs and summary counts is handled here.
+
+use std::io::{self, BufRead};
+use std::mem;
+use std::vec;
+
+use super::profile::Stage;
+use super::summary::Summary;
+use crate::text_styles::Style;
+use super::{Directive, JynxApp, JynxError};
+
+/// A `--squeeze` run: its first line, held back until a different line (or EOF) ends it
+struct Run {
+    input: String,
+    // The line as the summary counted it (after `--exec-filter`)
+    counted: String,
+    header: Option<String>,
+    output: String,
+    // The latest repeat's, so a run ending the input ends like it
+    terminator: &'static str,
+    count: usize,
+}
+
+/// Where input lines come from: straight off the reader, or buffered whole
+/// first so `%c:` templates can span lines
+enum LineSource<R> {
+    Pending(R),
+    Buffered(vec::IntoIter<String>),
+    Streamed(R),
+}
+
+/// `line` without its `\n` or `\r\n`, and that terminator (empty for a last line without one)
+fn split_terminator(mut line: String) -> (String, &'static str) {
+    let terminator = match () {
+        _ if line.ends_with("\r\n") => "\r\n",
+        _ if line.ends_with('\n') => "\n",
+        _ => "",
+    };
+    line.truncate(line.len() - terminator.len());
+    (line, terminator)
+}
+
+pub struct HighlightStream<'a, R: BufRead> {
+    app: &'a JynxApp,
+    source: LineSource<R>,
+    // App rebuilt from a `#jynx:` directive on line 1
+    reconfigured: Option<JynxApp>,
+    line_number: usize,
+    emitted: usize,
+    // Processed line held back while its header is yielded first
+    pending: Option<String>,
+    run: Option<Run>,
+    done: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    exec_filter: Option<super::exec_filter::ExecFilter>,
+    summary: Option<Summary>,
+}
+
+impl<'a, R: BufRead> HighlightStream<'a, R> {
+    pub fn new(app: &'a JynxApp, reader: R) -> Self {
+        let source = if app.multiline_templates {
+            LineSource::Pending(reader)
+        } else {
+            LineSource::Streamed(reader)
+        };
+
+        // Spawned once per stream; a command that can't start degrades to passthrough
+        #[cfg(not(target_arch = "wasm32"))]
+        let exec_filter = app.exec_filter.as_deref().and_then(|command| {
+            super::exec_filter::ExecFilter::spawn(command)
+                .map_err(|e| eprintln!("Warning: Failed to start --exec-filter '{}': {}", command, e))
+                .ok()
+        });
+
+        Self {
+            app,
+            source,
+            reconfigured: None,
+            line_number: 0,
+            emitted: 0,
+            pending: None,
+            run: None,
+            done: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            exec_filter,
+            summary: None,
+        }
+    }
+
+    /// The pipeline in effect: the directive's, if line 1 had one
+    fn active(&self) -> &JynxApp {
+        self.reconfigured.as_ref().unwrap_or(self.app)
+    }
+
+    /// `--summary` footer for the lines yielded so far
+    pub fn summary(&self) -> String {
+        let app = self.active();
+        match &self.summary {
+            Some(summary) => summary.render(app.no_color, app.renderer.as_ref()),
+            None => Summary::for_theme(app.theme.as_ref(), app.filter.as_deref()).render(app.no_color, app.renderer.as_ref()),
+        }
+    }
+
+    fn next_input(&mut self) -> Option<io::Result<String>> {
+        // Multi-line templates need the whole input; templates are expanded here, not per line
+        if let LineSource::Pending(_) = self.source {
+            let LineSource::Pending(mut reader) = mem::replace(&mut self.source, LineSource::Buffered(Vec::new().into_iter())) else {
+                unreachable!();
+            };
+            let mut text = String::new();
+            if let Err(e) = reader.read_to_string(&mut text) {
+                return Some(Err(e));
+            }
+            // Literal text is escaped along with the expansion, so lines aren't escaped again
+            let escaped = self.app.renderer.escape(&text);
+            let expanded = self.app.timed(Stage::Template, || self.app.template_parser.process(&escaped));
+            let lines: Vec<String> = expanded.split_inclusive('\n').map(str::to_string).collect();
+            self.source = LineSource::Buffered(lines.into_iter());
+        }
+
+        match &mut self.source {
+            LineSource::Pending(_) => None,
+            LineSource::Buffered(lines) => lines.next().map(Ok),
+            LineSource::Streamed(reader) => {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) => None,
+                    Ok(_) => Some(Ok(line)),
+                    Err(e) => Some(Err(e)),
+                }
+            },
+        }
+    }
+
+    /// Yield a processed line ended by `terminator`, with its header (if any) first
+    fn emit(&mut self, header: Option<String>, mut processed: String, terminator: &str) -> Option<Result<String, JynxError>> {
+        processed.push_str(terminator);
+        match header {
+            Some(mut header) => {
+                header.push_str(if terminator.is_empty() { "\n" } else { terminator });
+                self.pending = Some(processed);
+                Some(Ok(header))
+            },
+            None => Some(Ok(processed)),
+        }
+    }
+
+    /// Yield a finished `--squeeze` run, with its repeat count if it has one
+    fn emit_run(&mut self, run: Run) -> Option<Result<String, JynxError>> {
+        let mut output = run.output;
+        if run.count > 1 {
+            let suffix = format!(" (x{})", run.count);
+            match self.active().no_color {
+                true => output.push_str(&suffix),
+                false => output.push_str(&self.active().renderer.paint(&Style::new().dim(), &suffix)),
+            }
+        }
+        self.emit(run.header, output, run.terminator)
+    }
+}
+
+impl<R: BufRead> Iterator for HighlightStream<'_, R> {
+    type Item = Result<String, JynxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(line) = self.pending.take() {
+            return Some(Ok(line));
+        }
+        // Options the renderer can't carry out fail the stream before any line is read
+        if self.line_number == 0 && !self.done {
+            if let Err(e) = self.app.check_renderer() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+
+        loop {
+            if self.done {
+                return self.run.take().and_then(|run| self.emit_run(run));
+            }
+            let (line, terminator) = match self.next_input() {
+                Some(Ok(line)) => split_terminator(line),
+                // The line that couldn't be read is the next one
+                Some(Err(e)) => return Some(Err(JynxError::from(e).line_context(self.line_number + 1))),
+                None => {
+                    self.done = true;
+                    continue;
+                },
+            };
+            self.line_number += 1;
+
+            // A valid `#jynx:` first line swaps in its theme/filter and is not emitted
+            if self.line_number == 1 && self.app.auto_filter {
+                if let Some(directive) = Directive::parse(&line) {
+                    match self.app.with_directive(&directive) {
+                        Ok(app) => {
+                            self.reconfigured = Some(app);
+                            continue;
+                        },
+                        Err(e) => eprintln!("Warning: Ignoring #jynx: directive: {}", e.line_context(1)),
+                    }
+                }
+            }
+
+            // Lines outside --lines are suppressed entirely (not passed through)
+            if let Some(range) = &self.app.line_range {
+                if range.is_past_end(self.line_number) {
+                    self.done = true;
+                    continue;
+                }
+                if self.line_number < range.start {
+                    continue;
+                }
+            }
+
+            // A repeat of the held run's line only bumps its count (the summary still sees it)
+            if let Some(run) = self.run.as_mut().filter(|run| run.input == line) {
+                run.count += 1;
+                run.terminator = terminator;
+                if let Some(summary) = self.summary.as_mut() {
+                    let app = self.reconfigured.as_ref().unwrap_or(self.app);
+                    summary.count(&run.counted, app.detector.as_ref());
+                }
+                continue;
+            }
+            let input = self.app.squeeze.then(|| line.clone());
+
+            // Header goes out before the first line and every `repeat_every` lines after
+            let header = self.app.header.as_ref().filter(|_| match self.app.repeat_every {
+                Some(every) => self.emitted.is_multiple_of(every),
+                None => self.emitted == 0,
+            });
+            let header = header.map(|header| self.active().render_line(header, true, None).into_owned());
+            self.emitted += 1;
+
+            #[cfg(not(target_arch = "wasm32"))]
+            let line = match self.exec_filter.as_mut() {
+                Some(filter) => filter.filter(&line),
+                None => line,
+            };
+
+            // Counting starts once a `#jynx:` directive has had its chance to pick theme/filter
+            if self.app.summary.is_some() {
+                let app = self.reconfigured.as_ref().unwrap_or(self.app);
+                self.summary
+                    .get_or_insert_with(|| Summary::for_theme(app.theme.as_ref(), app.filter.as_deref()))
+                    .count(&line, app.detector.as_ref());
+            }
+
+            // Process the line - this is where the magic happens
+            let mut processed = self.active().render_line(&line, !self.app.multiline_templates, Some(self.line_number)).into_owned();
+            if self.active().rings_bell(&line) {
+                processed.push('\x07');
+            }
+            // Squeezing: this line starts a new run and the previous one, if any, is done
+            if let Some(input) = input {
+                let run = Run { input, counted: line, header, output: processed, terminator, count: 1 };
+                match self.run.replace(run) {
+                    Some(finished) => return self.emit_run(finished),
+                    None => continue,
+                }
+            }
+            return self.emit(header, processed, terminator);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::LineRange;
+    use std::io::Cursor;
+    use crate::text_styles::{DIM, RESET};
+
+    #[test]
+    fn test_stream_over_cursor() {
+        let app = JynxApp::new().with_auto_detection(false);
+        let lines: Vec<String> = HighlightStream::new(&app, Cursor::new("one\r\ntwo\nthree"))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["one\r\n", "two\n", "three"]);
+        
+        // run_io writes them back unchanged: no `\r` dropped, no final newline added
+        let mut output = Vec::new();
+        app.run_io(Cursor::new("one\r\ntwo\nthree"), &mut output).unwrap();
+        assert_eq!(output, b"one\r\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_stream_header_and_range() {
+        let app = JynxApp::new()
+            .with_auto_detection(false)
+            .with_header(Some("H".to_string()), Some(2))
+            .with_line_range(LineRange::parse("2:4").ok());
+        let lines: Vec<String> = app.highlight_stream(Cursor::new("1\n2\n3\n4\n5\n"))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["H\n", "2\n", "3\n", "H\n", "4\n"]);
+    }
+
+    #[test]
+    fn test_stream_propagates_read_errors() {
+        let app = JynxApp::new();
+        let mut stream = app.highlight_stream(Cursor::new(vec![b'o', b'k', b'\n', 0xff, b'\n']));
+        assert_eq!(stream.next().unwrap().unwrap(), "ok\n");
+        let error = stream.next().unwrap().unwrap_err();
+        assert!(matches!(error, JynxError::IoError(_)));
+        assert_eq!(error.to_string(), "IO error: line 2: stream did not contain valid UTF-8");
+    }
+
+    #[test]
+    fn test_squeeze_run_of_three() {
+        let app = JynxApp::new().with_auto_detection(false).with_squeeze(true);
+        let lines: Vec<String> = app.highlight_stream(Cursor::new("start\nretry\nretry\nretry\n"))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["start\n".to_string(), format!("retry{} (x3){}\n", DIM, RESET)]);
+    }
+
+    #[test]
+    fn test_squeeze_interleaved_lines() {
+        let app = JynxApp::with_theme_and_options(None, None, None, "left".to_string(), true)
+            .with_squeeze(true);
+        let lines: Vec<String> = app.highlight_stream(Cursor::new("a\nb\na\nb\nb\na"))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["a\n", "b\n", "a\n", "b (x2)\n", "a"]);
+    }
+}
@@ -0,0 +1,204 @@
+//! End-of-stream match counts (`--summary`)
+//!
+//! Counts the theme's `metadata.summary_groups` keyword matches and the
+//! built-in auto-detections while lines stream past, then renders a compact
+//! footer such as `3 errors, 12 warnings, 5 URLs.`
+
+use regex::{Regex, RegexSet};
+use super::auto_detect::AutoDetector;
+use super::theme::{StyleGroup, Theme};
+use crate::text_styles::{Renderer, Style};
+
+/// Where the summary footer is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryOutput {
+    Stdout,
+    Stderr,
+}
+
+/// A `summary_groups` label and the keywords of the style groups it counts
+struct CountedGroup {
+    label: String,
+    regex: Option<Regex>,
+    style: Style,
+    count: usize,
+}
+
+pub struct Summary {
+    groups: Vec<CountedGroup>,
+    // Built-in detector name -> (count, style), in detection order
+    detected: Vec<(String, usize, Style)>,
+}
+
+impl Summary {
+    /// Counters for the theme's summary groups in `filter` (none without a theme or filter)
+    pub fn for_theme(theme: Option<&Theme>, filter: Option<&str>) -> Self {
+        let mut groups = Vec::new();
+        let selected = theme.zip(filter).and_then(|(theme, name)| Some((theme, theme.filters.get(name)?)));
+        if let Some((theme, filter)) = selected {
+            for (label, group_names) in &theme.metadata.summary_groups {
+                let styles: Vec<&StyleGroup> = group_names.iter().filter_map(|name| filter.styles.get(name)).collect();
+                let keywords: Vec<String> = styles.iter()
+                    .flat_map(|style| style.keywords.iter().map(|k| StyleGroup::keyword_regex(k)))
+                    .collect();
+                groups.push(CountedGroup {
+                    label: label.clone(),
+                    regex: theme.regex_limits().compile(&keywords.join("|")).ok().filter(|_| !keywords.is_empty()),
+                    style: styles.first().map(|style| style.style()).unwrap_or_default(),
+                    count: 0,
+                });
+            }
+        }
+        Self { groups, detected: Vec::new() }
+    }
+    
+    /// Add one input line's matches to the totals
+    pub fn count(&mut self, line: &str, detector: Option<&AutoDetector>) {
+        for group in &mut self.groups {
+            if let Some(regex) = &group.regex {
+                group.count += regex.find_iter(line).count();
+            }
+        }
+        
+        let Some(detector) = detector else {
+            return;
+        };
+        for (name, count, style) in detector.count_matches(line) {
+            match self.detected.iter_mut().find(|(existing, _, _)| existing == name) {
+                Some(entry) => entry.1 += count,
+                None => self.detected.push((name.to_string(), count, style.clone())),
+            }
+        }
+    }
+    
+    /// Total for a summary group label or built-in detector name
+    pub fn total(&self, name: &str) -> usize {
+        self.groups.iter().find(|group| group.label == name).map(|group| group.count)
+            .or_else(|| self.detected.iter().find(|(detected, _, _)| detected == name).map(|entry| entry.1))
+            .unwrap_or(0)
+    }
+    
+    /// One-line footer: every summary group, then any non-zero detections
+    pub fn render(&self, no_color: bool, renderer: &dyn Renderer) -> String {
+        let paint = |count: usize, label: &str, style: &Style| {
+            let text = format!("{} {}", count, label);
+            if no_color { text } else { renderer.paint(style, &text) }
+        };
+        
+        let mut parts: Vec<String> = self.groups.iter()
+            .map(|group| paint(group.count, &group.label, &group.style))
+            .collect();
+        parts.extend(self.detected.iter()
+            .filter(|(_, count, _)| *count > 0)
+            .map(|(name, count, style)| paint(*count, detection_label(name), style)));
+        
+        if parts.is_empty() {
+            "No matches.".to_string()
+        } else {
+            format!("{}.", parts.join(", "))
+        }
+    }
+}
+
+/// `--gutter`: a one-character severity column from the same `summary_groups`
+///
+/// Each group is marked by the upper-cased first letter of its label (`errors`
+/// -> `E`). A line gets the mark of the group matching earliest in it, which is
+/// where the level of a typical log line sits.
+#[derive(Debug, Clone)]
+pub struct Gutter {
+    marks: Vec<(char, Regex, Style)>,
+}
+
+impl Gutter {
+    pub fn for_theme(theme: Option<&Theme>, filter: Option<&str>, no_color: bool) -> Self {
+        let marks = Summary::for_theme(theme, filter).groups.into_iter()
+            .filter_map(|group| {
+                let mark = group.label.chars().next()?.to_ascii_uppercase();
+                let style = if no_color { Style::new() } else { group.style };
+                Some((mark, group.regex?, style))
+            })
+            .collect();
+        Self { marks }
+    }
+    
+    /// `E ` styled like its group, or two blanks when no group matches `line`
+    pub fn mark(&self, line: &str, renderer: &dyn Renderer) -> String {
+        let earliest = self.marks.iter()
+            .filter_map(|(mark, regex, style)| regex.find(line).map(|found| (found.start(), *mark, style)))
+            .min_by_key(|(start, _, _)| *start);
+        match earliest {
+            None => "  ".to_string(),
+            Some((_, mark, style)) => format!("{} ", renderer.paint(style, &mark.to_string())),
+        }
+    }
+}
+
+/// Per style group line and match counts for `--count-only`: detection only,
+/// no string building (a `RegexSet` pass per line, `find_iter` for groups that hit)
+pub struct KeywordCounts {
+    names: Vec<String>,
+    set: Option<RegexSet>,
+    regexes: Vec<Regex>,
+    // (lines matched, total matches) per group
+    counts: Vec<(usize, usize)>,
+}
+
+impl KeywordCounts {
+    /// Counters for every style group in `filter` (none without a theme or filter)
+    pub fn for_filter(theme: Option<&Theme>, filter: Option<&str>) -> Self {
+        let mut names = Vec::new();
+        let mut patterns = Vec::new();
+        let limits = theme.map(|theme| theme.regex_limits()).unwrap_or_default();
+        if let Some(filter) = theme.zip(filter).and_then(|(theme, name)| theme.filters.get(name)) {
+            for (name, style) in &filter.styles {
+                if style.keywords.is_empty() {
+                    continue;
+                }
+                let keywords: Vec<String> = style.keywords.iter().map(|k| StyleGroup::keyword_regex(k)).collect();
+                names.push(name.clone());
+                patterns.push(keywords.join("|"));
+            }
+        }
+        
+        let regexes: Vec<Regex> = patterns.iter().filter_map(|pattern| limits.compile(pattern).ok()).collect();
+        let set = limits.compile_set(&patterns).ok().filter(|_| regexes.len() == patterns.len());
+        let counts = vec![(0, 0); names.len()];
+        Self { names, set, regexes, counts }
+    }
+    
+    /// Add one line's matches
+    pub fn count(&mut self, line: &str) {
+        let Some(set) = &self.set else {
+            return;
+        };
+        for index in set.matches(line).iter() {
+            self.counts[index].0 += 1;
+            self.counts[index].1 += self.regexes[index].find_iter(line).count();
+        }
+    }
+    
+    /// `(lines matched, total matches)` for a style group
+    pub fn get(&self, group: &str) -> Option<(usize, usize)> {
+        self.names.iter().position(|name| name == group).map(|index| self.counts[index])
+    }
+    
+    /// Aligned `group  lines  matches` table
+    pub fn render(&self) -> String {
+        let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max("group".len());
+        let mut out = format!("{:<width$}  {:>7}  {:>7}\n", "group", "lines", "matches", width = width);
+        for (name, (lines, matches)) in self.names.iter().zip(&self.counts) {
+            out.push_str(&format!("{:<width$}  {:>7}  {:>7}\n", name, lines, matches, width = width));
+        }
+        out
+    }
+}
+
+/// Human label for a built-in detector
+fn detection_label(name: &str) -> &str {
+    match name {
+        "urls" => "URLs",
+        "filelines" => "file references",
+        other => other,
+    }
+}
@@ -2,90 +2,465 @@
 //! 
 //! Handles loading and parsing YAML theme files with icon mappings and inheritance
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+#[cfg(not(target_arch = "wasm32"))]
 use std::fs;
-use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::path::PathBuf;
 use std::env;
-use crate::extended_colors::get_extended_color_code;
+use crate::extended_colors::{contrast_ratio, get_color_rgb};
+use crate::std::auto_detect::IconMode;
+use crate::std::RegexLimits;
+use crate::text_styles::{self, Renderer, Style, UnderlineStyle};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Parse `major.minor.patch` (optional leading `v`, missing parts are 0,
+/// pre-release/build suffixes ignored) for version comparisons
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// ` - description` for listings, or nothing when undocumented
+pub(crate) fn describe_suffix(description: &Option<String>) -> String {
+    description.as_ref().map(|d| format!(" - {}", d)).unwrap_or_default()
+}
+
+/// Serde helper: omit `false` style flags from serialized themes
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ThemeMetadata {
     pub name: String,
     pub version: String,
     pub description: String,
+    /// Terminal background this theme is designed for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appearance: Option<Appearance>,
+    /// Oldest jynx release that understands this theme (`major.minor.patch`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_jynx_version: Option<String>,
+    /// `--summary` labels and the style groups whose matches they count,
+    /// e.g. `errors: [errors, fatal]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub summary_groups: BTreeMap<String, Vec<String>>,
+    /// Style groups that ring the terminal bell when a line matches (`--bell-on`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bell_groups: Vec<String>,
+    /// Color for `--number` line numbers (palette or built-in name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_number_color: Option<String>,
+    /// Color for `--stderr-prefix` lines (palette or built-in name; default bold red)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr_style: Option<String>,
+    /// Style of the `--width` truncation indicator: a color, optionally after
+    /// attribute words (`bold`, `dim`, `italic`, `underline`, `reverse`), e.g. `dim red`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ellipsis_style: Option<String>,
+    /// `--rainbow-brackets` colors by nesting depth, cycled (palette or built-in names)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bracket_colors: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Terminal background appearance a theme targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    /// Parse `--appearance` value: `light`, `dark`, or `auto` (detect from terminal)
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            "auto" => Self::detect(),
+            _ => None,
+        }
+    }
+    
+    /// Guess terminal background from `$COLORFGBG`
+    pub fn detect() -> Option<Self> {
+        env::var("COLORFGBG").ok().and_then(|value| Self::from_colorfgbg(&value))
+    }
+    
+    /// Parse a `COLORFGBG` value ("fg;bg" or "fg;default;bg")
+    /// Background 7 (white) or 9-15 (bright) is light, 0-6 and 8 are dark
+    pub fn from_colorfgbg(value: &str) -> Option<Self> {
+        let background: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+        match background {
+            0..=6 | 8 => Some(Self::Dark),
+            7 | 9..=15 => Some(Self::Light),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct AutoDetectionPattern {
     pub pattern: String,
     pub color: String,
-    #[serde(default)]
+    /// Background color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
     pub bold: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub italic: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub underline: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub dim: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub strikethrough: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub blink: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub reverse: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub conceal: bool,
+    /// Underline shape: single, double, curly, dotted, dashed (implies underline)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline_style: Option<UnderlineStyle>,
+    /// Underline color, independent of the text color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline_color: Option<String>,
+    /// Processing paths this pattern runs on: `compiled`, `legacy` (default both)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enabled_in: Vec<ProcessingPath>,
+    /// Only match at the line `start`, `end`, or as the `full` line (default anywhere)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<Anchor>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Where in a line an auto-detection pattern may match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    Start,
+    End,
+    Full,
+}
+
+impl Anchor {
+    /// `pattern` pinned to the line start and/or end; a non-capturing group keeps
+    /// any alternation inside it and leaves capture group numbering alone
+    pub fn wrap(self, pattern: &str) -> String {
+        match self {
+            Anchor::Start => format!("^(?:{})", pattern),
+            Anchor::End => format!("(?:{})$", pattern),
+            Anchor::Full => format!("^(?:{})$", pattern),
+        }
+    }
+}
+
+/// The two highlighting paths: the pre-compiled theme and the per-pattern legacy fallback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessingPath {
+    Compiled,
+    Legacy,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct IconMapping {
     pub icon: String,
+    /// Text shown instead of `icon` on terminals without Unicode support
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_fallback: Option<String>,
     pub color: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct StyleGroup {
+    /// What this group is for (shown by `theme filters --verbose`; no effect on highlighting)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub keywords: Vec<String>,
     pub color: String,
-    #[serde(default)]
+    /// Background color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
     pub bold: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub italic: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub underline: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub dim: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub strikethrough: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub blink: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub reverse: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub conceal: bool,
+    /// Underline shape: single, double, curly, dotted, dashed (implies underline)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline_style: Option<UnderlineStyle>,
+    /// Underline color, independent of the text color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline_color: Option<String>,
+    /// Style the entire line, not just the keyword, when any keyword matches
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub whole_line: bool,
+    /// When several `whole_line` groups match a line, the highest priority wins
+    /// (ties go to the first group by name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Text a matched keyword is replaced by before styling; `{}` stands for the
+    /// match itself (e.g. `"✗ {}"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replace_with: Option<String>,
+    /// Icon put in front of every keyword match, outside its color (e.g. `"✗"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Text shown instead of `icon` on terminals without Unicode support
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_fallback: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Style group precedence: a number, or a level name (`critical` > `high` >
+/// `medium` > `low` > `trivial`, ranked 4 down to 0); unset ranks 0
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Priority {
+    Rank(i32),
+    Named(String),
+}
+
+impl Priority {
+    /// Numeric rank; unknown names rank 0
+    pub fn rank(&self) -> i32 {
+        match self {
+            Priority::Rank(rank) => *rank,
+            Priority::Named(name) => match name.to_lowercase().as_str() {
+                "critical" => 4,
+                "high" => 3,
+                "medium" => 2,
+                "low" => 1,
+                _ => 0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Filter {
-    #[serde(default)]
-    pub icon_mappings: HashMap<String, IconMapping>,
-    pub styles: HashMap<String, StyleGroup>,
+    /// What this filter is for (shown by `theme filters`; no effect on highlighting)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub icon_mappings: BTreeMap<String, IconMapping>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub styles: BTreeMap<String, StyleGroup>,
+    /// Regexes for spans keywords are never matched in, e.g. string literals or comments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skip_regions: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl Filter {
+    /// All `skip_regions` as one alternation (None when there are none)
+    pub fn skip_pattern(&self) -> Option<String> {
+        if self.skip_regions.is_empty() {
+            return None;
+        }
+        let alternatives: Vec<String> = self.skip_regions.iter().map(|region| format!("(?:{})", region)).collect();
+        Some(alternatives.join("|"))
+    }
+    
+    /// `skip_pattern` compiled within `limits`
+    pub fn skip_regex(&self, limits: &RegexLimits) -> Result<Option<regex::Regex>, regex::Error> {
+        self.skip_pattern().map(|pattern| limits.compile(&pattern)).transpose()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct CompilationSettings {
     pub optimize_for: String,
     pub pattern_limit: usize,
     pub enable_fast_lookup: bool,
+    /// Max compiled size in bytes for any one theme regex (default 10 MiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_size_limit: Option<usize>,
+    /// Max lazy DFA cache in bytes for any one theme regex (default 2 MiB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dfa_size_limit: Option<usize>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Theme {
     pub metadata: ThemeMetadata,
-    #[serde(default)]
+    /// Theme fragments merged in when loading from a file, relative to this
+    /// file's directory (peers, unlike `defaults`); this file wins on conflicts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub defaults: Option<ThemeDefaults>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub auto_detection: BTreeMap<String, AutoDetectionPattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compilation: Option<CompilationSettings>,
+    /// Semantic color names for `%c:name(text)` templates (name -> palette color)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<String, String>,
+    /// Defaults for `{{name}}` placeholders in theme values (override with `--theme-var`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, String>,
+    pub filters: BTreeMap<String, Filter>,
+}
+
+/// A `{{name}}` placeholder in a theme value
+static VAR_PLACEHOLDER: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+
+/// Partial theme pulled in through `include:`; every section is optional
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ThemeFragment {
     #[serde(default)]
-    pub auto_detection: HashMap<String, AutoDetectionPattern>,
+    include: Vec<String>,
     #[serde(default)]
-    pub compilation: Option<CompilationSettings>,
-    pub filters: HashMap<String, Filter>,
+    auto_detection: BTreeMap<String, AutoDetectionPattern>,
+    #[serde(default)]
+    palette: BTreeMap<String, String>,
+    #[serde(default)]
+    filters: BTreeMap<String, Filter>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Deepest chain of nested `include:`s followed before giving up
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ThemeFragment {
+    /// Load a fragment and, recursively, its own includes; `stack` holds the
+    /// files currently being included to catch cycles
+    fn load(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let canonical = path.canonicalize()
+            .map_err(|e| format!("include '{}': {}", path.display(), e))?;
+        if stack.contains(&canonical) {
+            return Err(format!("include cycle: '{}' is already being included", path.display()).into());
+        }
+        if stack.len() > MAX_INCLUDE_DEPTH {
+            return Err(format!("includes nested more than {} deep at '{}'", MAX_INCLUDE_DEPTH, path.display()).into());
+        }
+        
+        let mut fragment: ThemeFragment = serde_yaml::from_str(&fs::read_to_string(&canonical)?)
+            .map_err(|e| format!("include '{}': {}", path.display(), e))?;
+        stack.push(canonical.clone());
+        let base_dir = canonical.parent().unwrap_or(Path::new("")).to_path_buf();
+        for include in std::mem::take(&mut fragment.include) {
+            let nested = Self::load(&base_dir.join(include), stack)?;
+            merge_entries(&mut fragment.auto_detection, &nested.auto_detection, MergePolicy::KeepExisting);
+            merge_entries(&mut fragment.palette, &nested.palette, MergePolicy::KeepExisting);
+            merge_filters(&mut fragment.filters, &nested.filters, MergePolicy::KeepExisting);
+        }
+        stack.pop();
+        
+        Ok(fragment)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ThemeDefaults {
     #[serde(default)]
-    pub auto_detection: HashMap<String, AutoDetectionPattern>,
+    pub auto_detection: BTreeMap<String, AutoDetectionPattern>,
     #[serde(default)]
-    pub filters: HashMap<String, Filter>,
+    pub filters: BTreeMap<String, Filter>,
+}
+
+/// Which side wins when merging theme maps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MergePolicy {
+    /// Inheritance: entries already present are kept
+    KeepExisting,
+    /// Overlay: incoming entries replace existing ones
+    Overwrite,
+}
+
+/// Merge map entries from `source` into `target`
+fn merge_entries<V: Clone>(target: &mut BTreeMap<String, V>, source: &BTreeMap<String, V>, policy: MergePolicy) {
+    for (key, value) in source {
+        if policy == MergePolicy::Overwrite || !target.contains_key(key) {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Merge filters key-by-key: icon mappings and styles within shared filters,
+/// whole filters when only `source` has them
+fn merge_filters(target: &mut BTreeMap<String, Filter>, source: &BTreeMap<String, Filter>, policy: MergePolicy) {
+    for (filter_name, source_filter) in source {
+        if let Some(target_filter) = target.get_mut(filter_name) {
+            merge_entries(&mut target_filter.icon_mappings, &source_filter.icon_mappings, policy);
+            merge_entries(&mut target_filter.styles, &source_filter.styles, policy);
+            for region in &source_filter.skip_regions {
+                if !target_filter.skip_regions.contains(region) {
+                    target_filter.skip_regions.push(region.clone());
+                }
+            }
+        } else {
+            target.insert(filter_name.clone(), source_filter.clone());
+        }
+    }
+}
+
+/// Theme listing entry: (name, path, source)
+pub type ThemeEntry = (String, PathBuf, String);
+
+/// A theme pack file: (file name, contents)
+#[cfg(not(target_arch = "wasm32"))]
+type PackFile = (String, Vec<u8>);
+
+/// Outcome of `theme install`: installed themes (name, path) and skipped files (file, reason)
+#[derive(Debug, Default)]
+pub struct InstallReport {
+    pub installed: Vec<(String, PathBuf)>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Largest theme pack `theme install` downloads
+#[cfg(feature = "remote-themes")]
+const MAX_PACK_BYTES: u64 = 16 << 20;
+
+/// Largest single theme `--theme <url>` downloads
+#[cfg(feature = "remote-themes")]
+const MAX_THEME_BYTES: u64 = 1 << 20;
+
+/// Minimum WCAG contrast ratio for normal text (AA)
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Contrast of one style group's foreground against its background
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastCheck {
+    pub filter: String,
+    pub group: String,
+    pub color: String,
+    pub bg: String,
+    pub ratio: f64,
+}
+
+impl ContrastCheck {
+    /// Meets the WCAG AA ratio for normal text
+    pub fn passes(&self) -> bool {
+        self.ratio >= MIN_CONTRAST_RATIO
+    }
 }
 
 /// Special value to disable inheritance
@@ -112,6 +487,318 @@ impl<T> InheritanceValue<T> {
     }
 }
 
+impl Default for Theme {
+    /// Get default theme if no file is provided
+    fn default() -> Self {
+        Theme {
+            metadata: ThemeMetadata {
+                name: "jynx-minimal".to_string(),
+                version: "1.0.0".to_string(),
+                description: "Minimal default theme with auto-detection only".to_string(),
+                appearance: None,
+                min_jynx_version: None,
+                summary_groups: BTreeMap::new(),
+                bell_groups: Vec::new(),
+                line_number_color: None,
+                stderr_style: None,
+                ellipsis_style: None,
+                bracket_colors: Vec::new(),
+            },
+            include: Vec::new(),
+            defaults: None,
+            auto_detection: BTreeMap::new(),
+            compilation: None,
+            palette: BTreeMap::new(),
+            vars: BTreeMap::new(),
+            filters: BTreeMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// Parse theme from a YAML string with inheritance support (no filesystem access)
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_yaml_str_with_vars(yaml, &BTreeMap::new())
+    }
+
+    /// `from_yaml_str` with `--theme-var` overrides for its `{{name}}` placeholders
+    pub fn from_yaml_str_with_vars(yaml: &str, vars: &BTreeMap<String, String>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut theme: Theme = serde_yaml::from_str(yaml)?;
+        
+        // Apply inheritance if defaults are present
+        theme.apply_inheritance();
+        
+        theme.substitute_vars(vars)
+    }
+    
+    /// Replace `{{name}}` placeholders in every theme value with `overrides[name]`,
+    /// else the theme's `vars` default; a placeholder with neither is an error
+    pub fn substitute_vars(self, overrides: &BTreeMap<String, String>) -> Result<Self, Box<dyn std::error::Error>> {
+        let placeholder = VAR_PLACEHOLDER.get_or_init(|| regex::Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").unwrap());
+        let mut value = serde_yaml::to_value(&self)?;
+        let mut undefined = Vec::new();
+        
+        fn walk(value: &mut serde_yaml::Value, substitute: &mut dyn FnMut(&str) -> Option<String>) {
+            match value {
+                serde_yaml::Value::String(text) => {
+                    if let Some(replaced) = substitute(text) {
+                        *text = replaced;
+                    }
+                },
+                serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(|item| walk(item, substitute)),
+                serde_yaml::Value::Mapping(map) => map.iter_mut()
+                    .filter(|(key, _)| key.as_str() != Some("vars"))
+                    .for_each(|(_, item)| walk(item, substitute)),
+                _ => {},
+            }
+        }
+        walk(&mut value, &mut |text: &str| {
+            if !placeholder.is_match(text) {
+                return None;
+            }
+            Some(placeholder.replace_all(text, |caps: &regex::Captures| {
+                match overrides.get(&caps[1]).or_else(|| self.vars.get(&caps[1])) {
+                    Some(value) => value.clone(),
+                    None => {
+                        if !undefined.contains(&caps[1].to_string()) {
+                            undefined.push(caps[1].to_string());
+                        }
+                        caps[0].to_string()
+                    },
+                }
+            }).into_owned())
+        });
+        
+        if !undefined.is_empty() {
+            return Err(format!(
+                "theme '{}' uses undefined var(s) {} (give them a `vars:` default or pass --theme-var NAME=VALUE)",
+                self.metadata.name, undefined.join(", ")
+            ).into());
+        }
+        Ok(serde_yaml::from_value(value)?)
+    }
+    
+    /// Apply theme inheritance: defaults + user overrides
+    fn apply_inheritance(&mut self) {
+        if let Some(defaults) = &self.defaults.clone() {
+            // Merge auto_detection patterns
+            merge_entries(&mut self.auto_detection, &defaults.auto_detection, MergePolicy::KeepExisting);
+            
+            // Merge filters with selective override support (user overrides defaults)
+            merge_filters(&mut self.filters, &defaults.filters, MergePolicy::KeepExisting);
+        }
+    }
+    
+    /// Deep-merge another theme on top of this one (`other` wins on conflicts)
+    ///
+    /// Filters are merged key-by-key like inheritance, but in the opposite
+    /// direction: icon mappings and style groups from `other` replace ours.
+    pub fn merge(&mut self, other: Theme) {
+        let mut summary_groups = std::mem::take(&mut self.metadata.summary_groups);
+        merge_entries(&mut summary_groups, &other.metadata.summary_groups, MergePolicy::Overwrite);
+        let mut bell_groups = std::mem::take(&mut self.metadata.bell_groups);
+        for group in &other.metadata.bell_groups {
+            if !bell_groups.contains(group) {
+                bell_groups.push(group.clone());
+            }
+        }
+        self.metadata = other.metadata;
+        self.metadata.summary_groups = summary_groups;
+        self.metadata.bell_groups = bell_groups;
+        if other.compilation.is_some() {
+            self.compilation = other.compilation;
+        }
+        merge_entries(&mut self.auto_detection, &other.auto_detection, MergePolicy::Overwrite);
+        merge_entries(&mut self.palette, &other.palette, MergePolicy::Overwrite);
+        merge_filters(&mut self.filters, &other.filters, MergePolicy::Overwrite);
+    }
+    
+    /// Check `metadata.min_jynx_version` against the running jynx
+    pub fn check_min_version(&self) -> Result<(), String> {
+        let Some(required) = &self.metadata.min_jynx_version else {
+            return Ok(());
+        };
+        let running = env!("CARGO_PKG_VERSION");
+        match (parse_version(required), parse_version(running)) {
+            (Some(required_version), Some(running_version)) if running_version >= required_version => Ok(()),
+            (Some(_), _) => Err(format!(
+                "Theme '{}' requires jynx {} or newer (running {})",
+                self.metadata.name, required, running
+            )),
+            (None, _) => Err(format!(
+                "Theme '{}' has an invalid min_jynx_version '{}' (expected major.minor.patch)",
+                self.metadata.name, required
+            )),
+        }
+    }
+    
+    /// Apply the `min_jynx_version` check: an error under `strict`, otherwise a warning
+    pub fn enforce_min_version(self, strict: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Err(e) = self.check_min_version() {
+            if strict {
+                return Err(e.into());
+            }
+            eprintln!("Warning: {}", e);
+        }
+        Ok(self)
+    }
+    
+    /// Regex size caps from `compilation:`, with defaults for anything unset
+    pub fn regex_limits(&self) -> RegexLimits {
+        let defaults = RegexLimits::default();
+        let settings = self.compilation.as_ref();
+        RegexLimits {
+            size_limit: settings.and_then(|c| c.regex_size_limit).unwrap_or(defaults.size_limit),
+            dfa_size_limit: settings.and_then(|c| c.dfa_size_limit).unwrap_or(defaults.dfa_size_limit),
+        }
+    }
+    
+    /// Generate JSON Schema for the theme format (for editor YAML autocompletion)
+    pub fn json_schema() -> Result<String, serde_json::Error> {
+        let schema = schemars::schema_for!(Theme);
+        serde_json::to_string_pretty(&schema)
+    }
+    
+    /// Get icon mapping for a word pattern
+    pub fn get_icon_mapping(&self, filter_name: &str, word: &str) -> Option<&IconMapping> {
+        self.filters
+            .get(filter_name)?
+            .icon_mappings
+            .get(word)
+    }
+    
+    /// Get all keywords for a specific filter
+    pub fn get_filter_keywords(&self, filter_name: &str) -> Vec<&str> {
+        if let Some(filter) = self.filters.get(filter_name) {
+            filter.styles.values()
+                .flat_map(|style| style.keywords.iter().map(|s| s.as_str()))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+    
+    /// WCAG contrast of every style group that sets both `color` and `bg`
+    /// (groups using colors without a known RGB value are skipped)
+    pub fn contrast_report(&self) -> Vec<ContrastCheck> {
+        let mut checks = Vec::new();
+        for (filter_name, filter) in &self.filters {
+            for (group_name, group) in &filter.styles {
+                let Some(bg) = &group.bg else { continue };
+                if let (Some(fg_rgb), Some(bg_rgb)) = (get_color_rgb(&group.color), get_color_rgb(bg)) {
+                    checks.push(ContrastCheck {
+                        filter: filter_name.clone(),
+                        group: group_name.clone(),
+                        color: group.color.clone(),
+                        bg: bg.clone(),
+                        ratio: contrast_ratio(fg_rgb, bg_rgb),
+                    });
+                }
+            }
+        }
+        checks
+    }
+    
+    /// Human-readable filter listing: name with style group and icon mapping counts
+    /// With `verbose`, each style group's keywords are listed underneath
+    pub fn describe_filters(&self, verbose: bool) -> String {
+        let mut out = String::new();
+        
+        for (filter_name, filter) in &self.filters {
+            out.push_str(&format!("{} ({} style groups, {} icon mappings){}\n",
+                filter_name, filter.styles.len(), filter.icon_mappings.len(), describe_suffix(&filter.description)));
+            
+            if verbose {
+                for (style_name, style) in &filter.styles {
+                    out.push_str(&format!("    {}: {}{}\n",
+                        style_name, style.keywords.join(", "), describe_suffix(&style.description)));
+                }
+            }
+        }
+        
+        out
+    }
+    
+    /// Serialize theme as canonically-ordered YAML
+    /// Maps are already ordered (BTreeMap); keywords within each group are sorted
+    pub fn to_canonical_yaml(&self) -> Result<String, serde_yaml::Error> {
+        let mut theme = self.clone();
+        
+        let default_filters = theme.defaults.as_mut().map(|d| &mut d.filters);
+        for filter in theme.filters.values_mut().chain(default_filters.into_iter().flat_map(|f| f.values_mut())) {
+            for style in filter.styles.values_mut() {
+                style.keywords.sort();
+                style.keywords.dedup();
+            }
+        }
+        
+        serde_yaml::to_string(&theme)
+    }
+}
+
+/// Replace `path` with `contents` via a temp file in the same directory and a
+/// rename, so an interrupted write never leaves a truncated theme behind
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.tmp-{}", file_name, std::process::id()));
+    
+    let result = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Editors `theme edit` falls back to when neither `$VISUAL` nor `$EDITOR` is usable
+#[cfg(not(target_arch = "wasm32"))]
+const FALLBACK_EDITORS: &[&str] = if cfg!(windows) { &["nano", "vi", "vim", "notepad"] } else { &["nano", "vi", "vim"] };
+
+/// The first usable editor command, split into program and arguments: each of
+/// `configured` (`$VISUAL`, `$EDITOR`), then `FALLBACK_EDITORS`, whose program
+/// is a file or found on `path`. The error lists everything that was tried.
+#[cfg(not(target_arch = "wasm32"))]
+fn find_editor(configured: &[Option<String>], path: Option<&std::ffi::OsStr>) -> Result<Vec<String>, String> {
+    let mut tried = Vec::new();
+    let candidates = configured.iter().flatten().map(String::as_str).chain(FALLBACK_EDITORS.iter().copied());
+    for command in candidates {
+        let words: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+        let Some(program) = words.first() else {
+            continue;
+        };
+        if program_on_path(program, path) {
+            return Ok(words);
+        }
+        tried.push(command.to_string());
+    }
+    Err(format!("No editor found (tried {}); set $VISUAL or $EDITOR", tried.join(", ")))
+}
+
+/// Whether `program` (a bare name looked up on `path`, or a path) is an existing file
+#[cfg(not(target_arch = "wasm32"))]
+fn program_on_path(program: &str, path: Option<&std::ffi::OsStr>) -> bool {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file();
+    }
+    let Some(path) = path else {
+        return false;
+    };
+    env::split_paths(path).any(|dir| {
+        let candidate = dir.join(program);
+        candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+    })
+}
+
+/// Theme resolution and management on the filesystem (unavailable on wasm)
+#[cfg(not(target_arch = "wasm32"))]
 impl Theme {
     /// Get XDG+ theme directory path
     pub fn xdg_theme_dir() -> PathBuf {
@@ -122,64 +809,151 @@ impl Theme {
         }
     }
     
+    /// Directories searched for theme names, in precedence order, with a source label:
+    /// `$JYNX_THEME_PATH` entries (`:`-separated, `;` on Windows), then XDG+, then `./themes`
+    pub fn theme_search_path() -> Vec<(PathBuf, String)> {
+        Self::theme_search_path_with(&[])
+    }
+    
+    /// `theme_search_path` with extra directories (`--theme-dir`) searched first
+    pub fn theme_search_path_with(dirs: &[PathBuf]) -> Vec<(PathBuf, String)> {
+        Self::search_path_from(dirs, env::var_os("JYNX_THEME_PATH").as_deref())
+    }
+    
+    /// Build the search path from extra directories and a `JYNX_THEME_PATH` value
+    fn search_path_from(extra: &[PathBuf], theme_path: Option<&std::ffi::OsStr>) -> Vec<(PathBuf, String)> {
+        let mut dirs: Vec<(PathBuf, String)> = extra.iter()
+            .map(|dir| (dir.clone(), "dir".to_string()))
+            .collect();
+        dirs.extend(theme_path.into_iter()
+            .flat_map(env::split_paths)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| (dir, "path".to_string())));
+        dirs.push((Self::xdg_theme_dir(), "XDG+".to_string()));
+        dirs.push((PathBuf::from("themes"), "local".to_string()));
+        dirs
+    }
+    
     /// Resolve theme name to actual file path with XDG+ fallback hierarchy
-    /// - `rebel` → `~/.local/etc/rsb/jynx/themes/theme_rebel.yml`
+    /// - `rebel` → `theme_rebel.yml` in `$JYNX_THEME_PATH`, `~/.local/etc/rsb/jynx/themes`, `./themes`
     /// - `./my_theme.yml` → relative path as-is
     /// - `/abs/path.yml` → absolute path as-is
     pub fn resolve_theme_path(theme_name: &str) -> Option<PathBuf> {
+        Self::resolve_theme_path_in(theme_name, &Self::theme_search_path())
+    }
+    
+    /// Resolve a theme name against an explicit search path
+    ///
+    /// URLs never resolve: lookups stay offline, and a URL is turned into a file
+    /// with `fetch_remote_theme`, which is given the cache directory to use
+    pub fn resolve_theme_path_in(theme_name: &str, search_path: &[(PathBuf, String)]) -> Option<PathBuf> {
+        if Self::is_theme_url(theme_name) {
+            return None;
+        }
+        
         // Handle relative and absolute paths directly
         if theme_name.starts_with("./") || theme_name.starts_with("/") || theme_name.ends_with(".yml") {
             let path = PathBuf::from(theme_name);
             return if path.exists() { Some(path) } else { None };
         }
         
-        // Handle theme name resolution with fallback hierarchy
+        // 1. `theme_<name>.yml` in each directory, in order
         let theme_filename = format!("theme_{}.yml", theme_name);
-        
-        // 1. XDG+ location first
-        let xdg_path = Self::xdg_theme_dir().join(&theme_filename);
-        if xdg_path.exists() {
-            return Some(xdg_path);
-        }
-        
-        // 2. Local ./themes/ directory
-        let local_path = PathBuf::from("themes").join(&theme_filename);
-        if local_path.exists() {
-            return Some(local_path);
-        }
-        
-        // 3. Try direct filename in XDG+
-        let direct_xdg_path = Self::xdg_theme_dir().join(theme_name);
-        if direct_xdg_path.exists() {
-            return Some(direct_xdg_path);
+        // 2. Then the direct filename in each directory
+        [theme_filename.as_str(), theme_name]
+            .iter()
+            .flat_map(|filename| search_path.iter().map(move |(dir, _)| dir.join(filename)))
+            .find(|path| path.exists())
+    }
+    
+    /// True for `http://` and `https://` theme names
+    pub fn is_theme_url(theme_name: &str) -> bool {
+        theme_name.starts_with("http://") || theme_name.starts_with("https://")
+    }
+    
+    /// Where a theme URL is cached: `<cache_dir>/remote/<hash of the URL>.yml`
+    ///
+    /// FNV-1a, so the name stays the same across Rust releases and builds
+    pub fn remote_theme_cache_path(url: &str, cache_dir: &Path) -> PathBuf {
+        let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        cache_dir.join("remote").join(format!("{:016x}.yml", hash))
+    }
+    
+    /// Local copy of a theme URL: the cached one, or a fresh download when there is
+    /// none or `refresh` is set. A download must parse as a theme before it replaces
+    /// the cache; when it fails, an existing cached copy is used with a warning.
+    #[cfg(feature = "remote-themes")]
+    pub fn fetch_remote_theme(url: &str, cache_dir: &Path, refresh: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = Self::remote_theme_cache_path(url, cache_dir);
+        if !refresh && path.exists() {
+            return Ok(path);
         }
         
-        // 4. Try direct filename in local themes
-        let direct_local_path = PathBuf::from("themes").join(theme_name);
-        if direct_local_path.exists() {
-            return Some(direct_local_path);
+        let download = || -> Result<String, Box<dyn std::error::Error>> {
+            use std::io::Read;
+            let mut yaml = String::new();
+            ureq::get(url).call()?.into_reader().take(MAX_THEME_BYTES).read_to_string(&mut yaml)?;
+            // Only the shape is checked: `{{name}}` vars are filled in when the theme is loaded
+            serde_yaml::from_str::<Theme>(&yaml)?;
+            Ok(yaml)
+        };
+        match download() {
+            Ok(yaml) => {
+                fs::create_dir_all(path.parent().unwrap_or(cache_dir))?;
+                write_atomic(&path, yaml.as_bytes())?;
+                Ok(path)
+            },
+            Err(e) if path.exists() => {
+                eprintln!("Warning: Failed to refresh theme {}: {} (using the cached copy)", url, e);
+                Ok(path)
+            },
+            Err(e) => Err(format!("Failed to download theme {}: {}", url, e).into()),
         }
-        
-        None
     }
     
     /// Load theme with smart resolution
     pub fn load_theme(theme_name: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_theme_with_path(theme_name, false).map(|(theme, _)| theme)
+    }
+    
+    /// Load theme with smart resolution, also returning the file it came from
+    /// (`None` for the embedded default); `strict` makes `min_jynx_version` mismatches errors
+    pub fn load_theme_with_path(theme_name: Option<&str>, strict: bool) -> Result<(Self, Option<PathBuf>), Box<dyn std::error::Error>> {
+        Self::load_theme_with_path_in(theme_name, strict, &Self::theme_search_path(), &BTreeMap::new())
+    }
+    
+    /// `load_theme_with_path` against an explicit search path, with `--theme-var` overrides
+    pub fn load_theme_with_path_in(theme_name: Option<&str>, strict: bool, search_path: &[(PathBuf, String)],
+                                   vars: &BTreeMap<String, String>) -> Result<(Self, Option<PathBuf>), Box<dyn std::error::Error>> {
         match theme_name {
             Some(name) => {
-                if let Some(path) = Self::resolve_theme_path(name) {
-                    Self::load_from_file(path)
+                if let Some(path) = Self::resolve_theme_path_in(name, search_path) {
+                    Ok((Self::load_from_file_with_vars(&path, strict, vars)?, Some(path)))
+                } else if Self::is_theme_url(name) {
+                    match cfg!(feature = "remote-themes") {
+                        true => Err(format!("Theme URL '{}' must be downloaded first (`--theme` does; see `Theme::fetch_remote_theme`)", name).into()),
+                        false => Err(format!("Theme URLs need the `remote-themes` feature ('{}')", name).into()),
+                    }
                 } else {
-                    Err(format!("Theme '{}' not found in XDG+ or local themes", name).into())
+                    Err(format!("Theme '{}' not found in theme path, XDG+ or local themes", name).into())
                 }
             },
             None => {
+                // $JYNX_THEME names the user's preferred default theme
+                if let Ok(name) = env::var("JYNX_THEME") {
+                    if !name.is_empty() {
+                        return Self::load_theme_with_path_in(Some(&name), strict, search_path, vars);
+                    }
+                }
+                
                 // Try default theme from XDG+ first
-                if let Some(path) = Self::resolve_theme_path("default") {
-                    Self::load_from_file(path)
+                if let Some(path) = Self::resolve_theme_path_in("default", search_path) {
+                    Ok((Self::load_from_file_with_vars(&path, strict, vars)?, Some(path)))
                 } else {
                     // Fallback to embedded default
-                    Ok(Self::default())
+                    Ok((Self::default(), None))
                 }
             }
         }
@@ -187,112 +961,67 @@ impl Theme {
     
     /// Load theme from YAML file with inheritance support
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(path)?;
-        let mut theme: Theme = serde_yaml::from_str(&content)?;
-        
-        // Apply inheritance if defaults are present
-        theme.apply_inheritance();
-        
-        Ok(theme)
+        Self::load_from_file_checked(path, false)
     }
     
-    /// Apply theme inheritance: defaults + user overrides
-    fn apply_inheritance(&mut self) {
-        if let Some(defaults) = &self.defaults.clone() {
-            // Merge auto_detection patterns
-            for (key, default_pattern) in &defaults.auto_detection {
-                if !self.auto_detection.contains_key(key) {
-                    self.auto_detection.insert(key.clone(), default_pattern.clone());
-                }
-            }
-            
-            // Merge filters with selective override support
-            for (filter_name, default_filter) in &defaults.filters {
-                if let Some(user_filter) = self.filters.get_mut(filter_name) {
-                    // Merge icon mappings (user overrides defaults)
-                    for (icon_key, default_icon) in &default_filter.icon_mappings {
-                        if !user_filter.icon_mappings.contains_key(icon_key) {
-                            user_filter.icon_mappings.insert(icon_key.clone(), default_icon.clone());
-                        }
-                    }
-                    
-                    // Merge styles (user overrides defaults)
-                    for (style_key, default_style) in &default_filter.styles {
-                        if !user_filter.styles.contains_key(style_key) {
-                            user_filter.styles.insert(style_key.clone(), default_style.clone());
-                        }
-                    }
-                } else {
-                    // No user filter exists, use defaults entirely
-                    self.filters.insert(filter_name.clone(), default_filter.clone());
-                }
+    /// `load_from_file`, failing instead of warning on `min_jynx_version` under `strict`
+    pub fn load_from_file_checked<P: AsRef<Path>>(path: P, strict: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from_file_with_vars(path, strict, &BTreeMap::new())
+    }
+
+    /// `load_from_file_checked` with `--theme-var` overrides for its `{{name}}` placeholders
+    pub fn load_from_file_with_vars<P: AsRef<Path>>(path: P, strict: bool, vars: &BTreeMap<String, String>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let mut theme = Self::load_raw_from_file(path)?;
+        
+        // Included fragments are merged before `defaults` inheritance
+        if !theme.include.is_empty() {
+            let mut stack: Vec<PathBuf> = path.canonicalize().into_iter().collect();
+            let base_dir = path.parent().unwrap_or(Path::new(""));
+            for include in std::mem::take(&mut theme.include) {
+                let fragment = ThemeFragment::load(&base_dir.join(include), &mut stack)?;
+                merge_entries(&mut theme.auto_detection, &fragment.auto_detection, MergePolicy::KeepExisting);
+                merge_entries(&mut theme.palette, &fragment.palette, MergePolicy::KeepExisting);
+                merge_filters(&mut theme.filters, &fragment.filters, MergePolicy::KeepExisting);
             }
         }
+        
+        theme.apply_inheritance();
+        theme.substitute_vars(vars)?.enforce_min_version(strict)
     }
     
-    /// Get default theme if no file is provided
-    pub fn default() -> Self {
-        Theme {
-            metadata: ThemeMetadata {
-                name: "jynx-minimal".to_string(),
-                version: "1.0.0".to_string(),
-                description: "Minimal default theme with auto-detection only".to_string(),
-            },
-            defaults: None,
-            auto_detection: HashMap::new(),
-            compilation: None,
-            filters: HashMap::new(),
+    /// Load theme YAML from an open file descriptor (e.g. `jynx --theme-fd 3 3<theme.yml`)
+    /// with `--theme-var` overrides for its `{{name}}` placeholders
+    pub fn load_from_fd(fd: i32, vars: &BTreeMap<String, String>) -> Result<Self, Box<dyn std::error::Error>> {
+        if cfg!(unix) {
+            Self::from_yaml_str_with_vars(&fs::read_to_string(format!("/dev/fd/{}", fd))?, vars)
+        } else {
+            Err("Loading themes from file descriptors requires a Unix platform".into())
         }
     }
     
-    /// Get icon mapping for a word pattern
-    pub fn get_icon_mapping(&self, filter_name: &str, word: &str) -> Option<&IconMapping> {
-        self.filters
-            .get(filter_name)?
-            .icon_mappings
-            .get(word)
+    /// Load theme from YAML file exactly as written (no inheritance applied)
+    pub fn load_raw_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let theme: Theme = serde_yaml::from_str(&content)?;
+        Ok(theme)
     }
     
-    /// Get all keywords for a specific filter
-    pub fn get_filter_keywords(&self, filter_name: &str) -> Vec<&str> {
-        if let Some(filter) = self.filters.get(filter_name) {
-            filter.styles.values()
-                .flat_map(|style| style.keywords.iter().map(|s| s.as_str()))
-                .collect()
-        } else {
-            Vec::new()
-        }
+    /// List all available themes (XDG+ and local)
+    pub fn list_themes() -> Result<Vec<ThemeEntry>, Box<dyn std::error::Error>> {
+        Self::list_themes_in(&Self::theme_search_path())
     }
     
-    /// List all available themes (XDG+ and local)
-    pub fn list_themes() -> Result<Vec<(String, PathBuf, String)>, Box<dyn std::error::Error>> {
-        let mut themes = Vec::new();
+    /// List the themes in an explicit search path
+    pub fn list_themes_in(search_path: &[(PathBuf, String)]) -> Result<Vec<ThemeEntry>, Box<dyn std::error::Error>> {
+        let mut themes: Vec<ThemeEntry> = Vec::new();
         
-        // Check XDG+ themes
-        let xdg_dir = Self::xdg_theme_dir();
-        if xdg_dir.exists() {
-            for entry in fs::read_dir(&xdg_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if let Some(filename) = path.file_name() {
-                    if let Some(filename_str) = filename.to_str() {
-                        if filename_str.ends_with(".yml") {
-                            let theme_name = if filename_str.starts_with("theme_") {
-                                filename_str.strip_prefix("theme_").unwrap().strip_suffix(".yml").unwrap()
-                            } else {
-                                filename_str.strip_suffix(".yml").unwrap()
-                            };
-                            themes.push((theme_name.to_string(), path.clone(), "XDG+".to_string()));
-                        }
-                    }
-                }
+        // Earlier directories shadow later ones with the same theme name
+        for (dir, source) in search_path {
+            if !dir.exists() {
+                continue;
             }
-        }
-        
-        // Check local themes
-        let local_dir = PathBuf::from("themes");
-        if local_dir.exists() {
-            for entry in fs::read_dir(&local_dir)? {
+            for entry in fs::read_dir(dir)? {
                 let entry = entry?;
                 let path = entry.path();
                 if let Some(filename) = path.file_name() {
@@ -303,9 +1032,8 @@ impl Theme {
                             } else {
                                 filename_str.strip_suffix(".yml").unwrap()
                             };
-                            // Only add if not already found in XDG+
                             if !themes.iter().any(|(name, _, _)| name == theme_name) {
-                                themes.push((theme_name.to_string(), path.clone(), "local".to_string()));
+                                themes.push((theme_name.to_string(), path.clone(), source.clone()));
                             }
                         }
                     }
@@ -317,9 +1045,47 @@ impl Theme {
         Ok(themes)
     }
     
+    /// List installed themes whose metadata declares the given appearance
+    pub fn list_themes_for_appearance(appearance: Appearance) -> Result<Vec<ThemeEntry>, Box<dyn std::error::Error>> {
+        Self::list_themes_for_appearance_in(appearance, &Self::theme_search_path())
+    }
+    
+    /// `list_themes_for_appearance` over an explicit search path
+    pub fn list_themes_for_appearance_in(appearance: Appearance, search_path: &[(PathBuf, String)]) -> Result<Vec<ThemeEntry>, Box<dyn std::error::Error>> {
+        let themes = Self::list_themes_in(search_path)?;
+        
+        Ok(themes.into_iter()
+            .filter(|(_, path, _)| {
+                Self::load_raw_from_file(path)
+                    .map(|theme| theme.metadata.appearance == Some(appearance))
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+    
+    /// Pick an installed theme matching the appearance (prefers one named "default")
+    pub fn find_theme_for_appearance(appearance: Appearance) -> Option<PathBuf> {
+        Self::find_theme_for_appearance_in(appearance, &Self::theme_search_path())
+    }
+    
+    /// `find_theme_for_appearance` over an explicit search path
+    pub fn find_theme_for_appearance_in(appearance: Appearance, search_path: &[(PathBuf, String)]) -> Option<PathBuf> {
+        let themes = Self::list_themes_for_appearance_in(appearance, search_path).ok()?;
+        themes.iter()
+            .find(|(name, _, _)| name == "default")
+            .or_else(|| themes.first())
+            .map(|(_, path, _)| path.clone())
+    }
+    
     /// Create a new theme by copying default theme to current location
-    pub fn create_theme(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let target_path = PathBuf::from(format!("{}.yml", name));
+    pub fn create_theme(name: &str, force: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Self::create_theme_in(Path::new(""), name, force)
+    }
+    
+    /// Create `<name>.yml` in `dir` from the default theme
+    pub fn create_theme_in(dir: &Path, name: &str, force: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let target_path = dir.join(format!("{}.yml", name));
+        Self::check_overwrite(&target_path, force)?;
         
         // Load default theme or use embedded default
         let theme = Self::load_theme(Some("default")).unwrap_or_else(|_| Self::default());
@@ -327,53 +1093,202 @@ impl Theme {
         // Serialize theme to YAML
         let yaml_content = serde_yaml::to_string(&theme)?;
         
-        fs::write(&target_path, yaml_content)?;
+        write_atomic(&target_path, yaml_content.as_bytes())?;
         Ok(target_path)
     }
     
     /// Import theme from current location to XDG+
-    pub fn import_theme(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let source_path = PathBuf::from(format!("{}.yml", name));
+    pub fn import_theme(name: &str, force: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Self::import_theme_in(Path::new(""), &Self::xdg_theme_dir(), name, force)
+    }
+    
+    /// Copy `<name>.yml` from `source_dir` to `theme_<name>.yml` in `target_dir`
+    pub fn import_theme_in(source_dir: &Path, target_dir: &Path, name: &str, force: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let source_path = source_dir.join(format!("{}.yml", name));
         if !source_path.exists() {
             return Err(format!("Theme file '{}' not found in current directory", source_path.display()).into());
         }
         
-        let target_dir = Self::xdg_theme_dir();
-        fs::create_dir_all(&target_dir)?;
-        
         let target_path = target_dir.join(format!("theme_{}.yml", name));
-        fs::copy(&source_path, &target_path)?;
+        Self::check_overwrite(&target_path, force)?;
+        fs::create_dir_all(target_dir)?;
+        write_atomic(&target_path, &fs::read(&source_path)?)?;
         
         Ok(target_path)
     }
     
     /// Export theme from XDG+ to current location
-    pub fn export_theme(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let source_path = Self::xdg_theme_dir().join(format!("theme_{}.yml", name));
+    pub fn export_theme(name: &str, force: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Self::export_theme_in(&Self::xdg_theme_dir(), Path::new(""), name, force)
+    }
+    
+    /// Copy `theme_<name>.yml` from `source_dir` to `<name>.yml` in `target_dir`
+    pub fn export_theme_in(source_dir: &Path, target_dir: &Path, name: &str, force: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let source_path = source_dir.join(format!("theme_{}.yml", name));
         if !source_path.exists() {
             return Err(format!("Theme '{}' not found in XDG+ themes", name).into());
         }
         
-        let target_path = PathBuf::from(format!("{}.yml", name));
-        fs::copy(&source_path, &target_path)?;
+        let target_path = target_dir.join(format!("{}.yml", name));
+        Self::check_overwrite(&target_path, force)?;
+        write_atomic(&target_path, &fs::read(&source_path)?)?;
         
         Ok(target_path)
     }
     
-    /// Edit theme in $EDITOR
+    /// Install every `theme_*.yml` of a theme pack into XDG+
+    pub fn install_pack(source: &str, force: bool) -> Result<InstallReport, Box<dyn std::error::Error>> {
+        Self::install_pack_in(source, &Self::xdg_theme_dir(), force)
+    }
+    
+    /// Install every `theme_*.yml` of a theme pack (a directory, a `.tar.gz`, or
+    /// with `remote-themes` an http(s) URL to one) into `target_dir`. Each theme
+    /// must parse and compile; failing or already installed ones (unless forced)
+    /// are reported as skipped rather than aborting the rest.
+    pub fn install_pack_in(source: &str, target_dir: &Path, force: bool) -> Result<InstallReport, Box<dyn std::error::Error>> {
+        let files = Self::pack_files(source)?;
+        if files.is_empty() {
+            return Err(format!("No theme_*.yml files in '{}'", source).into());
+        }
+        
+        let mut report = InstallReport::default();
+        for (file_name, contents) in files {
+            let name = file_name.strip_prefix("theme_").and_then(|rest| rest.strip_suffix(".yml")).unwrap_or(&file_name).to_string();
+            let target_path = target_dir.join(&file_name);
+            let checked = Self::check_overwrite(&target_path, force).and_then(|_| Self::check_installable(&contents));
+            if let Err(e) = checked {
+                report.skipped.push((file_name, e.to_string()));
+                continue;
+            }
+            fs::create_dir_all(target_dir)?;
+            write_atomic(&target_path, &contents)?;
+            report.installed.push((name, target_path));
+        }
+        Ok(report)
+    }
+    
+    /// The `theme_*.yml` files of a pack, sorted by name
+    fn pack_files(source: &str) -> Result<Vec<PackFile>, Box<dyn std::error::Error>> {
+        let is_theme_file = |name: &str| name.starts_with("theme_") && name.ends_with(".yml");
+        let is_tarball = |name: &str| name.ends_with(".tar.gz") || name.ends_with(".tgz");
+        
+        let mut files = Vec::new();
+        if source.starts_with("http://") || source.starts_with("https://") {
+            #[cfg(feature = "remote-themes")]
+            {
+                use std::io::Read;
+                let mut bytes = Vec::new();
+                ureq::get(source).call()?.into_reader().take(MAX_PACK_BYTES).read_to_end(&mut bytes)?;
+                files = Self::tarball_files(&bytes[..], &is_theme_file)?;
+            }
+            #[cfg(not(feature = "remote-themes"))]
+            return Err("Installing from a URL needs the `remote-themes` feature".into());
+        } else if Path::new(source).is_dir() {
+            for entry in fs::read_dir(source)? {
+                let path = entry?.path();
+                let Some(file_name) = path.file_name().and_then(|name| name.to_str()).filter(|name| is_theme_file(name)) else {
+                    continue;
+                };
+                files.push((file_name.to_string(), fs::read(&path)?));
+            }
+        } else if is_tarball(source) {
+            #[cfg(feature = "gzip")]
+            {
+                files = Self::tarball_files(fs::File::open(source)?, &is_theme_file)?;
+            }
+            #[cfg(not(feature = "gzip"))]
+            return Err("Installing from a .tar.gz needs the `gzip` feature".into());
+        } else {
+            return Err(format!("'{}' is not a directory or .tar.gz theme pack", source).into());
+        }
+        
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(files)
+    }
+    
+    /// Theme files anywhere in a gzipped tar, keyed by their base name
+    #[cfg(feature = "gzip")]
+    fn tarball_files(reader: impl std::io::Read, is_theme_file: &dyn Fn(&str) -> bool) -> std::io::Result<Vec<PackFile>> {
+        use std::io::Read;
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+        let mut files = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?;
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()).filter(|name| is_theme_file(name)) else {
+                continue;
+            };
+            let file_name = file_name.to_string();
+            if entry.header().entry_type().is_file() {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents)?;
+                files.push((file_name, contents));
+            }
+        }
+        Ok(files)
+    }
+    
+    /// The compile check a pack theme must pass before it is installed
+    fn check_installable(contents: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let theme = Self::from_yaml_str(std::str::from_utf8(contents)?)?;
+        #[cfg(feature = "compiled")]
+        crate::std::compiled::CompiledTheme::from_theme(&theme)?.init_runtime()?;
+        #[cfg(not(feature = "compiled"))]
+        let _ = theme;
+        Ok(())
+    }
+    
+    /// Refuse to replace an existing file unless forced
+    fn check_overwrite(path: &Path, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if path.exists() && !force {
+            return Err(format!("'{}' already exists (use --force to overwrite)", path.display()).into());
+        }
+        Ok(())
+    }
+    
+    /// Rewrite a theme file in canonical form (sorted filters, sorted keywords)
+    /// With `resolve`, defaults are merged into the filters and dropped
+    pub fn format_theme(name: &str, resolve: bool) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Self::format_theme_in(name, resolve, &Self::theme_search_path())
+    }
+    
+    /// `format_theme` for a name resolved against an explicit search path
+    pub fn format_theme_in(name: &str, resolve: bool, search_path: &[(PathBuf, String)]) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let theme_path = Self::resolve_theme_path_in(name, search_path)
+            .ok_or_else(|| format!("Theme '{}' not found", name))?;
+        
+        let mut theme = Self::load_raw_from_file(&theme_path)?;
+        if resolve {
+            theme.apply_inheritance();
+            theme.defaults = None;
+        }
+        
+        write_atomic(&theme_path, theme.to_canonical_yaml()?.as_bytes())?;
+        Ok(theme_path)
+    }
+    
+    /// Edit theme in $VISUAL or $EDITOR (which may carry arguments, e.g. `code --wait`),
+    /// falling back to nano, vi or vim (and notepad on Windows)
     pub fn edit_theme(name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let theme_path = Self::resolve_theme_path(name)
+        Self::edit_theme_in(name, &Self::theme_search_path())
+    }
+    
+    /// `edit_theme` for a name resolved against an explicit search path
+    pub fn edit_theme_in(name: &str, search_path: &[(PathBuf, String)]) -> Result<(), Box<dyn std::error::Error>> {
+        let theme_path = Self::resolve_theme_path_in(name, search_path)
             .ok_or_else(|| format!("Theme '{}' not found", name))?;
         
-        let editor = env::var("EDITOR").unwrap_or_else(|_| "nano".to_string());
+        let editor = find_editor(&[env::var("VISUAL").ok(), env::var("EDITOR").ok()], env::var_os("PATH").as_deref())?;
         
-        let mut child = std::process::Command::new(&editor)
+        let mut child = std::process::Command::new(&editor[0])
+            .args(&editor[1..])
             .arg(&theme_path)
-            .spawn()?;
+            .spawn()
+            .map_err(|e| format!("Failed to start editor '{}': {}", editor.join(" "), e))?;
         
         let status = child.wait()?;
         if !status.success() {
-            return Err(format!("Editor '{}' exited with error", editor).into());
+            return Err(format!("Editor '{}' exited with error", editor.join(" ")).into());
         }
         
         Ok(())
@@ -383,81 +1298,688 @@ impl Theme {
 /// ANSI style codes
 pub struct AnsiCodes;
 impl AnsiCodes {
-    pub const RESET: &'static str = "\x1b[0m";
-    pub const BOLD: &'static str = "\x1b[1m";
-    pub const DIM: &'static str = "\x1b[2m";
-    pub const ITALIC: &'static str = "\x1b[3m";
-    pub const UNDERLINE: &'static str = "\x1b[4m";
-    pub const STRIKETHROUGH: &'static str = "\x1b[9m";
+    pub const RESET: &'static str = text_styles::RESET;
+    pub const BOLD: &'static str = text_styles::BOLD;
+    pub const DIM: &'static str = text_styles::DIM;
+    pub const ITALIC: &'static str = text_styles::ITALIC;
+    pub const UNDERLINE: &'static str = text_styles::UNDERLINE;
+    pub const STRIKETHROUGH: &'static str = text_styles::STRIKETHROUGH;
+    pub const BLINK: &'static str = text_styles::BLINK;
+    pub const REVERSE: &'static str = text_styles::REVERSE;
+    pub const CONCEAL: &'static str = text_styles::CONCEAL;
 }
 
 impl StyleGroup {
-    /// Convert style group to ANSI escape sequence
-    pub fn to_ansi(&self) -> String {
-        let mut ansi = String::new();
-        
-        // Add color first
-        ansi.push_str(get_extended_color_code(&self.color));
-        
-        // Add text styles
-        if self.bold {
-            ansi.push_str(AnsiCodes::BOLD);
+    /// What a keyword match is rendered as: `replace_with` with `{}` filled in, or the match
+    pub fn replaced<'a>(replace_with: Option<&str>, matched: &'a str) -> std::borrow::Cow<'a, str> {
+        match replace_with {
+            Some(text) => text.replace("{}", matched).into(),
+            None => matched.into(),
         }
-        if self.dim {
-            ansi.push_str(AnsiCodes::DIM);
-        }
-        if self.italic {
-            ansi.push_str(AnsiCodes::ITALIC);
+    }
+    
+    /// A keyword match in `style`, led by the group's `icon` unless icons are off.
+    /// In ASCII mode `icon_fallback` leads instead; without one, only an ASCII icon is kept.
+    pub fn styled_match(style: &Style, icon: Option<&str>, icon_fallback: Option<&str>, replace_with: Option<&str>,
+                        matched: &str, icon_mode: IconMode, renderer: &dyn Renderer) -> String {
+        let matched = Self::replaced(replace_with, matched);
+        let icon = icon.map(|icon| {
+            let fallback = icon_fallback.unwrap_or(if icon.is_ascii() { icon } else { "" });
+            icon_mode.pick(icon, fallback)
+        });
+        match icon.filter(|icon| !icon.is_empty()) {
+            Some(icon) => format!("{} {}", icon, renderer.paint(style, &matched)),
+            None => renderer.paint(style, &matched),
         }
-        if self.underline {
-            ansi.push_str(AnsiCodes::UNDERLINE);
+    }
+    
+    /// Regex source for one keyword entry. Keywords with `*`/`?` are globs
+    /// (`*` -> `\w*`, `?` -> `\w`), phrases with `:` or spaces match literally
+    /// and plain words match on word boundaries; all case-insensitive.
+    pub fn keyword_regex(keyword: &str) -> String {
+        if keyword.contains('*') || keyword.contains('?') {
+            let glob: String = keyword.chars().map(|c| match c {
+                '*' => r"\w*".to_string(),
+                '?' => r"\w".to_string(),
+                c => regex::escape(&c.to_string()),
+            }).collect();
+            format!(r"(?i)\b{}\b", glob)
+        } else if keyword.contains(':') || keyword.contains(' ') {
+            format!("(?i){}", regex::escape(keyword))
+        } else {
+            format!(r"(?i)\b{}\b", regex::escape(keyword))
         }
-        if self.strikethrough {
-            ansi.push_str(AnsiCodes::STRIKETHROUGH);
+    }
+    
+    /// Text style described by this group
+    pub fn style(&self) -> Style {
+        Style {
+            color: Some(self.color.clone()),
+            bg: self.bg.clone(),
+            italic: self.italic,
+            underline: self.underline,
+            bold: self.bold,
+            dim: self.dim,
+            strikethrough: self.strikethrough,
+            blink: self.blink,
+            reverse: self.reverse,
+            conceal: self.conceal,
+            underline_style: self.underline_style,
+            underline_color: self.underline_color.clone(),
         }
-        
-        ansi
+    }
+    
+    /// Convert style group to ANSI escape sequence
+    pub fn to_ansi(&self) -> String {
+        self.style().to_ansi()
+    }
+    
+    /// `priority` as a number (0 when unset)
+    pub fn rank(&self) -> i32 {
+        self.priority.as_ref().map_or(0, Priority::rank)
     }
 }
 
 impl AutoDetectionPattern {
-    /// Convert auto-detection pattern to ANSI escape sequence
-    pub fn to_ansi(&self) -> String {
-        let mut ansi = String::new();
-        
-        // Add color first
-        ansi.push_str(get_extended_color_code(&self.color));
-        
-        // Add text styles
-        if self.bold {
-            ansi.push_str(AnsiCodes::BOLD);
+    /// Whether this pattern applies on `path` (an empty `enabled_in` means both)
+    pub fn enabled_for(&self, path: ProcessingPath) -> bool {
+        self.enabled_in.is_empty() || self.enabled_in.contains(&path)
+    }
+    
+    /// The regex to compile: `pattern` wrapped by its `anchor`, if any
+    pub fn anchored_pattern(&self) -> String {
+        match self.anchor {
+            // Detectors style capture group 1, so give a group-less pattern one first
+            Some(anchor) if !self.pattern.contains('(') => anchor.wrap(&format!("({})", self.pattern)),
+            Some(anchor) => anchor.wrap(&self.pattern),
+            None => self.pattern.clone(),
         }
-        if self.dim {
-            ansi.push_str(AnsiCodes::DIM);
+    }
+    
+    /// Text style described by this pattern
+    pub fn style(&self) -> Style {
+        Style {
+            color: Some(self.color.clone()),
+            bg: self.bg.clone(),
+            italic: self.italic,
+            underline: self.underline,
+            bold: self.bold,
+            dim: self.dim,
+            strikethrough: self.strikethrough,
+            blink: self.blink,
+            reverse: self.reverse,
+            conceal: self.conceal,
+            underline_style: self.underline_style,
+            underline_color: self.underline_color.clone(),
         }
-        if self.italic {
-            ansi.push_str(AnsiCodes::ITALIC);
+    }
+    
+    /// Convert auto-detection pattern to ANSI escape sequence
+    pub fn to_ansi(&self) -> String {
+        self.style().to_ansi()
+    }
+}
+
+impl IconMapping {
+    /// Get formatted icon with color following the spec: ":word:" -> "🔥 word"
+    /// Icon is prefixed OUTSIDE color codes to avoid ANSI wrapping issues.
+    /// `icon_fallback` replaces the icon in ASCII mode; no icon leaves just the colored word.
+    pub fn formatted_icon(&self, word: &str, icon_mode: IconMode, renderer: &dyn Renderer) -> String {
+        let icon = icon_mode.pick(&self.icon, self.icon_fallback.as_deref().unwrap_or(&self.icon));
+        let colored = renderer.paint(&Style::new().color(&self.color), word);
+        if icon.is_empty() {
+            colored
+        } else {
+            format!("{} {}", icon, colored)
         }
-        if self.underline {
-            ansi.push_str(AnsiCodes::UNDERLINE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_json_schema_is_valid_json() {
+        let schema = Theme::json_schema().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&schema).unwrap();
+        
+        let properties = &value["properties"];
+        for key in ["metadata", "filters", "auto_detection", "compilation", "defaults"] {
+            assert!(properties.get(key).is_some(), "schema missing '{}'", key);
         }
-        if self.strikethrough {
-            ansi.push_str(AnsiCodes::STRIKETHROUGH);
+        assert!(value["definitions"].get("StyleGroup").is_some());
+        assert!(value["definitions"].get("IconMapping").is_some());
+    }
+    
+    #[test]
+    fn test_canonical_yaml_is_idempotent() {
+        let yaml = r#"
+metadata: { name: "fmt", version: "1.0.0", description: "format test" }
+filters:
+  zeta:
+    styles:
+      errors: { keywords: ["panic", "ERROR", "fatal"], color: "red", bold: true }
+  alpha:
+    icon_mappings:
+      done: { icon: "✅", color: "green" }
+    styles:
+      notes: { keywords: ["note", "info"], color: "azure" }
+"#;
+        let theme: Theme = serde_yaml::from_str(yaml).unwrap();
+        let first = theme.to_canonical_yaml().unwrap();
+        
+        let reparsed: Theme = serde_yaml::from_str(&first).unwrap();
+        let second = reparsed.to_canonical_yaml().unwrap();
+        
+        assert_eq!(first, second);
+        assert!(!first.contains("appearance"));
+        assert!(first.find("alpha:").unwrap() < first.find("zeta:").unwrap());
+        let (error, fatal, panic) = (first.find("ERROR").unwrap(), first.find("fatal").unwrap(), first.find("panic").unwrap());
+        assert!(error < fatal && fatal < panic);
+        assert!(!first.contains("italic"));
+    }
+    
+    #[test]
+    fn test_appearance_from_colorfgbg() {
+        assert_eq!(Appearance::from_colorfgbg("15;0"), Some(Appearance::Dark));
+        assert_eq!(Appearance::from_colorfgbg("0;15"), Some(Appearance::Light));
+        assert_eq!(Appearance::from_colorfgbg("0;7"), Some(Appearance::Light));
+        assert_eq!(Appearance::from_colorfgbg("7;default;0"), Some(Appearance::Dark));
+        assert_eq!(Appearance::from_colorfgbg("15;8"), Some(Appearance::Dark));
+        assert_eq!(Appearance::from_colorfgbg("default;default"), None);
+        assert_eq!(Appearance::from_colorfgbg(""), None);
+        assert_eq!(Appearance::from_colorfgbg("0;200"), None);
+    }
+    
+    #[test]
+    fn test_appearance_metadata_parses() {
+        let yaml = r#"
+metadata: { name: "paper", version: "1.0.0", description: "light theme", appearance: light }
+filters: {}
+"#;
+        let theme: Theme = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(theme.metadata.appearance, Some(Appearance::Light));
+        assert_eq!(Appearance::from_arg("DARK"), Some(Appearance::Dark));
+        assert_eq!(Appearance::from_arg("sepia"), None);
+    }
+    
+    #[test]
+    fn test_style_group_delegates_to_style() {
+        let group: StyleGroup = serde_yaml::from_str(r#"{ keywords: ["ERROR"], color: "crimson", bold: true, underline: true }"#).unwrap();
+        assert_eq!(group.to_ansi(), "\x1b[38;5;196m\x1b[1m\x1b[4m");
+        assert_eq!(group.to_ansi(), Style::new().color("crimson").bold().underline().to_ansi());
+    }
+    
+    #[test]
+    fn test_blink_reverse_conceal_order() {
+        let group: StyleGroup = serde_yaml::from_str(
+            r#"{ keywords: ["secret"], color: "red", conceal: true, bold: true, reverse: true, blink: true, strikethrough: true }"#
+        ).unwrap();
+        assert_eq!(group.to_ansi(), "\x1b[38;5;9m\x1b[1m\x1b[9m\x1b[5m\x1b[7m\x1b[8m");
+        
+        let pattern: AutoDetectionPattern = serde_yaml::from_str(r#"{ pattern: "x", color: "red", reverse: true }"#).unwrap();
+        assert_eq!(pattern.to_ansi(), format!("\x1b[38;5;9m{}", AnsiCodes::REVERSE));
+    }
+    
+    #[test]
+    fn test_undercurl_style_group() {
+        let group: StyleGroup = serde_yaml::from_str(
+            r#"{ keywords: ["teh"], color: "white", underline_style: curly, underline_color: "red" }"#
+        ).unwrap();
+        assert_eq!(group.to_ansi(), "\x1b[38;5;247m\x1b[4:3m\x1b[58;5;9m");
+    }
+    
+    #[test]
+    fn test_from_yaml_str_applies_inheritance() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "inline", version: "1.0.0", description: "inline theme" }
+defaults:
+  filters:
+    logs:
+      styles:
+        errors: { keywords: ["ERROR"], color: "red" }
+filters: {}
+"#).unwrap();
+        assert_eq!(theme.metadata.name, "inline");
+        assert_eq!(theme.get_filter_keywords("logs"), vec!["ERROR"]);
+        
+        assert!(Theme::from_yaml_str("metadata: [").is_err());
+    }
+    
+    #[test]
+    fn test_filter_with_icons_only_inherits_styles() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "icons", version: "1.0.0", description: "icons only" }
+defaults:
+  filters:
+    logs:
+      icon_mappings:
+        fire: { icon: "🔥", color: "red" }
+      styles:
+        errors: { keywords: ["ERROR"], color: "red" }
+filters:
+  logs:
+    icon_mappings:
+      rocket: { icon: "🚀", color: "azure" }
+"#).unwrap();
+        let logs = &theme.filters["logs"];
+        assert_eq!(logs.icon_mappings.keys().collect::<Vec<_>>(), vec!["fire", "rocket"]);
+        assert_eq!(theme.get_filter_keywords("logs"), vec!["ERROR"]);
+    }
+    
+    #[test]
+    fn test_merge_themes() {
+        let mut base = Theme::from_yaml_str(r#"
+metadata: { name: "base", version: "1.0.0", description: "base" }
+filters:
+  logs:
+    icon_mappings:
+      fire: { icon: "🔥", color: "red" }
+    styles:
+      errors: { keywords: ["ERROR"], color: "red" }
+      info: { keywords: ["INFO"], color: "azure" }
+  todo:
+    styles:
+      urgent: { keywords: ["URGENT"], color: "orange" }
+"#).unwrap();
+        let overlay = Theme::from_yaml_str(r#"
+metadata: { name: "project", version: "2.0.0", description: "overlay" }
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR", "FATAL"], color: "crimson" }
+  deploy:
+    styles:
+      done: { keywords: ["DEPLOYED"], color: "green" }
+"#).unwrap();
+        
+        base.merge(overlay);
+        
+        assert_eq!(base.metadata.name, "project");
+        let logs = &base.filters["logs"];
+        assert_eq!(logs.styles["errors"].color, "crimson");
+        assert_eq!(logs.styles["errors"].keywords, vec!["ERROR", "FATAL"]);
+        assert_eq!(logs.styles["info"].color, "azure");
+        assert!(logs.icon_mappings.contains_key("fire"));
+        assert!(base.filters.contains_key("todo"));
+        assert!(base.filters.contains_key("deploy"));
+    }
+    
+    #[test]
+    fn test_describe_filters() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "f", version: "1.0.0", description: "filters" }
+filters:
+  logs:
+    icon_mappings:
+      fire: { icon: "🔥", color: "red" }
+    styles:
+      errors: { keywords: ["ERROR", "FATAL"], color: "red" }
+  todo:
+    styles:
+      urgent: { keywords: ["URGENT"], color: "orange" }
+"#).unwrap();
+        
+        let summary = theme.describe_filters(false);
+        assert!(summary.contains("logs (1 style groups, 1 icon mappings)"));
+        assert!(summary.contains("todo (1 style groups, 0 icon mappings)"));
+        assert!(!summary.contains("FATAL"));
+        
+        let verbose = theme.describe_filters(true);
+        assert!(verbose.contains("    errors: ERROR, FATAL"));
+    }
+    
+    #[test]
+    fn test_descriptions_round_trip() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "d", version: "1.0.0", description: "documented" }
+filters:
+  logs:
+    description: "Application log levels"
+    styles:
+      errors: { description: "Failures that need a look", keywords: ["ERROR"], color: "red" }
+      plain: { keywords: ["INFO"], color: "blue" }
+"#).unwrap();
+        
+        let reloaded = Theme::from_yaml_str(&theme.to_canonical_yaml().unwrap()).unwrap();
+        let logs = &reloaded.filters["logs"];
+        assert_eq!(logs.description.as_deref(), Some("Application log levels"));
+        assert_eq!(logs.styles["errors"].description.as_deref(), Some("Failures that need a look"));
+        assert_eq!(logs.styles["plain"].description, None);
+        
+        let verbose = reloaded.describe_filters(true);
+        assert!(verbose.contains("logs (2 style groups, 0 icon mappings) - Application log levels"));
+        assert!(verbose.contains("    errors: ERROR - Failures that need a look"));
+        assert!(verbose.contains("    plain: INFO\n"));
+    }
+    
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_theme_search_path_order() {
+        let root = env::temp_dir().join(format!("jynx-theme-path-{}", std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+        for dir in [&first, &second] {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("theme_shared.yml"), "").unwrap();
         }
+        fs::write(second.join("theme_only.yml"), "").unwrap();
+        
+        let theme_path = env::join_paths([first.as_path(), Path::new(""), second.as_path()]).unwrap();
+        let search_path = Theme::search_path_from(&[], Some(&theme_path));
+        assert_eq!(search_path[0], (first.clone(), "path".to_string()));
+        assert_eq!(search_path[1], (second.clone(), "path".to_string()));
+        assert_eq!(search_path[2].1, "XDG+");
+        assert_eq!(search_path[3].1, "local");
         
-        ansi
+        // Extra directories (`--theme-dir`) come before the environment's
+        let with_dir = Theme::search_path_from(std::slice::from_ref(&second), Some(&theme_path));
+        assert_eq!(with_dir[0], (second.clone(), "dir".to_string()));
+        assert_eq!(Theme::resolve_theme_path_in("shared", &with_dir), Some(second.join("theme_shared.yml")));
+        
+        assert_eq!(Theme::resolve_theme_path_in("shared", &search_path), Some(first.join("theme_shared.yml")));
+        assert_eq!(Theme::resolve_theme_path_in("only", &search_path), Some(second.join("theme_only.yml")));
+        assert_eq!(Theme::resolve_theme_path_in("missing", &search_path), None);
+        
+        // `theme_<name>.yml` anywhere beats a bare `<name>` file in an earlier directory
+        fs::write(first.join("plain"), "").unwrap();
+        fs::write(second.join("theme_plain.yml"), "").unwrap();
+        assert_eq!(Theme::resolve_theme_path_in("plain", &search_path), Some(second.join("theme_plain.yml")));
+        fs::remove_file(second.join("theme_plain.yml")).unwrap();
+        assert_eq!(Theme::resolve_theme_path_in("plain", &search_path), Some(first.join("plain")));
+        
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_keyword_globs() {
+        let glob = regex::Regex::new(&StyleGroup::keyword_regex("error*")).unwrap();
+        assert!(glob.is_match("build errored"));
+        assert!(glob.is_match("ERROR"));
+        assert!(!glob.is_match("terror"));
+        
+        let single = regex::Regex::new(&StyleGroup::keyword_regex("warn?")).unwrap();
+        assert!(single.is_match("warns"));
+        assert!(!single.is_match("warn"));
+        
+        // No glob chars: still escaped literally
+        let literal = regex::Regex::new(&StyleGroup::keyword_regex("a.b")).unwrap();
+        assert!(!literal.is_match("axb"));
+    }
+    
+    #[test]
+    fn test_existing_files_need_force() {
+        let root = env::temp_dir().join(format!("jynx-force-{}", std::process::id()));
+        let (local, xdg) = (root.join("local"), root.join("xdg"));
+        fs::create_dir_all(&local).unwrap();
+        fs::create_dir_all(&xdg).unwrap();
+        
+        // create: an existing <name>.yml is preserved without force
+        fs::write(local.join("mine.yml"), "keep").unwrap();
+        assert!(Theme::create_theme_in(&local, "mine", false).is_err());
+        assert_eq!(fs::read_to_string(local.join("mine.yml")).unwrap(), "keep");
+        Theme::create_theme_in(&local, "mine", true).unwrap();
+        assert_ne!(fs::read_to_string(local.join("mine.yml")).unwrap(), "keep");
+        
+        // import: an existing XDG+ theme_<name>.yml is preserved without force
+        fs::write(xdg.join("theme_mine.yml"), "installed").unwrap();
+        assert!(Theme::import_theme_in(&local, &xdg, "mine", false).is_err());
+        assert_eq!(fs::read_to_string(xdg.join("theme_mine.yml")).unwrap(), "installed");
+        
+        // export: the local copy is preserved without force, replaced with it
+        assert!(Theme::export_theme_in(&xdg, &local, "mine", false).is_err());
+        assert_ne!(fs::read_to_string(local.join("mine.yml")).unwrap(), "installed");
+        Theme::export_theme_in(&xdg, &local, "mine", true).unwrap();
+        assert_eq!(fs::read_to_string(local.join("mine.yml")).unwrap(), "installed");
+        
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_write_atomic() {
+        let dir = env::temp_dir().join(format!("jynx-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let leftovers = |dir: &Path| fs::read_dir(dir).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().contains(".tmp-"))
+            .count();
+        
+        let target = dir.join("theme_x.yml");
+        fs::write(&target, "old").unwrap();
+        write_atomic(&target, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        
+        // Renaming over a directory fails: the temp file must not be left behind
+        let blocked = dir.join("theme_dir.yml");
+        fs::create_dir_all(blocked.join("inner")).unwrap();
+        assert!(write_atomic(&blocked, b"data").is_err());
+        assert_eq!(leftovers(&dir), 0);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_min_jynx_version() {
+        assert_eq!(parse_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("2.0.0-beta"), Some((2, 0, 0)));
+        assert_eq!(parse_version("two"), None);
+        
+        let with_min = |min: &str| Theme::from_yaml_str(&format!(
+            "metadata: {{ name: \"v\", version: \"1.0.0\", description: \"d\", min_jynx_version: \"{}\" }}\nfilters: {{}}", min
+        )).unwrap();
+        
+        assert!(with_min("1.5.0").check_min_version().is_ok());
+        assert!(with_min(env!("CARGO_PKG_VERSION")).check_min_version().is_ok());
+        
+        let future = with_min("99.0.0");
+        assert!(future.check_min_version().unwrap_err().contains("requires jynx 99.0.0"));
+        assert!(future.clone().enforce_min_version(true).is_err());
+        assert!(future.enforce_min_version(false).is_ok());
+    }
+    
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_include_fragments() {
+        let dir = env::temp_dir().join(format!("jynx-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("filters")).unwrap();
+        fs::write(dir.join("main.yml"), r#"
+metadata: { name: "mono", version: "1.0.0", description: "split theme" }
+include: [filters/rust.yml]
+filters:
+  logs:
+    styles:
+      errors: { keywords: ["ERROR"], color: "crimson" }
+"#).unwrap();
+        fs::write(dir.join("filters/rust.yml"), r#"
+filters:
+  rust:
+    styles:
+      keywords: { keywords: ["fn", "impl"], color: "orange" }
+  logs:
+    styles:
+      errors: { keywords: ["ERR"], color: "blue" }
+"#).unwrap();
+        
+        let theme = Theme::load_from_file(dir.join("main.yml")).unwrap();
+        assert!(theme.include.is_empty());
+        assert_eq!(theme.filters["rust"].styles["keywords"].keywords, vec!["fn", "impl"]);
+        // The including theme wins on conflicts
+        assert_eq!(theme.filters["logs"].styles["errors"].color, "crimson");
+        
+        // A fragment including the main theme is a cycle
+        fs::write(dir.join("filters/rust.yml"), "include: [../main.yml]\n").unwrap();
+        let err = Theme::load_from_file(dir.join("main.yml")).unwrap_err().to_string();
+        assert!(err.contains("include cycle"), "{}", err);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_install_pack_from_directory() {
+        let base = std::env::temp_dir().join(format!("jynx-pack-{}", std::process::id()));
+        let (pack, target) = (base.join("pack"), base.join("themes"));
+        fs::create_dir_all(&pack).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        let theme = |name: &str| format!("metadata: {{ name: \"{}\", version: \"1.0.0\", description: \"pack\" }}\nfilters: {{}}\n", name);
+        fs::write(pack.join("theme_ocean.yml"), theme("ocean")).unwrap();
+        fs::write(pack.join("theme_forest.yml"), theme("forest")).unwrap();
+        fs::write(pack.join("theme_broken.yml"), "metadata: [").unwrap();
+        fs::write(pack.join("README.md"), "not a theme").unwrap();
+        fs::write(target.join("theme_forest.yml"), "existing").unwrap();
+        
+        let report = Theme::install_pack_in(pack.to_str().unwrap(), &target, false).unwrap();
+        assert_eq!(report.installed, vec![("ocean".to_string(), target.join("theme_ocean.yml"))]);
+        let skipped: Vec<&str> = report.skipped.iter().map(|(file, _)| file.as_str()).collect();
+        assert_eq!(skipped, vec!["theme_broken.yml", "theme_forest.yml"]);
+        assert!(report.skipped[1].1.contains("--force"));
+        assert_eq!(fs::read_to_string(target.join("theme_forest.yml")).unwrap(), "existing");
+        assert!(Theme::load_from_file(target.join("theme_ocean.yml")).is_ok());
+        
+        // --force replaces installed themes; broken ones are still refused
+        let report = Theme::install_pack_in(pack.to_str().unwrap(), &target, true).unwrap();
+        assert_eq!(report.installed.len(), 2);
+        assert_eq!(report.skipped.len(), 1);
+        
+        assert!(Theme::install_pack_in(target.join("none").to_str().unwrap(), &target, false).is_err());
+        fs::remove_dir_all(&base).unwrap();
+    }
+    
+    /// Serve each body once, in order, as a minimal HTTP/1.1 server on a free local port
+    #[cfg(feature = "remote-themes")]
+    fn serve_bodies(bodies: Vec<String>) -> (String, std::thread::JoinHandle<()>) {
+        use std::io::{BufRead, BufReader, Write};
+        
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/theme_remote.yml", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            for body in bodies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+        (url, server)
+    }
+    
+    #[test]
+    fn test_remote_theme_cache_path_is_stable() {
+        let path = Theme::remote_theme_cache_path("https://example.com/theme_x.yml", Path::new("/cache"));
+        assert_eq!(path, Path::new("/cache/remote/4d77d36bb63ac3a2.yml"));
+    }
+    
+    #[cfg(feature = "remote-themes")]
+    #[test]
+    fn test_remote_theme_cache() {
+        let cache = std::env::temp_dir().join(format!("jynx-remote-{}", std::process::id()));
+        let theme = |version: &str| format!("metadata: {{ name: \"remote\", version: \"{}\", description: \"url\" }}\nfilters: {{}}\n", version);
+        let version = |path: &Path| Theme::load_from_file(path).unwrap().metadata.version;
+        
+        // First use downloads; later uses read the cache until a refresh
+        let (url, server) = serve_bodies(vec![theme("1.0.0"), theme("2.0.0")]);
+        let path = Theme::fetch_remote_theme(&url, &cache, false).unwrap();
+        assert_eq!(path, Theme::remote_theme_cache_path(&url, &cache));
+        assert!(Theme::resolve_theme_path_in(&url, &[]).is_none());
+        assert_eq!(version(&path), "1.0.0");
+        assert_eq!(version(&Theme::fetch_remote_theme(&url, &cache, false).unwrap()), "1.0.0");
+        assert_eq!(version(&Theme::fetch_remote_theme(&url, &cache, true).unwrap()), "2.0.0");
+        server.join().unwrap();
+        
+        // Server gone: a refresh falls back to the cached copy
+        assert_eq!(version(&Theme::fetch_remote_theme(&url, &cache, true).unwrap()), "2.0.0");
+        
+        // No cached copy and nothing usable to download: an error, and nothing cached
+        let (broken, server) = serve_bodies(vec!["metadata: [".to_string()]);
+        assert!(Theme::fetch_remote_theme(&broken, &cache, false).is_err());
+        assert!(!Theme::remote_theme_cache_path(&broken, &cache).exists());
+        server.join().unwrap();
+        fs::remove_dir_all(&cache).unwrap();
+    }
+    
+    #[test]
+    fn test_find_editor_fallback_chain() {
+        let dir = std::env::temp_dir().join(format!("jynx-editor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stub-edit"), "#!/bin/sh\nexit 0\n").unwrap();
+        let path = dir.clone().into_os_string();
+        let path = Some(path.as_os_str());
+        
+        // $VISUAL wins over $EDITOR; a missing one is skipped; arguments are kept
+        let configured = [Some("missing-visual --wait".to_string()), Some("stub-edit -n +1".to_string())];
+        assert_eq!(find_editor(&configured, path).unwrap(), vec!["stub-edit", "-n", "+1"]);
+        
+        // An editor given as a path doesn't need PATH
+        let absolute = dir.join("stub-edit").display().to_string();
+        assert_eq!(find_editor(&[None, Some(absolute.clone())], None).unwrap(), vec![absolute]);
+        
+        // Unset variables fall through to the built-in list
+        fs::write(dir.join("vi"), "").unwrap();
+        assert_eq!(find_editor(&[None, None], path).unwrap(), vec!["vi"]);
+        fs::remove_file(dir.join("vi")).unwrap();
+        
+        let err = find_editor(&[Some("missing-visual".to_string()), None], path).unwrap_err();
+        assert!(err.starts_with("No editor found (tried missing-visual, nano, vi, vim"), "{}", err);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_theme_vars() {
+        let yaml = r#"
+metadata: { name: "vars", version: "1.0.0", description: "{{ label }} theme" }
+vars: { accent: "orchid", label: "accented" }
+filters:
+  logs:
+    icon_mappings:
+      hot: { icon: "{{icon}}", color: "{{accent}}" }
+    styles:
+      errors: { keywords: ["ERROR"], color: "{{accent}}" }
+"#;
+        let raw: Theme = serde_yaml::from_str(yaml).unwrap();
+        let mut overrides = BTreeMap::new();
+        
+        // No default and no override: reported by name
+        let err = raw.clone().substitute_vars(&overrides).unwrap_err().to_string();
+        assert!(err.contains("undefined var(s) icon"), "{}", err);
+        
+        // Defaults from `vars:`, with the section itself left alone
+        overrides.insert("icon".to_string(), "🔥".to_string());
+        let theme = raw.clone().substitute_vars(&overrides).unwrap();
+        assert_eq!(theme.filters["logs"].styles["errors"].color, "orchid");
+        assert_eq!(theme.filters["logs"].icon_mappings["hot"].icon, "🔥");
+        assert_eq!(theme.metadata.description, "accented theme");
+        assert_eq!(theme.vars["label"], "accented");
+        
+        // An override beats the default
+        overrides.insert("accent".to_string(), "amber".to_string());
+        let theme = raw.substitute_vars(&overrides).unwrap();
+        assert_eq!(theme.filters["logs"].styles["errors"].color, "amber");
+        assert_eq!(theme.filters["logs"].icon_mappings["hot"].color, "amber");
+        
+        // Overrides only apply to the load they are passed to
+        assert_eq!(Theme::from_yaml_str_with_vars(yaml, &overrides).unwrap().filters["logs"].styles["errors"].color, "amber");
+        assert!(Theme::from_yaml_str(yaml).is_err());
+    }
+    
+    #[test]
+    fn test_contrast_report() {
+        let theme = Theme::from_yaml_str(r#"
+metadata: { name: "c", version: "1.0.0", description: "contrast" }
+filters:
+  logs:
+    styles:
+      good: { keywords: ["OK"], color: "white", bg: "navy" }
+      bad: { keywords: ["MEH"], color: "yellow", bg: "white" }
+      plain: { keywords: ["X"], color: "red" }
+"#).unwrap();
+        let report = theme.contrast_report();
+        assert_eq!(report.len(), 2);
+        let bad = report.iter().find(|check| check.group == "bad").unwrap();
+        let good = report.iter().find(|check| check.group == "good").unwrap();
+        assert!(!bad.passes());
+        assert!(good.passes());
+        assert!(theme.filters["logs"].styles["good"].to_ansi().contains("\x1B[48;5;"));
     }
 }
-
-impl IconMapping {
-    /// Get formatted icon with color following the spec: ":word:" -> "🔥 word"
-    /// Icon is prefixed OUTSIDE color codes to avoid ANSI wrapping issues
-    pub fn formatted_icon(&self, word: &str) -> String {
-        format!("{} {}{}{}", 
-            self.icon,
-            get_extended_color_code(&self.color), 
-            word,
-            AnsiCodes::RESET
-        )
-    }
-}
\ No newline at end of file
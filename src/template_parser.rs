@@ -5,13 +5,26 @@
 //! - Balanced parentheses: handles (), [], % signs, function calls
 //! - Independent parsing: each template processed separately
 //! - Graceful fallback: invalid patterns remain as literal text
+//! - Embedded ANSI: a reset inside the content (e.g. from colored input)
+//!   re-applies the template color, so the whole span stays colored
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use crate::extended_colors::get_extended_color_code;
+use crate::text_styles::{Ansi, Renderer, Style};
 
 /// Template parser for %c:colorname(text) patterns
+#[derive(Clone)]
 pub struct ColorTemplateParser {
     /// No-color mode flag
     no_color: bool,
+    /// Semantic names (e.g. theme `palette:`) resolved to palette color names
+    palette: HashMap<String, String>,
+    /// Output format templates are rendered in (ANSI unless set)
+    renderer: Arc<dyn Renderer>,
+    /// Opening markup per template color name, rendered on first use
+    opens: Arc<RwLock<HashMap<String, Arc<str>>>>,
 }
 
 impl ColorTemplateParser {
@@ -19,11 +32,68 @@ impl ColorTemplateParser {
     pub fn new(no_color: bool) -> Self {
         Self {
             no_color,
+            palette: HashMap::new(),
+            renderer: Arc::new(Ansi::current()),
+            opens: Arc::default(),
         }
     }
     
+    /// Render templates with `renderer` instead of ANSI escapes
+    pub fn with_renderer(mut self, renderer: Arc<dyn Renderer>) -> Self {
+        self.renderer = renderer;
+        self.opens = Arc::default();
+        self
+    }
+    
+    /// Add semantic color names (name -> palette color) checked before the built-in palette
+    /// Entries pointing at unknown colors are ignored
+    pub fn with_palette<'a, I>(mut self, palette: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a String, &'a String)>,
+    {
+        for (name, color) in palette {
+            if !get_extended_color_code(color).is_empty() {
+                self.palette.insert(name.clone(), color.clone());
+            }
+        }
+        self.opens = Arc::default();
+        self
+    }
+    
+    /// Resolve a template color name: palette first, then built-in colors
+    fn resolve_color<'s>(&'s self, color_name: &'s str) -> Option<&'s str> {
+        match self.palette.get(color_name) {
+            Some(color) => Some(color),
+            None => (!get_extended_color_code(color_name).is_empty()).then_some(color_name),
+        }
+    }
+    
+    /// Markup opening a template color, rendered once per name and then reused
+    /// (unknown names give `None` and aren't kept, so odd input can't grow the cache)
+    fn color_open(&self, color_name: &str) -> Option<Arc<str>> {
+        if let Some(open) = self.opens.read().ok().and_then(|opens| opens.get(color_name).cloned()) {
+            return Some(open);
+        }
+        let open: Arc<str> = self.renderer.color_open(&Style::new().color(self.resolve_color(color_name)?)).into();
+        if let Ok(mut opens) = self.opens.write() {
+            opens.insert(color_name.to_string(), open.clone());
+        }
+        Some(open)
+    }
+    
     /// Process text with color templates
-    pub fn process(&self, text: &str) -> String {
+    ///
+    /// Text without any `%c:` comes back borrowed, so the common template-free
+    /// line costs a substring search and no allocation. For markup renderers
+    /// the text is escaped first, once, so template content isn't escaped again.
+    pub fn process<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.renderer.escape(text) {
+            Cow::Borrowed(text) => self.process_escaped(text),
+            Cow::Owned(escaped) => Cow::Owned(self.process_escaped(&escaped).into_owned()),
+        }
+    }
+    
+    fn process_escaped<'a>(&self, text: &'a str) -> Cow<'a, str> {
         if self.no_color {
             // In no-color mode, strip templates to plain text
             self.strip_templates(text)
@@ -34,132 +104,114 @@ impl ColorTemplateParser {
     }
     
     /// Apply color templates, converting %c:colorname(text) to colored text
-    fn apply_templates(&self, text: &str) -> String {
+    fn apply_templates<'a>(&self, text: &'a str) -> Cow<'a, str> {
         self.process_templates(text, false)
     }
     
     /// Strip all templates to plain text only
-    fn strip_templates(&self, text: &str) -> String {
+    ///
+    /// Never panics and never returns more bytes than `text` has, for any UTF-8
+    /// input (the invariant `fuzz/fuzz_targets/template_parser.rs` checks)
+    pub fn strip_templates<'a>(&self, text: &'a str) -> Cow<'a, str> {
         self.process_templates(text, true)
     }
     
     /// Process templates with unified logic for both color and no-color modes
-    fn process_templates(&self, text: &str, strip_only: bool) -> String {
-        let mut result = String::new();
-        let chars: Vec<char> = text.chars().collect();
-        let mut i = 0;
+    ///
+    /// Scans byte offsets into `text` directly: a line without `%c:` is returned
+    /// as-is, and literal runs between templates are copied as slices
+    fn process_templates<'a>(&self, text: &'a str, strip_only: bool) -> Cow<'a, str> {
+        if !text.contains("%c:") {
+            return Cow::Borrowed(text);
+        }
+        
+        let mut result = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut search = 0;
         
-        while i < chars.len() {
-            // Try to match a template at current position
-            if let Some((template_end, processed_content)) = self.try_parse_template_at(&chars, i, strip_only) {
-                result.push_str(&processed_content);
-                i = template_end;
-            } else {
-                // No template match, add current character
-                result.push(chars[i]);
-                i += 1;
+        while let Some(offset) = text[search..].find("%c:") {
+            let start = search + offset;
+            // Try to match a template at this `%c:`; otherwise it stays literal
+            match self.try_parse_template_at(text, start, strip_only) {
+                Some((template_end, processed_content)) => {
+                    result.push_str(&text[copied..start]);
+                    result.push_str(&processed_content);
+                    copied = template_end;
+                    search = template_end;
+                },
+                None => search = start + 1, // `%` is a single byte
             }
         }
         
-        result
+        result.push_str(&text[copied..]);
+        Cow::Owned(result)
     }
     
-    /// Try to parse a template starting at the given position
-    /// Returns (end_position, processed_content) on success
-    fn try_parse_template_at(&self, chars: &[char], start: usize, strip_only: bool) -> Option<(usize, String)> {
-        // Check if we have enough characters for a minimal template
-        if start + 4 >= chars.len() {
-            return None;
-        }
+    /// Try to parse a template starting at byte offset `start`
+    /// Returns (end_offset, processed_content) on success
+    fn try_parse_template_at(&self, text: &str, start: usize, strip_only: bool) -> Option<(usize, String)> {
+        // `%c:` + a color char + `()`: anything shorter is literal
+        let rest = text.get(start..).filter(|rest| rest.len() >= 6)?;
         
         // Check for %c: prefix
-        if chars[start] != '%' || chars[start + 1] != 'c' || chars[start + 2] != ':' {
-            return None;
-        }
-        
-        // Find the opening parenthesis and extract color name
-        let mut color_name = String::new();
-        let mut i = start + 3;
-        
-        // Extract color name until we find '('
-        while i < chars.len() {
-            let ch = chars[i];
-            if ch == '(' {
-                break;
-            } else if ch.is_alphabetic() || ch == '_' || ch.is_numeric() {
-                color_name.push(ch);
-            } else {
-                // Invalid character in color name
-                return None;
-            }
-            i += 1;
-        }
+        let rest = rest.strip_prefix("%c:")?;
         
-        // Check if we found the opening parenthesis
-        if i >= chars.len() || chars[i] != '(' {
+        // Extract color name until the first character that can't be part of one,
+        // which must be the opening parenthesis
+        let name_len = rest.find(|ch: char| !(ch.is_alphabetic() || ch == '_' || ch.is_numeric()))?;
+        let (color_name, after) = rest.split_at(name_len);
+        if color_name.is_empty() || !after.starts_with('(') {
             return None;
         }
         
         // Find balanced content
-        let content_start = i + 1; // After the '('
-        let (content_end, content) = self.find_balanced_content_from_chars(chars, content_start)?;
+        let content_start = start + 3 + name_len + 1; // After the '('
+        let (content_end, content) = self.find_balanced_content(text, content_start)?;
         
         // Process the template
         if strip_only {
-            Some((content_end + 1, content)) // +1 to skip the closing ')'
+            Some((content_end + 1, content.to_string())) // +1 to skip the closing ')'
         } else {
-            // Get color code
-            let color_code = get_extended_color_code(&color_name);
-            if color_code.is_empty() {
-                // Unknown color, return None to keep as literal
-                return None;
+            // Unknown color, return None to keep as literal
+            let open = self.color_open(color_name)?;
+            if open.is_empty() {
+                return Some((content_end + 1, content.to_string()));
             }
             
-            let colored_text = format!("{}{}\x1B[0m", color_code, content);
+            // A reset already in the content would end the span early: re-apply the color
+            // after it (markup spans nest instead). A span crossing newlines is closed
+            // and reopened on each line, so every line stays self-contained once the text is split
+            let reset = self.renderer.reset();
+            let content = match self.renderer.is_markup() {
+                true => Cow::Borrowed(content),
+                false => Cow::Owned(content.replace(&reset, &format!("{}{}", reset, open))),
+            };
+            let colored_text = format!("{}{}{}", open, content, reset)
+                .replace('\n', &format!("{}\n{}", reset, open));
             Some((content_end + 1, colored_text)) // +1 to skip the closing ')'
         }
     }
     
-    /// Find balanced parentheses content from character array
-    fn find_balanced_content_from_chars(&self, chars: &[char], start: usize) -> Option<(usize, String)> {
-        if start >= chars.len() {
-            return None;
-        }
-        
+    /// Find the content up to the `)` balancing an already-consumed `(`
+    /// Returns (offset of that `)`, content) or `None` if it's never closed
+    fn find_balanced_content<'a>(&self, text: &'a str, start: usize) -> Option<(usize, &'a str)> {
         let mut depth = 1; // We start after the opening (
-        let mut content = String::new();
-        let mut pos = start;
         
-        while pos < chars.len() && depth > 0 {
-            let ch = chars[pos];
-            
+        for (pos, ch) in text.get(start..)?.char_indices() {
             match ch {
-                '(' => {
-                    depth += 1;
-                    content.push(ch);
-                },
+                '(' => depth += 1,
                 ')' => {
                     depth -= 1;
-                    if depth > 0 {
-                        content.push(ch);
+                    if depth == 0 {
+                        return Some((start + pos, &text[start..start + pos]));
                     }
-                    // If depth == 0, we found our closing ), don't include it
                 },
-                _ => {
-                    content.push(ch);
-                }
+                _ => {},
             }
-            
-            pos += 1;
         }
         
-        // Check if we found balanced parentheses
-        if depth == 0 {
-            Some((pos - 1, content)) // pos-1 because we incremented after finding the )
-        } else {
-            // Unbalanced parentheses, template is invalid
-            None
-        }
+        // Unbalanced parentheses, template is invalid
+        None
     }
     
 }
@@ -247,6 +299,76 @@ mod tests {
         assert_eq!(result, "%c:red(unbalanced"); // Should remain unchanged
     }
     
+    #[test]
+    fn test_palette_color() {
+        let palette: HashMap<String, String> = [("accent".to_string(), "crimson".to_string())].into();
+        let parser = ColorTemplateParser::new(false).with_palette(&palette);
+        let result = parser.process("%c:accent(boom)");
+        assert_eq!(result, "\x1B[38;5;196mboom\x1B[0m");
+    }
+    
+    #[test]
+    fn test_palette_falls_back_to_builtin() {
+        let palette: HashMap<String, String> = [
+            ("accent".to_string(), "crimson".to_string()),
+            ("broken".to_string(), "nosuchcolor".to_string()),
+        ].into();
+        let parser = ColorTemplateParser::new(false).with_palette(&palette);
+        
+        // No palette entry: built-in name still works
+        assert_eq!(parser.process("%c:green(ok)"), "\x1B[38;5;10mok\x1B[0m");
+        // Palette entry with an unknown color: treated as unknown
+        assert_eq!(parser.process("%c:broken(x)"), "%c:broken(x)");
+        // Without a palette the semantic name is unknown and stays literal
+        assert_eq!(ColorTemplateParser::new(false).process("%c:accent(boom)"), "%c:accent(boom)");
+    }
+    
+    #[test]
+    fn test_multiline_span() {
+        let parser = ColorTemplateParser::new(false);
+        let red = get_extended_color_code("red");
+        let result = parser.process("%c:red(one\ntwo)");
+        assert_eq!(result, format!("{}one\x1B[0m\n{}two\x1B[0m", red, red));
+    }
+    
+    #[test]
+    fn test_truncated_templates_stay_literal() {
+        for parser in [ColorTemplateParser::new(false), ColorTemplateParser::new(true)] {
+            for text in ["%", "%c", "%c:", "%c:r", "%c:r(", "%c:(", "x%c:r(", "%c:é(", "%c:red(", "(%c:red"] {
+                assert_eq!(parser.process(text), text);
+            }
+        }
+        // A template needs a color name, even when only stripping
+        assert_eq!(ColorTemplateParser::new(true).process("%c:()"), "%c:()");
+    }
+    
+    #[test]
+    fn test_shortest_template_at_line_end() {
+        let strip = ColorTemplateParser::new(true);
+        assert_eq!(strip.process("%c:r()"), "");
+        assert_eq!(strip.process("ab%c:r()"), "ab");
+        assert_eq!(strip.process("ab%c:r(x)"), "abx");
+        
+        let color = ColorTemplateParser::new(false);
+        let red = get_extended_color_code("red");
+        assert_eq!(color.process("end %c:red()"), format!("end {}\x1B[0m", red));
+        assert_eq!(color.process("end %c:red(x)"), format!("end {}x\x1B[0m", red));
+        assert_eq!(color.process("%c:red(a)%c:red(b)"), format!("{r}a\x1B[0m{r}b\x1B[0m", r = red));
+    }
+    
+    #[test]
+    fn test_embedded_reset_keeps_color() {
+        let parser = ColorTemplateParser::new(false);
+        let red = get_extended_color_code("red");
+        let bold = "\x1B[1m";
+        let result = parser.process(&format!("%c:red(a {}b\x1B[0m c)", bold)).into_owned();
+        assert_eq!(result, format!("{r}a {bold}b\x1B[0m{r} c\x1B[0m", r = red));
+        
+        // Stripping leaves the embedded codes alone
+        let stripped = ColorTemplateParser::new(true).process(&format!("%c:red({}b\x1B[0m)", bold)).into_owned();
+        assert_eq!(stripped, format!("{}b\x1B[0m", bold));
+    }
+    
     #[test]
     fn test_no_nesting() {
         let parser = ColorTemplateParser::new(true);
@@ -254,4 +376,55 @@ mod tests {
         let result = parser.process("%c:red(text %c:blue(inner))");
         assert_eq!(result, "text %c:blue(inner)");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_multibyte_text_around_templates() {
+        let parser = ColorTemplateParser::new(true);
+        assert_eq!(parser.process("漢字 %c:red(ünï(cö)dé) → %c:nope(x) 🚀"), "漢字 ünï(cö)dé → x 🚀");
+        assert_eq!(parser.process("%c:красный(x)"), "x");
+        assert_eq!(parser.process("no templates: ünïcödé"), "no templates: ünïcödé");
+    }
+    
+    #[test]
+    fn test_template_free_text_is_borrowed() {
+        for parser in [ColorTemplateParser::new(false), ColorTemplateParser::new(true)] {
+            assert!(matches!(parser.process("plain 100% text (no templates)"), Cow::Borrowed(_)));
+            assert!(matches!(parser.process("%c:red(x)"), Cow::Owned(_)));
+        }
+    }
+    
+    #[test]
+    fn test_color_opens_are_cached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        /// ANSI, counting how often a style is rendered
+        struct Counting(AtomicUsize);
+        impl Renderer for Counting {
+            fn color_open(&self, style: &Style) -> String {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Ansi::default().color_open(style)
+            }
+            fn reset(&self) -> String {
+                Ansi::default().reset()
+            }
+        }
+        
+        let counting = Arc::new(Counting(AtomicUsize::new(0)));
+        let parser = ColorTemplateParser::new(false).with_renderer(counting.clone());
+        let line = "%c:red(a) %c:blue(b) %c:red(c) %c:nope(d)";
+        let first = parser.process(line).into_owned();
+        assert_eq!(parser.process(line), first);
+        assert_eq!(first, ColorTemplateParser::new(false).process(line));
+        assert_eq!(counting.0.load(Ordering::Relaxed), 2);
+    }
+    
+    #[test]
+    fn test_markup_escapes_literal_text_once() {
+        use crate::text_styles::Html;
+        
+        let parser = ColorTemplateParser::new(false).with_renderer(Arc::new(Html));
+        let red = Html.color_open(&Style::new().color("red"));
+        assert_eq!(parser.process("if a < b && c > d: %c:red(x<y)"), format!("if a &lt; b &amp;&amp; c &gt; d: {}x&lt;y</span>", red));
+        assert_eq!(ColorTemplateParser::new(true).with_renderer(Arc::new(Html)).process("%c:red(x<y)"), "x&lt;y");
+    }
+}
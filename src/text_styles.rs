@@ -1,7 +1,9 @@
 // Text Style Support - Italic, Underline, and Combined Formatting
 // Extends color highlighting with rich typography for semantic emphasis
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use crate::extended_colors::{ansi_code_to_index, bg_code_for, bold_mode, bright_variant, color_code_for, get_color_rgb, palette, BoldMode, Palette};
 
 // ANSI escape codes for text formatting
 pub const ITALIC: &str = "\x1B[3m";
@@ -9,32 +11,87 @@ pub const UNDERLINE: &str = "\x1B[4m";
 pub const BOLD: &str = "\x1B[1m";
 pub const DIM: &str = "\x1B[2m";
 pub const STRIKETHROUGH: &str = "\x1B[9m";
+pub const BLINK: &str = "\x1B[5m";
+pub const REVERSE: &str = "\x1B[7m";
+pub const CONCEAL: &str = "\x1B[8m";
 pub const RESET: &str = "\x1B[0m";
 
 // Combined style definitions
-pub struct TextStyle {
+
+/// Extended underline shapes (`\x1B[4:Nm`), supported by kitty, wezterm and others
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "themes", derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema))]
+#[cfg_attr(feature = "themes", serde(rename_all = "lowercase"))]
+pub enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// SGR sub-parameter for `4:N`
+    pub fn sgr_param(&self) -> u8 {
+        match self {
+            Self::Single => 1,
+            Self::Double => 2,
+            Self::Curly => 3,
+            Self::Dotted => 4,
+            Self::Dashed => 5,
+        }
+    }
+    
+    /// CSS `text-decoration-style` equivalent
+    pub fn css_value(&self) -> &'static str {
+        match self {
+            Self::Single => "solid",
+            Self::Double => "double",
+            Self::Curly => "wavy",
+            Self::Dotted => "dotted",
+            Self::Dashed => "dashed",
+        }
+    }
+}
+
+/// Composable text style: foreground/background color plus text attributes
+///
+/// Colors are jynx palette names (see `extended_colors`). Built with chained
+/// methods, e.g. `Style::new().color("crimson").bold().underline()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "themes", derive(serde::Deserialize, serde::Serialize))]
+pub struct Style {
     pub color: Option<String>,
+    pub bg: Option<String>,
     pub italic: bool,
     pub underline: bool,
     pub bold: bool,
     pub dim: bool,
     pub strikethrough: bool,
+    pub blink: bool,
+    pub reverse: bool,
+    pub conceal: bool,
+    /// Underline shape; implies underline when set
+    pub underline_style: Option<UnderlineStyle>,
+    /// Underline color (palette name), independent of the text color
+    pub underline_color: Option<String>,
 }
 
-impl TextStyle {
+/// Previous name of `Style`, kept for compatibility
+pub type TextStyle = Style;
+
+impl Style {
     pub fn new() -> Self {
-        TextStyle {
-            color: None,
-            italic: false,
-            underline: false,
-            bold: false,
-            dim: false,
-            strikethrough: false,
-        }
+        Self::default()
     }
     
-    pub fn color(mut self, color: String) -> Self {
-        self.color = Some(color);
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+    
+    pub fn bg(mut self, color: impl Into<String>) -> Self {
+        self.bg = Some(color.into());
         self
     }
     
@@ -48,6 +105,21 @@ impl TextStyle {
         self
     }
     
+    pub fn underline_style(mut self, style: UnderlineStyle) -> Self {
+        self.underline_style = Some(style);
+        self
+    }
+    
+    pub fn underline_color(mut self, color: impl Into<String>) -> Self {
+        self.underline_color = Some(color.into());
+        self
+    }
+    
+    /// True when any underline is requested (plain or shaped)
+    pub fn has_underline(&self) -> bool {
+        self.underline || self.underline_style.is_some()
+    }
+    
     pub fn bold(mut self) -> Self {
         self.bold = true;
         self
@@ -63,42 +135,483 @@ impl TextStyle {
         self
     }
     
-    // Generate ANSI escape sequence for this style
+    pub fn blink(mut self) -> Self {
+        self.blink = true;
+        self
+    }
+    
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+    
+    pub fn conceal(mut self) -> Self {
+        self.conceal = true;
+        self
+    }
+    
+    /// True when the style emits nothing (no color, no attributes)
+    pub fn is_plain(&self) -> bool {
+        self.to_ansi().is_empty()
+    }
+    
+    /// ANSI escape sequence opening this style: color, background, then attributes,
+    /// at the process-wide palette and bold mode (`set_palette`, `set_bold_mode`)
     pub fn to_ansi(&self) -> String {
-        let mut codes = Vec::new();
+        self.to_ansi_with(palette(), bold_mode())
+    }
+    
+    /// `to_ansi` with named colors at `palette` depth and `bold` rendered per
+    /// `bold_mode`: in `Bright` mode a bold standard color becomes its bright
+    /// variant instead of emitting the weight
+    pub fn to_ansi_with(&self, palette: Palette, bold_mode: BoldMode) -> String {
+        let mut ansi = String::new();
         
-        if self.bold {
-            codes.push("1".to_string());
+        let bright = match (bold_mode, &self.color) {
+            (BoldMode::Bright, Some(color)) if self.bold => bright_variant(color),
+            _ => None,
+        };
+        if let Some(color) = bright.or(self.color.as_deref()) {
+            ansi.push_str(color_code_for(color, palette));
+        }
+        if let Some(bg) = &self.bg {
+            ansi.push_str(&bg_code_for(bg, palette));
+        }
+        
+        if self.bold && bright.is_none() {
+            ansi.push_str(BOLD);
         }
         if self.dim {
-            codes.push("2".to_string());
+            ansi.push_str(DIM);
         }
         if self.italic {
-            codes.push("3".to_string());
+            ansi.push_str(ITALIC);
         }
-        if self.underline {
-            codes.push("4".to_string());
+        match self.underline_style {
+            Some(shape) => ansi.push_str(&format!("\x1B[4:{}m", shape.sgr_param())),
+            None if self.underline => ansi.push_str(UNDERLINE),
+            None => {}
+        }
+        if self.has_underline() {
+            // SGR 58 takes an indexed or an RGB color, at the palette's depth
+            let underline_color = self.underline_color.as_deref().and_then(|color| match palette {
+                Palette::TrueColor => get_color_rgb(color).map(|(r, g, b)| format!("\x1B[58;2;{};{};{}m", r, g, b)),
+                _ => ansi_code_to_index(color_code_for(color, palette)).map(|index| format!("\x1B[58;5;{}m", index)),
+            });
+            if let Some(code) = underline_color {
+                ansi.push_str(&code);
+            }
         }
         if self.strikethrough {
-            codes.push("9".to_string());
+            ansi.push_str(STRIKETHROUGH);
+        }
+        if self.blink {
+            ansi.push_str(BLINK);
+        }
+        if self.reverse {
+            ansi.push_str(REVERSE);
+        }
+        if self.conceal {
+            ansi.push_str(CONCEAL);
         }
         
-        // Add color code if specified
-        if let Some(color) = &self.color {
-            if let Some(color_code) = get_color_code(color) {
-                codes.push(format!("38;5;{}", color_code));
+        ansi
+    }
+    
+    /// Wrap text in this style, closing with a reset (plain styles add nothing)
+    pub fn paint(&self, text: &str) -> String {
+        Ansi::current().paint(self, text)
+    }
+    
+    /// CSS declarations equivalent to this style
+    pub fn to_css(&self) -> String {
+        let mut css = Vec::new();
+        
+        // Reverse video swaps foreground and background
+        let (fg, bg) = if self.reverse { (&self.bg, &self.color) } else { (&self.color, &self.bg) };
+        if let Some((r, g, b)) = fg.as_deref().and_then(get_color_rgb) {
+            css.push(format!("color:#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if let Some((r, g, b)) = bg.as_deref().and_then(get_color_rgb) {
+            css.push(format!("background-color:#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if self.dim {
+            css.push("opacity:0.6".to_string());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_string());
+        }
+        
+        if self.conceal {
+            css.push("visibility:hidden".to_string());
+        }
+        
+        let decorations: Vec<&str> = [(self.has_underline(), "underline"), (self.strikethrough, "line-through"), (self.blink, "blink")]
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, decoration)| *decoration)
+            .collect();
+        if !decorations.is_empty() {
+            css.push(format!("text-decoration:{}", decorations.join(" ")));
+        }
+        if self.has_underline() {
+            if let Some(shape) = self.underline_style {
+                css.push(format!("text-decoration-style:{}", shape.css_value()));
+            }
+            if let Some((r, g, b)) = self.underline_color.as_deref().and_then(get_color_rgb) {
+                css.push(format!("text-decoration-color:#{:02x}{:02x}{:02x}", r, g, b));
             }
         }
         
-        if codes.is_empty() {
-            String::new()
+        css.join(";")
+    }
+    
+    /// Wrap HTML-escaped text in a styled `<span>`
+    pub fn to_html(&self, text: &str) -> String {
+        Html.paint(self, text)
+    }
+    
+    /// Pango markup attributes equivalent to this style (for GTK labels and notifications)
+    pub fn to_pango(&self) -> String {
+        let mut attrs = Vec::new();
+        
+        let (fg, bg) = if self.reverse { (&self.bg, &self.color) } else { (&self.color, &self.bg) };
+        if let Some(rgb) = fg.as_deref().and_then(get_color_rgb) {
+            attrs.push(format!("foreground=\"{}\"", hex(rgb)));
+        }
+        if let Some(rgb) = bg.as_deref().and_then(get_color_rgb) {
+            attrs.push(format!("background=\"{}\"", hex(rgb)));
+        }
+        if self.bold {
+            attrs.push("weight=\"bold\"".to_string());
+        }
+        if self.dim {
+            attrs.push("alpha=\"60%\"".to_string());
+        }
+        if self.italic {
+            attrs.push("style=\"italic\"".to_string());
+        }
+        if self.has_underline() {
+            // Pango has no dotted or dashed underline; its wavy one is `error`
+            let shape = match self.underline_style {
+                Some(UnderlineStyle::Double) => "double",
+                Some(UnderlineStyle::Curly) => "error",
+                _ => "single",
+            };
+            attrs.push(format!("underline=\"{}\"", shape));
+            if let Some(rgb) = self.underline_color.as_deref().and_then(get_color_rgb) {
+                attrs.push(format!("underline_color=\"{}\"", hex(rgb)));
+            }
+        }
+        if self.strikethrough {
+            attrs.push("strikethrough=\"true\"".to_string());
+        }
+        
+        attrs.join(" ")
+    }
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// An output format for styled text: how a style is opened and closed, and how
+/// literal text is escaped
+///
+/// `paint` is built on the three, so a new format only says what its markup is.
+/// The highlighting pipeline takes a `&dyn Renderer` wherever it styles text.
+pub trait Renderer: Send + Sync {
+    /// Markup opening `style` (empty when the format can't express any of it)
+    fn color_open(&self, style: &Style) -> String;
+    
+    /// Markup closing a span opened by `color_open`
+    fn reset(&self) -> String;
+    
+    /// `text` made safe to place between markup
+    fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(text)
+    }
+    
+    /// Escaped `text` in `style`, closed again; a style with no markup adds nothing
+    fn paint(&self, style: &Style, text: &str) -> String {
+        let open = self.color_open(style);
+        let text = self.escape(text);
+        if open.is_empty() {
+            text.into_owned()
         } else {
-            format!("\x1B[{}m", codes.join(";"))
+            format!("{}{}{}", open, text, self.reset())
+        }
+    }
+    
+    /// Already rendered `text` in `style`: a span closed inside it (nested
+    /// styling) re-opens `style` after its reset, so the whole text stays styled
+    fn paint_over(&self, style: &Style, text: &str) -> String {
+        let open = self.color_open(style);
+        if open.is_empty() {
+            return text.to_string();
+        }
+        let reset = self.reset();
+        format!("{}{}{}", open, text.replace(&reset, &format!("{}{}", reset, open)), reset)
+    }
+    
+    /// True for tag markup (`<span>`) that nests, rather than SGR state: such
+    /// output has its literal text escaped, and passes over already rendered
+    /// text must step around tags and entities
+    fn is_markup(&self) -> bool {
+        false
+    }
+}
+
+/// `paint_over` for nesting markup: the span wraps the text, inner spans stay as they are
+fn nest(renderer: &dyn Renderer, style: &Style, text: &str) -> String {
+    match renderer.color_open(style) {
+        open if open.is_empty() => text.to_string(),
+        open => format!("{}{}{}", open, text, renderer.reset()),
+    }
+}
+
+/// `renderer` for text it has already escaped: `escape` leaves text as it is,
+/// so a line escaped once up front isn't escaped again as it is styled
+#[cfg(feature = "themes")]
+pub(crate) struct PreEscaped(pub(crate) std::sync::Arc<dyn Renderer>);
+
+#[cfg(feature = "themes")]
+impl Renderer for PreEscaped {
+    fn color_open(&self, style: &Style) -> String {
+        self.0.color_open(style)
+    }
+    
+    fn reset(&self) -> String {
+        self.0.reset()
+    }
+    
+    fn paint_over(&self, style: &Style, text: &str) -> String {
+        self.0.paint_over(style, text)
+    }
+    
+    fn is_markup(&self) -> bool {
+        self.0.is_markup()
+    }
+}
+
+/// SGR escape sequences for terminals, with named colors at `palette` depth and
+/// `bold` rendered per `bold_mode` (by default 256 colors and a heavier weight)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ansi {
+    pub palette: Palette,
+    pub bold_mode: BoldMode,
+}
+
+impl Ansi {
+    pub fn new(palette: Palette, bold_mode: BoldMode) -> Self {
+        Self { palette, bold_mode }
+    }
+    
+    /// At the process-wide palette and bold mode (`set_palette`, `set_bold_mode`)
+    pub fn current() -> Self {
+        Self::new(palette(), bold_mode())
+    }
+}
+
+impl Renderer for Ansi {
+    fn color_open(&self, style: &Style) -> String {
+        style.to_ansi_with(self.palette, self.bold_mode)
+    }
+    
+    fn reset(&self) -> String {
+        RESET.to_string()
+    }
+}
+
+/// `<span style="...">` with inline CSS
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Html;
+
+impl Renderer for Html {
+    fn color_open(&self, style: &Style) -> String {
+        match style.to_css() {
+            css if css.is_empty() => String::new(),
+            css => format!("<span style=\"{}\">", css),
+        }
+    }
+    
+    fn reset(&self) -> String {
+        "</span>".to_string()
+    }
+    
+    fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        escape_text(text)
+    }
+    
+    fn paint_over(&self, style: &Style, text: &str) -> String {
+        nest(self, style, text)
+    }
+    
+    fn is_markup(&self) -> bool {
+        true
+    }
+}
+
+/// Pango markup `<span>` attributes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pango;
+
+impl Renderer for Pango {
+    fn color_open(&self, style: &Style) -> String {
+        match style.to_pango() {
+            attrs if attrs.is_empty() => String::new(),
+            attrs => format!("<span {}>", attrs),
+        }
+    }
+    
+    fn reset(&self) -> String {
+        "</span>".to_string()
+    }
+    
+    // Pango markup is XML, so the HTML entities apply
+    fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        escape_text(text)
+    }
+    
+    fn paint_over(&self, style: &Style, text: &str) -> String {
+        nest(self, style, text)
+    }
+    
+    fn is_markup(&self) -> bool {
+        true
+    }
+}
+
+/// Escape text placed between tags (quotes only matter inside attributes);
+/// borrowed when there is nothing to escape
+fn escape_text(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(ch),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Escape text for inclusion in HTML
+pub fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
         }
     }
+    escaped
+}
+
+/// SGR attributes in effect, followed across escape sequences so a pass can
+/// tell styled text from plain: `\x1b[1;31m` styles, and `\x1b[22;39m` ends
+/// the styling just like `\x1b[0m` does. Markup tags count as well: text
+/// inside an open `<span>` is styled until its `</span>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SgrState {
+    attributes: u16,
+    // Open markup spans
+    spans: u16,
+}
+
+impl SgrState {
+    const FOREGROUND: u16 = 1 << 0;
+    const BACKGROUND: u16 = 1 << 1;
+    const INTENSITY: u16 = 1 << 2;
+    const ITALIC: u16 = 1 << 3;
+    const UNDERLINE: u16 = 1 << 4;
+    const BLINK: u16 = 1 << 5;
+    const REVERSE: u16 = 1 << 6;
+    const CONCEAL: u16 = 1 << 7;
+    const STRIKETHROUGH: u16 = 1 << 8;
+    const OVERLINE: u16 = 1 << 9;
+    const UNDERLINE_COLOR: u16 = 1 << 10;
+    
+    /// Follow one escape sequence or tag; anything else (an OSC, an entity) changes nothing
+    pub(crate) fn apply(&mut self, sequence: &str) {
+        if let Some(tag) = sequence.strip_prefix('<') {
+            match tag.starts_with('/') {
+                true => self.spans = self.spans.saturating_sub(1),
+                false if !tag.ends_with("/>") => self.spans += 1,
+                false => {},
+            }
+            return;
+        }
+        let Some(params) = sequence.strip_prefix("\x1b[").and_then(|rest| rest.strip_suffix('m')) else {
+            return;
+        };
+        let params: Vec<&str> = params.split(';').collect();
+        let mut i = 0;
+        while i < params.len() {
+            // `:` sub-parameters (`4:3`, `38:5:n`) belong to the parameter before them;
+            // an empty parameter is 0, so `ESC[m` and `ESC[;1m` reset
+            let mut parts = params[i].split(':');
+            let code: u16 = parts.next().and_then(|code| code.parse().ok()).unwrap_or(0);
+            let sub = parts.next();
+            i += 1;
+            let (on, off) = match code {
+                0 => (0, u16::MAX),
+                1 | 2 => (Self::INTENSITY, 0),
+                3 => (Self::ITALIC, 0),
+                4 if sub == Some("0") => (0, Self::UNDERLINE),
+                4 | 21 => (Self::UNDERLINE, 0),
+                5 | 6 => (Self::BLINK, 0),
+                7 => (Self::REVERSE, 0),
+                8 => (Self::CONCEAL, 0),
+                9 => (Self::STRIKETHROUGH, 0),
+                22 => (0, Self::INTENSITY),
+                23 => (0, Self::ITALIC),
+                24 => (0, Self::UNDERLINE),
+                25 => (0, Self::BLINK),
+                27 => (0, Self::REVERSE),
+                28 => (0, Self::CONCEAL),
+                29 => (0, Self::STRIKETHROUGH),
+                30..=38 | 90..=97 => (Self::FOREGROUND, 0),
+                39 => (0, Self::FOREGROUND),
+                40..=48 | 100..=107 => (Self::BACKGROUND, 0),
+                49 => (0, Self::BACKGROUND),
+                53 => (Self::OVERLINE, 0),
+                55 => (0, Self::OVERLINE),
+                58 => (Self::UNDERLINE_COLOR, 0),
+                59 => (0, Self::UNDERLINE_COLOR),
+                _ => (0, 0),
+            };
+            self.attributes = (self.attributes & !off) | on;
+            // `38;5;n` and `38;2;r;g;b` carry their color in the parameters that follow
+            if matches!(code, 38 | 48 | 58) && sub.is_none() {
+                i += match params.get(i).copied() {
+                    Some("5") => 2,
+                    Some("2") => 4,
+                    _ => 1,
+                };
+            }
+        }
+    }
+    
+    /// True while any attribute or color is in effect
+    pub(crate) fn is_styled(&self) -> bool {
+        self.attributes != 0 || self.spans != 0
+    }
 }
 
 // Enhanced configuration for YAML themes with style support
+#[cfg(feature = "themes")]
 #[derive(Debug, serde::Deserialize)]
 pub struct StyledColorConfig {
     // Traditional color-only configuration (backward compatible)
@@ -109,6 +622,7 @@ pub struct StyledColorConfig {
     pub styles: Option<HashMap<String, StyleDefinition>>,
 }
 
+#[cfg(feature = "themes")]
 #[derive(Debug, serde::Deserialize)]
 pub struct StyleDefinition {
     pub keywords: Vec<String>,
@@ -120,15 +634,18 @@ pub struct StyleDefinition {
     pub strikethrough: Option<bool>,
 }
 
+#[cfg(feature = "themes")]
 impl StyleDefinition {
     pub fn to_text_style(&self) -> TextStyle {
         TextStyle {
             color: self.color.clone(),
+            bg: None,
             italic: self.italic.unwrap_or(false),
             underline: self.underline.unwrap_or(false),
             bold: self.bold.unwrap_or(false),
             dim: self.dim.unwrap_or(false),
             strikethrough: self.strikethrough.unwrap_or(false),
+            ..TextStyle::default()
         }
     }
 }
@@ -138,55 +655,35 @@ pub fn get_semantic_styles() -> HashMap<&'static str, TextStyle> {
     let mut styles = HashMap::new();
     
     // Documentation styles
-    styles.insert("emphasis", TextStyle::new().italic().color("amber".to_string()));
-    styles.insert("strong", TextStyle::new().bold().color("crimson".to_string()));
-    styles.insert("code", TextStyle::new().color("azure".to_string()));
-    styles.insert("link", TextStyle::new().underline().color("royal".to_string()));
-    styles.insert("quote", TextStyle::new().italic().dim().color("slate".to_string()));
+    styles.insert("emphasis", TextStyle::new().italic().color("amber"));
+    styles.insert("strong", TextStyle::new().bold().color("crimson"));
+    styles.insert("code", TextStyle::new().color("azure"));
+    styles.insert("link", TextStyle::new().underline().color("royal"));
+    styles.insert("quote", TextStyle::new().italic().dim().color("slate"));
     
     // Status styles
-    styles.insert("deprecated", TextStyle::new().strikethrough().dim().color("rust".to_string()));
-    styles.insert("new_feature", TextStyle::new().bold().underline().color("emerald".to_string()));
-    styles.insert("breaking_change", TextStyle::new().bold().color("crimson".to_string()));
-    styles.insert("experimental", TextStyle::new().italic().color("orchid".to_string()));
+    styles.insert("deprecated", TextStyle::new().strikethrough().dim().color("rust"));
+    styles.insert("new_feature", TextStyle::new().bold().underline().color("emerald"));
+    styles.insert("breaking_change", TextStyle::new().bold().color("crimson"));
+    styles.insert("experimental", TextStyle::new().italic().color("orchid"));
     
     // Priority styles
-    styles.insert("urgent", TextStyle::new().bold().underline().color("crimson".to_string()));
-    styles.insert("important", TextStyle::new().bold().color("amber".to_string()));
-    styles.insert("note", TextStyle::new().italic().color("azure".to_string()));
-    styles.insert("tip", TextStyle::new().color("mint".to_string()));
+    styles.insert("urgent", TextStyle::new().bold().underline().color("crimson"));
+    styles.insert("important", TextStyle::new().bold().color("amber"));
+    styles.insert("note", TextStyle::new().italic().color("azure"));
+    styles.insert("tip", TextStyle::new().color("mint"));
     
     // Technical styles
-    styles.insert("variable", TextStyle::new().italic().color("turquoise".to_string()));
-    styles.insert("function", TextStyle::new().color("violet".to_string()));
-    styles.insert("class", TextStyle::new().bold().color("royal".to_string()));
-    styles.insert("keyword", TextStyle::new().bold().color("magenta".to_string()));
-    styles.insert("string", TextStyle::new().color("forest".to_string()));
-    styles.insert("comment", TextStyle::new().italic().dim().color("slate".to_string()));
+    styles.insert("variable", TextStyle::new().italic().color("turquoise"));
+    styles.insert("function", TextStyle::new().color("violet"));
+    styles.insert("class", TextStyle::new().bold().color("royal"));
+    styles.insert("keyword", TextStyle::new().bold().color("magenta"));
+    styles.insert("string", TextStyle::new().color("forest"));
+    styles.insert("comment", TextStyle::new().italic().dim().color("slate"));
     
     styles
 }
 
-// Helper function to get color code (references extended_colors.rs)
-fn get_color_code(color: &str) -> Option<u8> {
-    // This would reference the extended color palette
-    match color {
-        "crimson" => Some(196),
-        "amber" => Some(220),
-        "azure" => Some(33),
-        "royal" => Some(21),
-        "emerald" => Some(34),
-        "orchid" => Some(170),
-        "mint" => Some(121),
-        "turquoise" => Some(45),
-        "violet" => Some(129),
-        "forest" => Some(22),
-        "slate" => Some(244),
-        "rust" => Some(166),
-        _ => None,
-    }
-}
-
 // Example YAML configuration with style support
 pub const EXAMPLE_STYLED_THEME: &str = r#"
 filters:
@@ -369,4 +866,138 @@ fn apply_code_styles(content: &str) -> String {
     }
     
     result
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_composed_style_order() {
+        let style = Style::new().color("crimson").bg("navy").bold().italic().underline();
+        assert_eq!(style.to_ansi(), "\x1B[38;5;196m\x1B[48;5;17m\x1B[1m\x1B[3m\x1B[4m");
+    }
+    
+    #[test]
+    fn test_bold_modes() {
+        let style = Style::new().color("red").bold();
+        assert_eq!(style.to_ansi_with(Palette::Ansi256, BoldMode::Weight), "\x1B[38;5;9m\x1B[1m");
+        assert_eq!(style.to_ansi_with(Palette::Ansi256, BoldMode::Bright), "\x1B[91m");
+        
+        // Only the 16 standard colors have a bright variant; others keep the weight
+        assert_eq!(Style::new().color("crimson").bold().to_ansi_with(Palette::Ansi256, BoldMode::Bright), "\x1B[38;5;196m\x1B[1m");
+        assert_eq!(Style::new().color("red").to_ansi_with(Palette::Ansi256, BoldMode::Bright), "\x1B[38;5;9m");
+    }
+    
+    #[test]
+    fn test_sgr_state() {
+        let styled_after = |sequences: &[&str]| {
+            let mut state = SgrState::default();
+            sequences.iter().for_each(|sequence| state.apply(sequence));
+            state.is_styled()
+        };
+        assert!(styled_after(&["\x1b[1;31m"]));
+        assert!(!styled_after(&["\x1b[1;31m", "\x1b[22;39m"]));
+        assert!(!styled_after(&["\x1b[31;0m"]));
+        assert!(!styled_after(&["\x1b[4m", "\x1b[4:0m"]));
+        assert!(styled_after(&["\x1b[4:3m", "\x1b[39m"]));
+        
+        // Extended color arguments aren't codes of their own (`5` is not blink, `1` not bold)
+        assert!(!styled_after(&["\x1b[38;5;1m", "\x1b[39m"]));
+        assert!(!styled_after(&["\x1b[48;2;1;2;3m", "\x1b[49m"]));
+        assert!(!styled_after(&["\x1b[32m", "\x1b]8;;https://example.com\x1b\\", "\x1b[m"]));
+        
+        // Markup spans are styled until closed; entities change nothing
+        assert!(styled_after(&["<span style=\"color:#ff0000\">", "<span a=\"1\">", "</span>"]));
+        assert!(!styled_after(&["<span foreground=\"#ff0000\">", "&amp;", "</span>"]));
+    }
+    
+    #[test]
+    fn test_blink_reverse_conceal() {
+        let style = Style::new().color("red").blink().reverse().conceal();
+        assert_eq!(style.to_ansi(), "\x1B[38;5;9m\x1B[5m\x1B[7m\x1B[8m");
+        assert_eq!(Style::new().color("red").bg("white2").reverse().to_css(), "color:#ffffff;background-color:#ff0000");
+    }
+    
+    #[test]
+    fn test_extended_underline() {
+        let curly = Style::new().underline_style(UnderlineStyle::Curly).underline_color("red");
+        assert_eq!(curly.to_ansi(), "\x1B[4:3m\x1B[58;5;9m");
+        
+        let double = Style::new().color("azure").underline().underline_style(UnderlineStyle::Double);
+        assert_eq!(double.to_ansi(), "\x1B[38;5;33m\x1B[4:2m");
+        
+        // Plain underline is unchanged; underline color alone does nothing
+        assert_eq!(Style::new().underline().underline_color("crimson").to_ansi(), "\x1B[4m\x1B[58;5;196m");
+        assert_eq!(Style::new().underline_color("crimson").to_ansi(), "");
+        
+        // The underline color follows the palette depth
+        let crimson = Style::new().underline().underline_color("crimson");
+        assert_eq!(Ansi::new(Palette::Ansi256, BoldMode::Weight).color_open(&crimson), "\x1B[4m\x1B[58;5;196m");
+        assert_eq!(Ansi::new(Palette::Ansi16, BoldMode::Weight).color_open(&crimson), "\x1B[4m\x1B[58;5;9m");
+        assert_eq!(Ansi::new(Palette::TrueColor, BoldMode::Weight).color_open(&crimson), "\x1B[4m\x1B[58;2;255;0;0m");
+        
+        assert_eq!(curly.to_css(), "text-decoration:underline;text-decoration-style:wavy;text-decoration-color:#ff0000");
+    }
+    
+    #[test]
+    fn test_basic_color_background() {
+        assert_eq!(Style::new().bg("yellow").to_ansi(), "\x1B[43m");
+    }
+    
+    #[test]
+    fn test_paint_resets() {
+        let painted = Style::new().color("emerald").paint("ok");
+        assert_eq!(painted, "\x1B[38;5;34mok\x1B[0m");
+        
+        // Plain style and unknown colors emit no escapes (and no stray reset)
+        assert_eq!(Style::new().paint("ok"), "ok");
+        assert_eq!(Style::new().color("nosuchcolor").paint("ok"), "ok");
+        assert!(Style::new().is_plain());
+    }
+    
+    #[test]
+    fn test_to_html() {
+        let html = Style::new().color("red").bold().strikethrough().underline().to_html("a < b");
+        assert_eq!(html, "<span style=\"color:#ff0000;font-weight:bold;text-decoration:underline line-through\">a &lt; b</span>");
+        assert_eq!(Style::new().to_html("x & y"), "x &amp; y");
+    }
+    
+    #[test]
+    fn test_renderers() {
+        let style = Style::new().color("red").bold().underline_style(UnderlineStyle::Curly);
+        
+        assert_eq!(Ansi::default().color_open(&style), style.to_ansi());
+        assert_eq!(Ansi::default().reset(), "\x1B[0m");
+        assert_eq!(Ansi::default().paint(&style, "a<b"), format!("{}a<b\x1B[0m", style.to_ansi()));
+        
+        assert_eq!(Html.paint(&style, "a<b"), "<span style=\"color:#ff0000;font-weight:bold;text-decoration:underline;text-decoration-style:wavy\">a&lt;b</span>");
+        
+        assert_eq!(Pango.color_open(&style), "<span foreground=\"#ff0000\" weight=\"bold\" underline=\"error\">");
+        assert_eq!(Pango.paint(&style, "a<b"), "<span foreground=\"#ff0000\" weight=\"bold\" underline=\"error\">a&lt;b</span>");
+        
+        // No markup for a plain style, but text is still escaped
+        for renderer in [&Ansi::default() as &dyn Renderer, &Html, &Pango] {
+            assert_eq!(renderer.color_open(&Style::new()), "");
+        }
+        assert_eq!(Pango.paint(&Style::new(), "x & y"), "x &amp; y");
+        assert_eq!(Ansi::default().paint(&Style::new(), "x & y"), "x & y");
+    }
+    
+    #[test]
+    fn test_paint_over_nests_markup() {
+        let (outer, inner) = (Style::new().color("red"), Style::new().bold());
+        
+        // SGR re-opens the outer style after the inner reset
+        let ansi = Ansi::default();
+        let painted = ansi.paint_over(&outer, &format!("{} b", ansi.paint(&inner, "x")));
+        assert_eq!(painted, format!("{o}{i}x\x1B[0m{o} b\x1B[0m", o = outer.to_ansi(), i = inner.to_ansi()));
+        
+        // Markup nests: one opening tag per closing tag, inner span inside the outer one
+        for renderer in [&Html as &dyn Renderer, &Pango] {
+            let painted = renderer.paint_over(&outer, &format!("{} b", renderer.paint(&inner, "x")));
+            assert_eq!(painted, format!("{}{}x</span> b</span>", renderer.color_open(&outer), renderer.color_open(&inner)));
+            assert_eq!(painted.matches("<span").count(), painted.matches("</span>").count());
+        }
+    }
+}